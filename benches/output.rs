@@ -0,0 +1,62 @@
+//! Writing a large colored render out, character by character and all at
+//! once, run with `cargo bench`.
+
+use asciidraw::{render, ColorMode, RenderOptions};
+use image::{DynamicImage, Rgb, RgbImage};
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 5;
+
+/// Times `write` over a fresh file each iteration; the file isn't buffered,
+/// like an unlocked stdout piped somewhere.
+fn bench<F>(name: &str, text: &str, write: F)
+where
+    F: Fn(&mut File, &str),
+{
+    let path = env::temp_dir().join("asciidraw-output-bench.txt");
+
+    let mut total = Duration::default();
+    for _ in 0..ITERATIONS {
+        let mut file = File::create(&path).unwrap();
+        let start = Instant::now();
+        write(&mut file, text);
+        total += start.elapsed();
+        assert_eq!(fs::metadata(&path).unwrap().len(), text.len() as u64);
+    }
+    fs::remove_file(&path).unwrap();
+
+    println!(
+        "{:<24}{:>10.1} ms",
+        name,
+        total.as_secs_f64() * 1000.0 / ITERATIONS as f64
+    );
+}
+
+fn main() {
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(1600, 1200, |x, y| {
+        Rgb([(x / 7) as u8, (y / 5) as u8, ((x + y) / 11) as u8])
+    }));
+    let opts = RenderOptions {
+        color: ColorMode::TrueColor,
+        ..RenderOptions::default()
+    };
+
+    let start = Instant::now();
+    let text = render(&img, &opts);
+    println!(
+        "{:<24}{:>10.1} ms ({} bytes)",
+        "render",
+        start.elapsed().as_secs_f64() * 1000.0,
+        text.len()
+    );
+
+    bench("per character", &text, |file, text| {
+        text.chars().for_each(|ch| write!(file, "{}", ch).unwrap())
+    });
+    bench("single write", &text, |file, text| {
+        file.write_all(text.as_bytes()).unwrap()
+    });
+}
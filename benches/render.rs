@@ -0,0 +1,136 @@
+//! Renders of the images in `benches/images` with every rule, charset and
+//! dither, run with `cargo bench --bench render`; the text is rendered into
+//! a reused `String`, leaving I/O out of the measurements.
+//!
+//! To check a change for regressions, save a baseline before it with
+//! `cargo bench --bench render -- --save-baseline before`, then compare with
+//! `cargo bench --bench render -- --baseline before`.
+
+use asciidraw::{
+    render_into, AdaptiveKernel, Charset, Dither, GlyphMap, OnOffRule, RenderOptions, RuleExpr,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use image::DynamicImage;
+use std::path::Path;
+
+fn bundled(file: &str) -> DynamicImage {
+    image::open(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("benches")
+            .join(file),
+    )
+    .unwrap()
+}
+
+fn images() -> Vec<(&'static str, DynamicImage)> {
+    vec![
+        ("photo", bundled("images/photo.png")),
+        ("lineart", bundled("images/lineart.png")),
+    ]
+}
+
+/// Benchmarks every option of `options` on every image, in a group named
+/// `group`.
+fn bench_options(c: &mut Criterion, group: &str, options: &[(String, RenderOptions)]) {
+    let images = images();
+    let mut group = c.benchmark_group(group);
+    let mut out = String::new();
+
+    for (name, opts) in options {
+        for (image, img) in &images {
+            group.bench_with_input(BenchmarkId::new(name, image), img, |b, img| {
+                b.iter(|| {
+                    out.clear();
+                    render_into(black_box(img), opts, &mut out);
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn rules(c: &mut Criterion) {
+    let rules = [
+        OnOffRule::PxThreshold(384),
+        OnOffRule::InvertedPxThreshold(384),
+        OnOffRule::Border(30, 2),
+        OnOffRule::LumaThreshold(128),
+        OnOffRule::Otsu,
+        OnOffRule::Adaptive {
+            window: 15,
+            c: 5,
+            kernel: AdaptiveKernel::Mean,
+        },
+        OnOffRule::Adaptive {
+            window: 15,
+            c: 5,
+            kernel: AdaptiveKernel::Gaussian,
+        },
+        OnOffRule::Sobel(100),
+        OnOffRule::Canny(50, 100),
+    ];
+    let options: Vec<_> = rules
+        .iter()
+        .map(|&rule| {
+            let opts = RenderOptions {
+                rule: RuleExpr::from(rule),
+                ..RenderOptions::default()
+            };
+            (rule.to_string(), opts)
+        })
+        .collect();
+
+    bench_options(c, "rule", &options);
+}
+
+fn charsets(c: &mut Criterion) {
+    let glyphs =
+        GlyphMap::from_file(Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/glyphs.toml"))
+            .unwrap();
+    let charsets = [
+        "braille".parse().unwrap(),
+        "ascii".parse().unwrap(),
+        Charset::HalfBlock,
+        Charset::Quadrant,
+        Charset::Sextant,
+        Charset::Glyphs(glyphs),
+    ];
+    let options: Vec<_> = charsets
+        .iter()
+        .map(|charset| {
+            let opts = RenderOptions {
+                charset: charset.clone(),
+                ..RenderOptions::default()
+            };
+            (charset.to_string(), opts)
+        })
+        .collect();
+
+    bench_options(c, "charset", &options);
+}
+
+fn dithers(c: &mut Criterion) {
+    let dithers = [
+        Dither::None,
+        Dither::FloydSteinberg,
+        Dither::Bayer(2),
+        Dither::Bayer(4),
+        Dither::Bayer(8),
+    ];
+    let options: Vec<_> = dithers
+        .iter()
+        .map(|&dither| {
+            let opts = RenderOptions {
+                dither,
+                ..RenderOptions::default()
+            };
+            (dither.to_string(), opts)
+        })
+        .collect();
+
+    bench_options(c, "dither", &options);
+}
+
+criterion_group!(benches, rules, charsets, dithers);
+criterion_main!(benches);
@@ -0,0 +1,32 @@
+//! The luma threshold, pixel by pixel and vectorized; run with
+//! `cargo bench --features simd --bench threshold`, without the feature both
+//! take the scalar path.
+
+use asciidraw::{threshold_luma, threshold_luma_scalar};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use image::{GrayImage, Luma};
+
+fn photo() -> GrayImage {
+    let mut seed = 0x2545_f491_u32;
+    GrayImage::from_fn(4000, 3000, |x, y| {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+
+        Luma([((x / 16 + y / 12 + seed % 32) % 256) as u8])
+    })
+}
+
+fn threshold(c: &mut Criterion) {
+    let luma = photo();
+
+    let mut group = c.benchmark_group("luma threshold");
+    group.bench_function("scalar", |b| {
+        b.iter(|| threshold_luma_scalar(black_box(&luma), 128))
+    });
+    group.bench_function("simd", |b| b.iter(|| threshold_luma(black_box(&luma), 128)));
+    group.finish();
+}
+
+criterion_group!(benches, threshold);
+criterion_main!(benches);
@@ -0,0 +1,59 @@
+/* Generated by cbindgen from src/ffi.rs, don't edit by hand. */
+
+#ifndef ASCII_IMAGE_H
+#define ASCII_IMAGE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum AsciiImageStatus {
+  ASCII_IMAGE_STATUS_OK = 0,
+  // `data`, `opts` or `out` is `NULL`.
+  ASCII_IMAGE_STATUS_NULL_ARGUMENT,
+  // An option isn't valid UTF-8, or doesn't parse.
+  ASCII_IMAGE_STATUS_INVALID_OPTION,
+  // The data isn't an image in a format the `image` crate reads.
+  ASCII_IMAGE_STATUS_INVALID_IMAGE,
+  // The text has a NUL byte, or the render panicked.
+  ASCII_IMAGE_STATUS_INTERNAL,
+} AsciiImageStatus;
+
+// The options of a render; the strings are parsed like the flags of the
+// command line, and are left to the defaults when `NULL`, so a zeroed
+// struct renders with the defaults.
+typedef struct RenderOptions {
+  const char *rule;
+  const char *size;
+  const char *charset;
+  const char *color;
+  const char *dither;
+  const char *edges;
+  const char *format;
+  float contrast;
+  bool invert;
+} RenderOptions;
+
+// Renders the image encoded in the `len` bytes at `data` with `opts`, and
+// points `out` at the text, to be freed with [`ascii_image_free`]; `out`
+// is left alone on errors. PNG output falls back to plain text.
+//
+// # Safety
+//
+// `data` must point to `len` readable bytes, the strings of `opts` must be
+// `NULL` or NUL terminated, and `out` must be writable.
+AsciiImageStatus ascii_image_render(const uint8_t *data,
+                                    size_t len,
+                                    const struct RenderOptions *opts,
+                                    char **out);
+
+// Frees a text of [`ascii_image_render`]; `NULL` is ignored.
+//
+// # Safety
+//
+// `text` must come from [`ascii_image_render`], and not be freed already.
+void ascii_image_free(char *text);
+
+#endif  /* ASCII_IMAGE_H */
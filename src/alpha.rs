@@ -0,0 +1,65 @@
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use std::borrow::Cow;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// How the alpha channel of transparent images is handled.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum AlphaPolicy {
+    /// Use the color channels as they are, whatever the alpha.
+    #[default]
+    Ignore,
+    /// Composite the image over a white background.
+    PremultiplyWhite,
+    /// Composite the image over a black background.
+    PremultiplyBlack,
+    /// Pixels that are mostly transparent are always off.
+    TreatAsOff,
+}
+
+impl AlphaPolicy {
+    pub fn apply<'a>(&self, img: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        match self {
+            AlphaPolicy::Ignore | AlphaPolicy::TreatAsOff => Cow::Borrowed(img),
+            AlphaPolicy::PremultiplyWhite => Cow::Owned(composite(img, 255)),
+            AlphaPolicy::PremultiplyBlack => Cow::Owned(composite(img, 0)),
+        }
+    }
+
+    /// Whether the pixel at (`x`, `y`) must be off regardless of the rule.
+    pub(crate) fn forces_off(&self, img: &DynamicImage, x: u32, y: u32) -> bool {
+        *self == AlphaPolicy::TreatAsOff && img.get_pixel(x, y)[3] < 128
+    }
+}
+
+fn composite(img: &DynamicImage, background: u8) -> DynamicImage {
+    let (width, height) = img.dimensions();
+
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+        let px = img.get_pixel(x, y);
+        let a = px[3] as u32;
+        let blend = |c: u8| ((c as u32 * a + background as u32 * (255 - a)) / 255) as u8;
+
+        Rgba([blend(px[0]), blend(px[1]), blend(px[2]), 255])
+    }))
+}
+
+#[derive(Error, Debug)]
+pub enum AlphaPolicyParseError {
+    #[error("unknown alpha policy `{0}`")]
+    UnknownAlphaPolicy(String),
+}
+
+impl FromStr for AlphaPolicy {
+    type Err = AlphaPolicyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ignore" => Ok(AlphaPolicy::Ignore),
+            "premultiply-white" => Ok(AlphaPolicy::PremultiplyWhite),
+            "premultiply-black" => Ok(AlphaPolicy::PremultiplyBlack),
+            "treat-as-off" => Ok(AlphaPolicy::TreatAsOff),
+            _ => Err(AlphaPolicyParseError::UnknownAlphaPolicy(s.into())),
+        }
+    }
+}
@@ -0,0 +1,361 @@
+use crate::screen::{Anchor, Screen};
+use crate::{render_cells, RenderOptions};
+use image::gif::GifDecoder;
+use image::{AnimationDecoder, DynamicImage, ImageResult};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Delay used for the frames that don't specify one, as browsers do.
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// A frame of an animation, and how long it stays on screen.
+pub struct Frame {
+    pub image: DynamicImage,
+    pub delay: Duration,
+}
+
+/// Decodes every frame of a GIF, composited over the previous ones.
+pub fn decode_gif<R: Read>(reader: R) -> ImageResult<Vec<Frame>> {
+    let decoder = GifDecoder::new(reader)?;
+
+    decoder
+        .into_frames()
+        .map(|frame| {
+            let frame = frame?;
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay = match Duration::from_millis(numer as u64) / denom.max(1) {
+                d if d == Duration::from_millis(0) => DEFAULT_DELAY,
+                d => d,
+            };
+
+            Ok(Frame {
+                image: DynamicImage::ImageRgba8(frame.into_buffer()),
+                delay,
+            })
+        })
+        .collect()
+}
+
+/// A range of frames, `start..end` with `end` left out; either end can be
+/// left out, for the first or past the last frame. A lone index is the
+/// frame at that index alone.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrameRange {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+impl FrameRange {
+    /// The frame at `index` alone.
+    pub fn single(index: usize) -> Self {
+        FrameRange {
+            start: index,
+            end: Some(index + 1),
+        }
+    }
+}
+
+/// Formats the range the way it is parsed.
+impl fmt::Display for FrameRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..", self.start)?;
+        match self.end {
+            Some(end) => write!(f, "{}", end),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum FrameRangeParseError {
+    #[error("invalid frame range `{0}`, expected `start..end` or an index")]
+    InvalidFrameRange(String),
+    #[error("empty frame range `{0}`")]
+    EmptyFrameRange(String),
+}
+
+impl FromStr for FrameRange {
+    type Err = FrameRangeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || FrameRangeParseError::InvalidFrameRange(s.into());
+        let (start, end) = match s.split_once("..") {
+            Some(range) => range,
+            None => return s.parse().map(FrameRange::single).map_err(|_| invalid()),
+        };
+        let start = match start {
+            "" => 0,
+            start => start.parse().map_err(|_| invalid())?,
+        };
+        let end = match end {
+            "" => None,
+            end => Some(end.parse().map_err(|_| invalid())?),
+        };
+        if end.is_some_and(|end| end <= start) {
+            return Err(FrameRangeParseError::EmptyFrameRange(s.into()));
+        }
+
+        Ok(FrameRange { start, end })
+    }
+}
+
+/// Which frames of an animation are rendered: every `every`th frame of
+/// `range`, starting with its first one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FrameSelection {
+    pub range: FrameRange,
+    pub every: usize,
+}
+
+impl Default for FrameSelection {
+    fn default() -> Self {
+        FrameSelection {
+            range: FrameRange::default(),
+            every: 1,
+        }
+    }
+}
+
+impl FrameSelection {
+    /// How many frames are selected out of `count`.
+    pub fn count(&self, count: usize) -> usize {
+        let end = self.range.end.map_or(count, |end| end.min(count));
+        end.saturating_sub(self.range.start)
+            .div_ceil(self.every.max(1))
+    }
+
+    /// The selected frames of `frames`, as they are decoded; a frame stays
+    /// on screen for the frames left out after it too, for the animation to
+    /// keep its pace. The frames past the range aren't decoded at all.
+    pub fn select<I, E>(self, frames: I) -> Selected<I>
+    where
+        I: Iterator<Item = Result<Frame, E>>,
+    {
+        Selected {
+            frames,
+            selection: self,
+            index: 0,
+            pending: None,
+        }
+    }
+}
+
+/// The iterator of [`FrameSelection::select`].
+pub struct Selected<I> {
+    frames: I,
+    selection: FrameSelection,
+    /// The index of the next frame of `frames`.
+    index: usize,
+    /// The last selected frame, held back until the next one to add up the
+    /// delays of the frames left out in between.
+    pending: Option<Frame>,
+}
+
+impl<I, E> Iterator for Selected<I>
+where
+    I: Iterator<Item = Result<Frame, E>>,
+{
+    type Item = Result<Frame, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let FrameRange { start, end } = self.selection.range;
+        loop {
+            let frame = match end.is_some_and(|end| self.index >= end) {
+                true => None,
+                false => self.frames.next(),
+            };
+            let frame = match frame {
+                Some(Ok(frame)) => frame,
+                Some(Err(err)) => return Some(Err(err)),
+                None => return self.pending.take().map(Ok),
+            };
+
+            let index = self.index;
+            self.index += 1;
+            if index < start {
+                continue;
+            }
+            if (index - start).is_multiple_of(self.selection.every.max(1)) {
+                if let Some(selected) = self.pending.replace(frame) {
+                    return Some(Ok(selected));
+                }
+            } else if let Some(selected) = &mut self.pending {
+                selected.delay += frame.delay;
+            }
+        }
+    }
+}
+
+/// How an animation is played back in the terminal.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Playback {
+    /// Start over after the last frame, until interrupted.
+    pub looping: bool,
+    /// Never show more than this many frames per second.
+    pub fps_cap: Option<f32>,
+    /// Draw every frame in one synchronized update, see
+    /// [`detect_synchronized_output`](crate::detect_synchronized_output).
+    pub synchronized: bool,
+}
+
+impl Playback {
+    /// How long `frame` stays on screen.
+    fn delay(&self, frame: &Frame) -> Duration {
+        match self.fps_cap {
+            Some(fps) => frame.delay.max(Duration::from_secs_f64(1.0 / fps as f64)),
+            None => frame.delay,
+        }
+    }
+
+    /// Renders every frame, then plays them in place, rewriting the cells
+    /// that changed since the previous frame.
+    pub fn play<W: Write>(
+        &self,
+        frames: &[Frame],
+        opts: &RenderOptions,
+        out: &mut W,
+    ) -> io::Result<()> {
+        let rendered: Vec<_> = frames
+            .iter()
+            .map(|f| render_cells(&f.image, opts))
+            .collect();
+        if rendered.is_empty() {
+            return Ok(());
+        }
+
+        let mut deadline = Instant::now();
+        let mut screen = Screen::new(opts.color, Anchor::Inline, self.synchronized);
+        loop {
+            for (frame, cells) in frames.iter().zip(&rendered) {
+                screen.draw(out, cells.clone())?;
+
+                deadline += self.delay(frame);
+                thread::sleep(deadline.saturating_duration_since(Instant::now()));
+            }
+
+            if !self.looping {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Plays frames as they are decoded, rendering them on the fly. Frames
+    /// that are already late, or that would go over the frame rate cap, are
+    /// dropped to keep up with the clock; `open` is called again to start
+    /// over when looping.
+    pub fn stream<W, I, F>(&self, mut open: F, opts: &RenderOptions, out: &mut W) -> io::Result<()>
+    where
+        W: Write,
+        I: Iterator<Item = io::Result<Frame>>,
+        F: FnMut() -> io::Result<I>,
+    {
+        let min_interval = self
+            .fps_cap
+            .map(|fps| Duration::from_secs_f64(1.0 / fps as f64))
+            .unwrap_or_default();
+
+        let mut screen = Screen::new(opts.color, Anchor::Inline, self.synchronized);
+        loop {
+            // the clock starts with the first decoded frame
+            let mut deadline: Option<Instant> = None;
+            let mut last_shown: Option<Instant> = None;
+            for frame in open()? {
+                let frame = frame?;
+                let shown_at = deadline.unwrap_or_else(Instant::now);
+                let end = shown_at + frame.delay;
+                deadline = Some(end);
+
+                let now = Instant::now();
+                let capped = last_shown.is_some_and(|t| shown_at < t + min_interval);
+                if now > end || capped {
+                    continue;
+                }
+
+                screen.draw(out, render_cells(&frame.image, opts))?;
+                last_shown = Some(shown_at);
+
+                thread::sleep(end.saturating_duration_since(Instant::now()));
+            }
+
+            if !self.looping {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+    use std::convert::Infallible;
+
+    /// The index and the delay in milliseconds of the selected frames of
+    /// ten frames of 10ms, the index being the luma of the frame.
+    fn select(selection: FrameSelection) -> Vec<(u8, u128)> {
+        let frames = (0..10).map(|i| {
+            Ok::<_, Infallible>(Frame {
+                image: DynamicImage::ImageLuma8(GrayImage::from_pixel(1, 1, Luma([i]))),
+                delay: Duration::from_millis(10),
+            })
+        });
+
+        selection
+            .select(frames)
+            .map(|frame| {
+                let frame = frame.unwrap();
+                (frame.image.to_luma8()[(0, 0)][0], frame.delay.as_millis())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn selects_frames() {
+        let range = |s: &str| s.parse::<FrameRange>().unwrap();
+        assert_eq!(
+            range("2..5"),
+            FrameRange {
+                start: 2,
+                end: Some(5)
+            }
+        );
+        assert_eq!(range("..5").to_string(), "0..5");
+        assert_eq!(range("3..").end, None);
+        assert!("5..5".parse::<FrameRange>().is_err());
+        assert_eq!(range("5"), FrameRange::single(5));
+        assert!("5.".parse::<FrameRange>().is_err());
+
+        assert_eq!(select(FrameSelection::default()).len(), 10);
+        let selection = FrameSelection {
+            range: range("1..8"),
+            every: 3,
+        };
+        assert_eq!(selection.count(10), 3);
+        assert_eq!(selection.count(5), 2);
+        assert_eq!(
+            select(FrameSelection {
+                range: FrameRange::single(4),
+                every: 1,
+            }),
+            [(4, 10)]
+        );
+        assert_eq!(
+            select(FrameSelection {
+                range: range("1..8"),
+                every: 3,
+            }),
+            [(1, 30), (4, 30), (7, 10)]
+        );
+        assert_eq!(
+            select(FrameSelection {
+                range: range("20.."),
+                every: 1
+            }),
+            []
+        );
+    }
+}
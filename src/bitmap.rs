@@ -0,0 +1,211 @@
+use crate::par;
+
+const WORD_BITS: u32 = u64::BITS;
+
+/// The on/off state of every pixel of an image, one bit per pixel, row by
+/// row. Every row starts on a new word, and the bits past the end of a row
+/// are always off.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bitmap {
+    width: u32,
+    height: u32,
+    /// Words per row.
+    stride: usize,
+    words: Vec<u64>,
+}
+
+impl Bitmap {
+    /// A bitmap with every pixel off.
+    pub fn new(width: u32, height: u32) -> Self {
+        let stride = width.div_ceil(WORD_BITS) as usize;
+
+        Bitmap {
+            width,
+            height,
+            stride,
+            words: vec![0; stride * height as usize],
+        }
+    }
+
+    /// A bitmap with the pixels `f` returns `true` for on, the rows computed
+    /// in parallel.
+    pub fn from_fn<F>(width: u32, height: u32, f: F) -> Self
+    where
+        F: Fn(u32, u32) -> bool + Sync,
+    {
+        Bitmap::from_rows(width, height, |y, row| {
+            for (i, word) in row.iter_mut().enumerate() {
+                let start = i as u32 * WORD_BITS;
+                *word = (start..width.min(start + WORD_BITS))
+                    .fold(0, |word, x| word | (f(x, y) as u64) << (x - start));
+            }
+        })
+    }
+
+    /// A bitmap with the words of every row `y` filled in by `f`, the rows
+    /// in parallel; `f` must leave the bits past the end of the row off.
+    pub(crate) fn from_rows<F>(width: u32, height: u32, f: F) -> Self
+    where
+        F: Fn(u32, &mut [u64]) + Sync,
+    {
+        let mut bitmap = Bitmap::new(width, height);
+        if bitmap.stride == 0 {
+            return bitmap;
+        }
+
+        par::for_each_row_mut(&mut bitmap.words, bitmap.stride, f);
+
+        bitmap
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Whether the pixel at (`x`, `y`) is on; pixels outside are off.
+    pub fn get(&self, x: u32, y: u32) -> bool {
+        self.bits(x, y, 1) != 0
+    }
+
+    /// Turns the pixel at (`x`, `y`) on or off.
+    ///
+    /// # Panics
+    ///
+    /// When the pixel is outside the bitmap.
+    pub fn set(&mut self, x: u32, y: u32, on: bool) {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel ({}, {}) outside of the {}x{} bitmap",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+
+        let word = &mut self.words[y as usize * self.stride + (x / WORD_BITS) as usize];
+        let bit = 1 << (x % WORD_BITS);
+        if on {
+            *word |= bit;
+        } else {
+            *word &= !bit;
+        }
+    }
+
+    /// The `n` pixels of row `y` from `x` on, at most 32, the pixel at `x` in
+    /// bit 0; pixels outside are off.
+    pub fn bits(&self, x: u32, y: u32, n: u32) -> u32 {
+        debug_assert!(n <= 32);
+        if y >= self.height || x >= self.width {
+            return 0;
+        }
+
+        let index = y as usize * self.stride + (x / WORD_BITS) as usize;
+        let offset = x % WORD_BITS;
+        let mut bits = self.words[index] >> offset;
+        // a run crossing into the next word of the row, if there is one
+        if offset + n > WORD_BITS && x - offset + WORD_BITS < self.width {
+            bits |= self.words[index + 1] << (WORD_BITS - offset);
+        }
+
+        (bits & ((1 << n) - 1)) as u32
+    }
+
+    /// The `height` rows from row `y` on.
+    ///
+    /// # Panics
+    ///
+    /// When the rows aren't all in the bitmap.
+    pub fn rows(&self, y: u32, height: u32) -> Bitmap {
+        assert!(
+            y + height <= self.height,
+            "rows {}..{} outside of the {}x{} bitmap",
+            y,
+            y + height,
+            self.width,
+            self.height
+        );
+
+        let start = y as usize * self.stride;
+        Bitmap {
+            width: self.width,
+            height,
+            stride: self.stride,
+            words: self.words[start..start + height as usize * self.stride].to_vec(),
+        }
+    }
+
+    /// The smallest region (x, y, width, height) holding every pixel on,
+    /// `None` when none is.
+    pub fn bounds(&self) -> Option<(u32, u32, u32, u32)> {
+        if self.stride == 0 {
+            return None;
+        }
+
+        let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+        for (y, row) in self.words.chunks(self.stride).enumerate() {
+            for (i, &word) in row.iter().enumerate().filter(|&(_, &word)| word != 0) {
+                let start = i as u32 * WORD_BITS;
+                left = left.min(start + word.trailing_zeros());
+                right = right.max(start + WORD_BITS - word.leading_zeros());
+                top = top.min(y as u32);
+                bottom = y as u32 + 1;
+            }
+        }
+
+        (right > 0).then(|| (left, top, right - left, bottom - top))
+    }
+
+    /// The number of pixels on.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Swaps the on and off pixels.
+    pub fn invert(&mut self) {
+        let tail = self.width % WORD_BITS;
+        let last = if tail == 0 { !0 } else { (1 << tail) - 1 };
+        let stride = self.stride;
+
+        self.words.iter_mut().enumerate().for_each(|(i, word)| {
+            *word = !*word;
+            if i % stride == stride - 1 {
+                *word &= last;
+            }
+        });
+    }
+
+    /// Keeps on the pixels that are also on in `other`.
+    ///
+    /// # Panics
+    ///
+    /// When the bitmaps are of different sizes.
+    pub fn and(&mut self, other: &Bitmap) {
+        self.combine(other, |a, b| a & b);
+    }
+
+    /// Turns on the pixels that are on in `other`.
+    ///
+    /// # Panics
+    ///
+    /// When the bitmaps are of different sizes.
+    pub fn or(&mut self, other: &Bitmap) {
+        self.combine(other, |a, b| a | b);
+    }
+
+    fn combine(&mut self, other: &Bitmap, op: fn(u64, u64) -> u64) {
+        assert_eq!(
+            (self.width, self.height),
+            (other.width, other.height),
+            "bitmaps of different sizes"
+        );
+
+        self.words
+            .iter_mut()
+            .zip(&other.words)
+            .for_each(|(a, &b)| *a = op(*a, b));
+    }
+}
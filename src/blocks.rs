@@ -0,0 +1,43 @@
+/// Quadrant block elements, indexed by a bitmask of the on quadrants:
+/// top left = 1, top right = 2, bottom left = 4, bottom right = 8.
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+pub(crate) fn quadrant(mask: u8) -> char {
+    QUADRANTS[(mask & 0xf) as usize]
+}
+
+/// The sextant character for a bitmask of the on sixths, numbered row by
+/// row from the top left, the top left being bit 0.
+pub(crate) fn sextant(mask: u8) -> char {
+    let mask = (mask & 0x3f) as u32;
+
+    // the empty, full, left half and right half patterns are not part of
+    // the sextant block, as they already exist elsewhere
+    match mask {
+        0 => ' ',
+        0b01_0101 => '▌',
+        0b10_1010 => '▐',
+        0b11_1111 => '█',
+        _ => {
+            let skipped = (mask > 0b01_0101) as u32 + (mask > 0b10_1010) as u32;
+            std::char::from_u32(0x1FB00 + mask - 1 - skipped).unwrap()
+        }
+    }
+}
+
+/// The bitmask of the on sixths of a sextant character, the inverse of
+/// [`sextant`] for the characters of the sextant block.
+pub(crate) fn sextant_mask(ch: char) -> Option<u8> {
+    let index = (ch as u32).checked_sub(0x1FB00).filter(|&i| i < 60)?;
+    let mut mask = index + 1;
+    if mask >= 0b01_0101 {
+        mask += 1;
+    }
+    if mask >= 0b10_1010 {
+        mask += 1;
+    }
+
+    Some(mask as u8)
+}
@@ -0,0 +1,107 @@
+use crate::bitmap::Bitmap;
+
+/// UTF8 of first (empty) braille character
+const OFF_0: u32 = 0x2800;
+
+/// The bits of a braille codepoint, and the (column, row) of their dot.
+pub(crate) const DOTS: [(u32, u32); 8] = [
+    (0, 0),
+    (0, 1),
+    (0, 2),
+    (1, 0),
+    (1, 1),
+    (1, 2),
+    (0, 3),
+    (1, 3),
+];
+
+/// The raised dots of a braille character, as the bits of [`DOTS`].
+pub(crate) fn dots(ch: char) -> Option<u32> {
+    let bits = (ch as u32).wrapping_sub(OFF_0);
+    if bits > 0xff {
+        return None;
+    }
+
+    Some(bits)
+}
+
+/// The braille character with the dots of `cell` raised, `cell` being
+/// indexed by row then column.
+pub(crate) fn encode_cell(cell: [[bool; 2]; 4]) -> char {
+    let bits = DOTS
+        .iter()
+        .enumerate()
+        .filter(|&(_, &(col, row))| cell[row as usize][col as usize])
+        .fold(0, |bits, (bit, _)| bits | 1 << bit);
+
+    std::char::from_u32(OFF_0 + bits).unwrap()
+}
+
+/// The braille character of the 2x4 cell at (`x`, `y`) of `bitmap`.
+pub(crate) fn region_braille(bitmap: &Bitmap, x: u32, y: u32) -> char {
+    let mut cell = [[false; 2]; 4];
+    for (row, dots) in cell.iter_mut().enumerate() {
+        let pair = bitmap.bits(x * 2, y * 4 + row as u32, 2);
+        *dots = [pair & 1 != 0, pair & 2 != 0];
+    }
+
+    encode_cell(cell)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The cell with the dots of the `bits` of a codepoint raised, straight
+    /// from the numbering of the Unicode chart: dots 1-3 and 7 down the left
+    /// column, 4-6 and 8 down the right one, dot `n` at bit `n - 1`.
+    fn chart_cell(bits: u32) -> [[bool; 2]; 4] {
+        let dot = |n: u32| bits & 1 << (n - 1) != 0;
+
+        [
+            [dot(1), dot(4)],
+            [dot(2), dot(5)],
+            [dot(3), dot(6)],
+            [dot(7), dot(8)],
+        ]
+    }
+
+    #[test]
+    fn encodes_every_cell_per_the_unicode_chart() {
+        for bits in 0..=0xff {
+            let ch = encode_cell(chart_cell(bits));
+            assert_eq!(ch as u32, 0x2800 + bits, "dots {:08b}", bits);
+            assert_eq!(dots(ch), Some(bits));
+        }
+    }
+
+    #[test]
+    fn encodes_single_dots() {
+        let single = |row: usize, col: usize| {
+            let mut cell = [[false; 2]; 4];
+            cell[row][col] = true;
+            encode_cell(cell)
+        };
+
+        assert_eq!(single(0, 0), '⠁');
+        assert_eq!(single(1, 0), '⠂');
+        assert_eq!(single(2, 0), '⠄');
+        assert_eq!(single(0, 1), '⠈');
+        assert_eq!(single(1, 1), '⠐');
+        assert_eq!(single(2, 1), '⠠');
+        assert_eq!(single(3, 0), '⡀');
+        assert_eq!(single(3, 1), '⢀');
+        assert_eq!(encode_cell([[false; 2]; 4]), '⠀');
+        assert_eq!(encode_cell([[true; 2]; 4]), '⣿');
+    }
+
+    #[test]
+    fn reads_cells_from_bitmap() {
+        // the bottom row of the first cell, and the left column of the second
+        let bitmap = Bitmap::from_fn(4, 4, |x, y| y == 3 && x < 2 || x == 2);
+
+        assert_eq!(region_braille(&bitmap, 0, 0), '⣀');
+        assert_eq!(region_braille(&bitmap, 1, 0), '⡇');
+        assert_eq!(region_braille(&bitmap, 2, 0), '⠀');
+    }
+}
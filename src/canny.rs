@@ -0,0 +1,89 @@
+use crate::bitmap::Bitmap;
+use image::{imageops, GrayImage};
+use std::f32::consts::PI;
+
+/// Runs the Canny edge detector on `gray`, returning the edge map.
+///
+/// Gradients of at least `high` are strong edges, and gradients between
+/// `low` and `high` are kept only when connected to a strong edge.
+pub(crate) fn canny(gray: &GrayImage, low: f32, high: f32) -> Bitmap {
+    let (width, height) = gray.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    if w == 0 || h == 0 {
+        return Bitmap::new(width, height);
+    }
+
+    let blurred = imageops::blur(gray, 1.4);
+    let l = |x: isize, y: isize| {
+        let x = x.clamp(0, w as isize - 1) as u32;
+        let y = y.clamp(0, h as isize - 1) as u32;
+        blurred.get_pixel(x, y)[0] as f32
+    };
+
+    let mut magnitude = vec![0f32; w * h];
+    let mut direction = vec![0u8; w * h];
+    for y in 0..h as isize {
+        for x in 0..w as isize {
+            let gx = (l(x + 1, y - 1) + 2.0 * l(x + 1, y) + l(x + 1, y + 1))
+                - (l(x - 1, y - 1) + 2.0 * l(x - 1, y) + l(x - 1, y + 1));
+            let gy = (l(x - 1, y + 1) + 2.0 * l(x, y + 1) + l(x + 1, y + 1))
+                - (l(x - 1, y - 1) + 2.0 * l(x, y - 1) + l(x + 1, y - 1));
+
+            let i = y as usize * w + x as usize;
+            magnitude[i] = (gx * gx + gy * gy).sqrt();
+
+            // quantize the gradient direction to one of 4 sectors:
+            // 0 = horizontal, 1 = 45°, 2 = vertical, 3 = 135°
+            let angle = gy.atan2(gx).rem_euclid(PI);
+            direction[i] = ((angle / (PI / 4.0)).round() as u8) % 4;
+        }
+    }
+
+    // non-maximum suppression
+    let mag = |x: isize, y: isize| {
+        if x < 0 || y < 0 || x >= w as isize || y >= h as isize {
+            0.0
+        } else {
+            magnitude[y as usize * w + x as usize]
+        }
+    };
+    let mut thin = vec![0f32; w * h];
+    for y in 0..h as isize {
+        for x in 0..w as isize {
+            let i = y as usize * w + x as usize;
+            let (dx, dy) = match direction[i] {
+                0 => (1, 0),
+                1 => (1, 1),
+                2 => (0, 1),
+                _ => (-1, 1),
+            };
+            let m = magnitude[i];
+            if m >= mag(x + dx, y + dy) && m >= mag(x - dx, y - dy) {
+                thin[i] = m;
+            }
+        }
+    }
+
+    // hysteresis
+    let mut edges = vec![false; w * h];
+    let mut stack: Vec<usize> = (0..w * h).filter(|&i| thin[i] >= high).collect();
+    stack.iter().for_each(|&i| edges[i] = true);
+
+    while let Some(i) = stack.pop() {
+        let (x, y) = ((i % w) as isize, (i / w) as isize);
+        for ny in y - 1..=y + 1 {
+            for nx in x - 1..=x + 1 {
+                if nx < 0 || ny < 0 || nx >= w as isize || ny >= h as isize {
+                    continue;
+                }
+                let j = ny as usize * w + nx as usize;
+                if !edges[j] && thin[j] >= low {
+                    edges[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+    }
+
+    Bitmap::from_fn(width, height, |x, y| edges[y as usize * w + x as usize])
+}
@@ -0,0 +1,29 @@
+use image::Rgb;
+
+/// A single output character, with its colors.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub fg: Option<Rgb<u8>>,
+    pub bg: Option<Rgb<u8>>,
+}
+
+impl Cell {
+    pub fn new(ch: char) -> Self {
+        Cell {
+            ch,
+            fg: None,
+            bg: None,
+        }
+    }
+
+    pub fn fg(mut self, c: Option<Rgb<u8>>) -> Self {
+        self.fg = c;
+        self
+    }
+
+    pub fn bg(mut self, c: Option<Rgb<u8>>) -> Self {
+        self.bg = c;
+        self
+    }
+}
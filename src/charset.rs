@@ -0,0 +1,105 @@
+use crate::glyph_map::GlyphMap;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The default brightness ramp of the ASCII charset, from darkest to
+/// brightest.
+pub const DEFAULT_RAMP: &str = " .:-=+*#%@";
+
+/// The charsets parsed by name, see [`Charset`].
+pub const CHARSETS: [&str; 6] = [
+    "braille",
+    "ascii",
+    "lines",
+    "halfblock",
+    "quadrant",
+    "sextant",
+];
+
+/// The set of characters the image is encoded with.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Charset {
+    /// One braille character per 2x4 pixels, one dot per pixel.
+    #[default]
+    Braille,
+    /// One character of the ramp per 2x4 pixels, picked by the brightness of
+    /// the cell.
+    Ascii { ramp: Vec<char> },
+    /// One of `-`, `_`, `|`, `/`, `\` and `+` per 2x4 pixels, following
+    /// the direction of the edges of the on pixels, for line art.
+    Lines,
+    /// One `▀` per 1x2 pixels, colored with the top pixel in the foreground
+    /// and the bottom one in the background.
+    HalfBlock,
+    /// One quadrant block element per 2x2 pixels.
+    Quadrant,
+    /// One teletext sextant (Unicode 13) per 2x3 pixels.
+    Sextant,
+    /// User-defined glyphs, see [`GlyphMap`].
+    Glyphs(GlyphMap),
+}
+
+impl Charset {
+    /// The size (width, height) in pixels of the image region encoded by
+    /// each character.
+    pub fn cell_size(&self) -> (u32, u32) {
+        match self {
+            Charset::Braille | Charset::Ascii { .. } | Charset::Lines => (2, 4),
+            Charset::HalfBlock => (1, 2),
+            Charset::Quadrant => (2, 2),
+            Charset::Sextant => (2, 3),
+            Charset::Glyphs(map) => map.cell_size(),
+        }
+    }
+
+    /// Replaces the brightness ramp of the ramp-based charsets.
+    pub fn with_ramp(self, ramp: &str) -> Self {
+        match self {
+            Charset::Ascii { .. } => Charset::Ascii {
+                ramp: ramp.chars().collect(),
+            },
+            other => other,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CharsetParseError {
+    #[error("unknown charset `{0}`")]
+    UnknownCharset(String),
+}
+
+/// Formats the charset the way it is parsed, leaving out the ramp and the
+/// glyph map which have their own flags.
+impl fmt::Display for Charset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Charset::Braille => write!(f, "braille"),
+            Charset::Ascii { .. } => write!(f, "ascii"),
+            Charset::Lines => write!(f, "lines"),
+            Charset::HalfBlock => write!(f, "halfblock"),
+            Charset::Quadrant => write!(f, "quadrant"),
+            Charset::Sextant => write!(f, "sextant"),
+            Charset::Glyphs(_) => write!(f, "glyphs"),
+        }
+    }
+}
+
+impl FromStr for Charset {
+    type Err = CharsetParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "braille" => Ok(Charset::Braille),
+            "ascii" => Ok(Charset::Ascii {
+                ramp: DEFAULT_RAMP.chars().collect(),
+            }),
+            "lines" => Ok(Charset::Lines),
+            "halfblock" => Ok(Charset::HalfBlock),
+            "quadrant" => Ok(Charset::Quadrant),
+            "sextant" => Ok(Charset::Sextant),
+            _ => Err(CharsetParseError::UnknownCharset(s.into())),
+        }
+    }
+}
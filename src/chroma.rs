@@ -0,0 +1,64 @@
+use crate::bitmap::Bitmap;
+use image::{DynamicImage, GenericImageView, Rgb, Rgba};
+
+/// The background of an image shot over a solid color: the pixels within
+/// `tolerance` of `color`, as a distance in RGB, are made transparent
+/// before the rule and kept off after it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChromaKey {
+    pub color: Rgb<u8>,
+    pub tolerance: f32,
+}
+
+impl ChromaKey {
+    pub fn is_keyed(&self, px: Rgba<u8>) -> bool {
+        let distance: f32 = (0..3)
+            .map(|c| (px[c] as f32 - self.color[c] as f32).powi(2))
+            .sum::<f32>()
+            .sqrt();
+
+        distance <= self.tolerance
+    }
+
+    /// `img` with its keyed pixels transparent, and the pixels of the
+    /// subject, the ones that aren't.
+    pub(crate) fn apply(&self, img: &DynamicImage) -> (DynamicImage, Bitmap) {
+        let (width, height) = img.dimensions();
+        let mut rgba = img.to_rgba8();
+        let subject = Bitmap::from_fn(width, height, |x, y| !self.is_keyed(*rgba.get_pixel(x, y)));
+        for (x, y, px) in rgba.enumerate_pixels_mut() {
+            if !subject.get(x, y) {
+                px[3] = 0;
+            }
+        }
+
+        (DynamicImage::ImageRgba8(rgba), subject)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn keys_the_colors_near_the_key() {
+        let key = ChromaKey {
+            color: Rgb([0, 255, 0]),
+            tolerance: 30.0,
+        };
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(3, 1, |x, _| match x {
+            0 => Rgba([0, 255, 0, 255]),
+            1 => Rgba([20, 235, 10, 255]),
+            _ => Rgba([200, 40, 40, 255]),
+        }));
+        let (keyed, subject) = key.apply(&img);
+
+        assert_eq!(
+            (0..3).map(|x| subject.get(x, 0)).collect::<Vec<_>>(),
+            [false, false, true]
+        );
+        assert_eq!(keyed.get_pixel(1, 0), Rgba([20, 235, 10, 0]));
+        assert_eq!(keyed.get_pixel(2, 0), Rgba([200, 40, 40, 255]));
+    }
+}
@@ -0,0 +1,34 @@
+use arboard::{Clipboard, ImageData};
+use image::{DynamicImage, RgbaImage};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ClipboardError {
+    #[error("clipboard error: {0}")]
+    Clipboard(#[from] arboard::Error),
+    #[error("the clipboard image is malformed")]
+    Malformed,
+}
+
+/// Reads the image currently in the system clipboard.
+pub fn read_clipboard_image() -> Result<DynamicImage, ClipboardError> {
+    let ImageData {
+        width,
+        height,
+        bytes,
+    } = Clipboard::new()?.get_image()?;
+
+    RgbaImage::from_raw(width as u32, height as u32, bytes.into_owned())
+        .map(DynamicImage::ImageRgba8)
+        .ok_or(ClipboardError::Malformed)
+}
+
+/// Puts `text` in the system clipboard.
+///
+/// On X11 and Wayland the text only outlives the process when a clipboard
+/// manager is running.
+pub fn write_clipboard_text(text: &str) -> Result<(), ClipboardError> {
+    Clipboard::new()?.set_text(text)?;
+
+    Ok(())
+}
@@ -0,0 +1,435 @@
+#[cfg(feature = "color")]
+use crate::palette::{self, ColorMetric};
+use image::{DynamicImage, GenericImageView, Pixel, Rgb};
+#[cfg(feature = "color")]
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "color")]
+use std::fmt::Write;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The colors written along with the characters; only `None` is left
+/// without the `color` feature.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    #[default]
+    None,
+    #[cfg(feature = "color")]
+    TrueColor,
+    #[cfg(feature = "color")]
+    Ansi256(ColorMetric),
+    #[cfg(feature = "color")]
+    Ansi16(ColorMetric),
+    /// The brightness of the colors, in the 24 grays of the xterm 256-color
+    /// palette.
+    #[cfg(feature = "color")]
+    Gray,
+}
+
+impl ColorMode {
+    pub(crate) fn write_fg_escape(&self, out: &mut String, c: Rgb<u8>) {
+        self.write_escape(out, c, 38, 30, 90)
+    }
+
+    pub(crate) fn write_bg_escape(&self, out: &mut String, c: Rgb<u8>) {
+        self.write_escape(out, c, 48, 40, 100)
+    }
+
+    /// The longest escape [`ColorMode::write_fg_escape`] and
+    /// [`ColorMode::write_bg_escape`] write, in bytes.
+    pub(crate) fn max_escape_len(&self) -> usize {
+        match self {
+            ColorMode::None => 0,
+            #[cfg(feature = "color")]
+            ColorMode::TrueColor => "\x1b[38;2;255;255;255m".len(),
+            #[cfg(feature = "color")]
+            ColorMode::Ansi256(_) | ColorMode::Gray => "\x1b[38;5;255m".len(),
+            #[cfg(feature = "color")]
+            ColorMode::Ansi16(_) => "\x1b[107m".len(),
+        }
+    }
+
+    /// `extended` is the SGR code selecting 256/24-bit colors, `base` and
+    /// `bright` the first SGR codes of the normal and bright 8 colors.
+    #[cfg_attr(not(feature = "color"), allow(unused_variables, clippy::ptr_arg))]
+    fn write_escape(&self, out: &mut String, c: Rgb<u8>, extended: u8, base: u8, bright: u8) {
+        // writing to a `String` can't fail
+        let _: fmt::Result = match self {
+            ColorMode::None => Ok(()),
+            #[cfg(feature = "color")]
+            ColorMode::TrueColor => {
+                write!(out, "\x1b[{};2;{};{};{}m", extended, c[0], c[1], c[2])
+            }
+            #[cfg(feature = "color")]
+            ColorMode::Ansi256(metric) => write!(
+                out,
+                "\x1b[{};5;{}m",
+                extended,
+                palette::nearest_256(c, *metric)
+            ),
+            #[cfg(feature = "color")]
+            ColorMode::Gray => write!(
+                out,
+                "\x1b[{};5;{}m",
+                extended,
+                232 + palette::nearest_gray(c)
+            ),
+            #[cfg(feature = "color")]
+            ColorMode::Ansi16(metric) => {
+                let idx = palette::nearest_16(c, *metric);
+                let code = if idx < 8 {
+                    base + idx
+                } else {
+                    bright + idx - 8
+                };
+                write!(out, "\x1b[{}m", code)
+            }
+        };
+    }
+
+    /// The distance metric of the quantizing modes, the default one for the
+    /// others.
+    #[cfg(feature = "color")]
+    pub(crate) fn metric(&self) -> ColorMetric {
+        match self {
+            ColorMode::Ansi256(metric) | ColorMode::Ansi16(metric) => *metric,
+            _ => ColorMetric::default(),
+        }
+    }
+
+    /// Replaces the distance metric used by the quantizing modes.
+    #[cfg(feature = "color")]
+    pub fn with_metric(self, metric: ColorMetric) -> Self {
+        match self {
+            ColorMode::Ansi256(_) => ColorMode::Ansi256(metric),
+            ColorMode::Ansi16(_) => ColorMode::Ansi16(metric),
+            other => other,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ColorModeParseError {
+    #[error("unknown color mode `{0}`")]
+    UnknownColorMode(String),
+}
+
+/// Formats the mode the way it is parsed, leaving out the color metric.
+impl fmt::Display for ColorMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorMode::None => write!(f, "none"),
+            #[cfg(feature = "color")]
+            ColorMode::TrueColor => write!(f, "truecolor"),
+            #[cfg(feature = "color")]
+            ColorMode::Ansi256(_) => write!(f, "ansi256"),
+            #[cfg(feature = "color")]
+            ColorMode::Ansi16(_) => write!(f, "ansi16"),
+            #[cfg(feature = "color")]
+            ColorMode::Gray => write!(f, "gray"),
+        }
+    }
+}
+
+impl FromStr for ColorMode {
+    type Err = ColorModeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(ColorMode::None),
+            #[cfg(feature = "color")]
+            "truecolor" => Ok(ColorMode::TrueColor),
+            #[cfg(feature = "color")]
+            "ansi256" => Ok(ColorMode::Ansi256(ColorMetric::default())),
+            #[cfg(feature = "color")]
+            "ansi16" => Ok(ColorMode::Ansi16(ColorMetric::default())),
+            #[cfg(feature = "color")]
+            "gray" | "grey" => Ok(ColorMode::Gray),
+            _ => Err(ColorModeParseError::UnknownColorMode(s.into())),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum HexColorParseError {
+    #[error("invalid color `{0}`, expected #rrggbb or #rgb")]
+    InvalidColor(String),
+}
+
+/// Parses a CSS-like hex color: `#rrggbb` or `#rgb`, the `#` being optional.
+pub fn parse_hex_color(s: &str) -> Result<Rgb<u8>, HexColorParseError> {
+    let invalid = || HexColorParseError::InvalidColor(s.into());
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| invalid());
+    match hex.len() {
+        6 => Ok(Rgb([
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        ])),
+        3 => Ok(Rgb([
+            channel(&hex[0..1])? * 17,
+            channel(&hex[1..2])? * 17,
+            channel(&hex[2..3])? * 17,
+        ])),
+        _ => Err(invalid()),
+    }
+}
+
+/// The background of the cells in color modes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Background {
+    /// The background of the terminal.
+    #[default]
+    Transparent,
+    Color(Rgb<u8>),
+    /// The average color of the cell, dimmed, for the dots to stand out on
+    /// dark and light terminals alike.
+    Auto,
+}
+
+/// How much of the average color of a cell [`Background::Auto`] keeps.
+const AUTO_DIM: f32 = 0.35;
+
+impl Background {
+    /// The background of the cell whose average color is `average`.
+    pub(crate) fn of(&self, average: impl FnOnce() -> Rgb<u8>) -> Option<Rgb<u8>> {
+        match self {
+            Background::Transparent => None,
+            Background::Color(c) => Some(*c),
+            Background::Auto => Some(average().map(|v| (v as f32 * AUTO_DIM).round() as u8)),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum BackgroundParseError {
+    #[error("invalid background `{0}`, expected a color, auto or transparent")]
+    InvalidBackground(String),
+}
+
+impl FromStr for Background {
+    type Err = BackgroundParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "transparent" => Ok(Background::Transparent),
+            "auto" => Ok(Background::Auto),
+            _ => parse_hex_color(s)
+                .map(Background::Color)
+                .map_err(|_| BackgroundParseError::InvalidBackground(s.into())),
+        }
+    }
+}
+
+/// How the color of a cell is derived from its pixels.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ColorSample {
+    /// The average color of all its pixels.
+    #[default]
+    Average,
+    /// The average color of the most common colors, told apart by the top
+    /// bits of their channels.
+    Dominant,
+    /// The color of its center pixel.
+    Center,
+    /// The average color of its on pixels, of all of them when none is on,
+    /// not to wash the color out with the background of the dots.
+    OnPixels,
+}
+
+/// The bits of every channel telling the colors apart in
+/// [`ColorSample::Dominant`].
+const DOMINANT_BITS: u8 = 3;
+
+impl ColorSample {
+    /// The color of the `w`x`h` cell at (`x`, `y`) of `img`, whose pixels
+    /// `on` tells on or off.
+    pub(crate) fn sample(
+        &self,
+        img: &DynamicImage,
+        on: impl Fn(u32, u32) -> bool,
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+    ) -> Rgb<u8> {
+        match self {
+            ColorSample::Average => region_average(img, x, y, w, h),
+            ColorSample::Dominant => {
+                let key = |c: Rgb<u8>| c.map(|v| v >> (8 - DOMINANT_BITS));
+                let mut counts = HashMap::new();
+                for_region(img, x, y, w, h, |_, _, c| {
+                    *counts.entry(key(c)).or_insert(0) += 1
+                });
+                // the first most common color on ties, for the same output
+                // on every run
+                let dominant = counts
+                    .into_iter()
+                    .max_by_key(|&(k, count)| (count, std::cmp::Reverse(k.0)))
+                    .map(|(k, _)| k);
+
+                region_average_where(img, x, y, w, h, |_, _, c| Some(key(c)) == dominant)
+                    .unwrap_or(Rgb([0, 0, 0]))
+            }
+            ColorSample::Center => {
+                let (cx, cy) = (
+                    (x + w / 2).min(img.width().max(1) - 1),
+                    (y + h / 2).min(img.height().max(1) - 1),
+                );
+                match img.in_bounds(cx, cy) {
+                    true => img.get_pixel(cx, cy).to_rgb(),
+                    false => Rgb([0, 0, 0]),
+                }
+            }
+            ColorSample::OnPixels => region_average_where(img, x, y, w, h, |px, py, _| on(px, py))
+                .unwrap_or_else(|| region_average(img, x, y, w, h)),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ColorSampleParseError {
+    #[error("unknown color sampling `{0}`")]
+    UnknownColorSample(String),
+}
+
+impl fmt::Display for ColorSample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorSample::Average => write!(f, "average"),
+            ColorSample::Dominant => write!(f, "dominant"),
+            ColorSample::Center => write!(f, "center"),
+            ColorSample::OnPixels => write!(f, "on-pixels"),
+        }
+    }
+}
+
+impl FromStr for ColorSample {
+    type Err = ColorSampleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "average" => Ok(ColorSample::Average),
+            "dominant" => Ok(ColorSample::Dominant),
+            "center" => Ok(ColorSample::Center),
+            "on-pixels" => Ok(ColorSample::OnPixels),
+            _ => Err(ColorSampleParseError::UnknownColorSample(s.into())),
+        }
+    }
+}
+
+pub(crate) const RESET: &str = "\x1b[0m";
+pub(crate) const DEFAULT_FG: &str = "\x1b[39m";
+pub(crate) const DEFAULT_BG: &str = "\x1b[49m";
+
+/// Removes the ANSI SGR escape sequences (colors) from `s`.
+#[cfg(feature = "color")]
+pub fn strip_ansi(s: &str) -> String {
+    let re = Regex::new("\x1b\\[[0-9;]*m").unwrap();
+
+    re.replace_all(s, "").into_owned()
+}
+
+/// Average color of the pixels of `img` in the `w`x`h` region at (`x`, `y`),
+/// ignoring the parts of the region that fall outside of the image.
+pub(crate) fn region_average(img: &DynamicImage, x: u32, y: u32, w: u32, h: u32) -> Rgb<u8> {
+    region_average_where(img, x, y, w, h, |_, _, _| true).unwrap_or(Rgb([0, 0, 0]))
+}
+
+/// [`region_average`] of the pixels `keep` keeps, `None` when it keeps none.
+fn region_average_where(
+    img: &DynamicImage,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    keep: impl Fn(u32, u32, Rgb<u8>) -> bool,
+) -> Option<Rgb<u8>> {
+    let mut sum = [0u32; 3];
+    let mut count = 0;
+
+    for_region(img, x, y, w, h, |px, py, rgb| {
+        if !keep(px, py, rgb) {
+            return;
+        }
+
+        sum.iter_mut()
+            .zip(rgb.0.iter())
+            .for_each(|(s, &v)| *s += v as u32);
+        count += 1;
+    });
+
+    if count == 0 {
+        return None;
+    }
+
+    Some(Rgb([
+        (sum[0] / count) as u8,
+        (sum[1] / count) as u8,
+        (sum[2] / count) as u8,
+    ]))
+}
+
+/// Calls `f` with the position and color of the pixels of `img` in the
+/// `w`x`h` region at (`x`, `y`) that fall inside of the image.
+fn for_region(
+    img: &DynamicImage,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    mut f: impl FnMut(u32, u32, Rgb<u8>),
+) {
+    for py in y..y + h {
+        for px in x..x + w {
+            if img.in_bounds(px, py) {
+                f(px, py, img.get_pixel(px, py).to_rgb());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_backgrounds() {
+        let average = || Rgb([200, 100, 0]);
+        let background = |s: &str| s.parse::<Background>().unwrap().of(average);
+
+        assert_eq!(background("transparent"), None);
+        assert_eq!(background("#102030"), Some(Rgb([16, 32, 48])));
+        assert_eq!(background("auto"), Some(Rgb([70, 35, 0])));
+        assert!("dim".parse::<Background>().is_err());
+    }
+
+    #[test]
+    fn samples_cells() {
+        // a red dot on the left of three white pixels
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(4, 1, |x, _| match x {
+            0 => Rgb([255, 0, 0]),
+            _ => Rgb([255, 255, 255]),
+        }));
+        let sample = |s: &str, on: fn(u32, u32) -> bool| {
+            s.parse::<ColorSample>()
+                .unwrap()
+                .sample(&img, on, 0, 0, 4, 1)
+        };
+
+        assert_eq!(sample("average", |_, _| false), Rgb([255, 191, 191]));
+        assert_eq!(sample("dominant", |_, _| false), Rgb([255, 255, 255]));
+        assert_eq!(sample("center", |_, _| false), Rgb([255, 255, 255]));
+        assert_eq!(sample("on-pixels", |x, _| x == 0), Rgb([255, 0, 0]));
+        // no on pixel, the average of all of them
+        assert_eq!(sample("on-pixels", |_, _| false), Rgb([255, 191, 191]));
+        assert!("median".parse::<ColorSample>().is_err());
+    }
+}
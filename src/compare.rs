@@ -0,0 +1,167 @@
+use crate::cell::Cell;
+use crate::color::ColorMode;
+use crate::{text, with_ascii, RenderOptions};
+use image::{DynamicImage, Rgb};
+
+/// The background of the cells that differ in [`Comparison::diff`].
+const HIGHLIGHT: Rgb<u8> = Rgb([170, 0, 0]);
+
+/// The cells of two images rendered with the same options, to be shown side
+/// by side or compared cell by cell.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    pub left: Vec<Vec<Cell>>,
+    pub right: Vec<Vec<Cell>>,
+    color: ColorMode,
+}
+
+impl Comparison {
+    pub fn new(left: &DynamicImage, right: &DynamicImage, opts: &RenderOptions) -> Self {
+        let cells = |img| {
+            with_ascii(img, opts, &mut |_, _| {}, |ascii| {
+                ascii.cells(&opts.charset)
+            })
+        };
+
+        Comparison {
+            left: cells(left),
+            right: cells(right),
+            color: opts.color,
+        }
+    }
+
+    /// Columns and rows covering both renders.
+    fn size(&self) -> (usize, usize) {
+        let columns = |cells: &[Vec<Cell>]| cells.first().map_or(0, Vec::len);
+
+        (
+            columns(&self.left).max(columns(&self.right)),
+            self.left.len().max(self.right.len()),
+        )
+    }
+
+    /// Whether the cells at (`x`, `y`) of both renders differ, a cell
+    /// outside of one of them differing from any.
+    fn differs(&self, x: usize, y: usize) -> bool {
+        let cell = |cells: &[Vec<Cell>]| cells.get(y).and_then(|row| row.get(x)).copied();
+        cell(&self.left) != cell(&self.right)
+    }
+
+    /// How many cells differ between the renders.
+    pub fn differing(&self) -> usize {
+        let (columns, rows) = self.size();
+
+        (0..rows)
+            .map(|y| (0..columns).filter(|&x| self.differs(x, y)).count())
+            .sum()
+    }
+
+    /// Both renders as lines of text, the left one then the right one, the
+    /// shorter one padded with blank lines.
+    pub fn side_by_side(&self) -> String {
+        let (_, rows) = self.size();
+        let width = |cells: &[Vec<Cell>]| cells.first().map_or(0, Vec::len);
+        let row = |cells: &[Vec<Cell>], y: usize| {
+            cells
+                .get(y)
+                .cloned()
+                .unwrap_or_else(|| vec![Cell::new(' '); width(cells)])
+        };
+
+        let cells: Vec<_> = (0..rows)
+            .map(|y| {
+                let mut line = row(&self.left, y);
+                line.extend([Cell::new(' '), Cell::new('│'), Cell::new(' ')]);
+                line.extend(row(&self.right, y));
+                line
+            })
+            .collect();
+
+        let mut out = String::new();
+        text::write(&cells, self.color, &mut out);
+        out
+    }
+
+    /// The right render as lines of text, the cells differing from the left
+    /// one on a red background; without colors, the highlights are in the
+    /// 16 colors of the terminal.
+    pub fn diff(&self) -> String {
+        let (columns, rows) = self.size();
+        let cells: Vec<Vec<_>> = (0..rows)
+            .map(|y| {
+                (0..columns)
+                    .map(|x| {
+                        let cell = self
+                            .right
+                            .get(y)
+                            .and_then(|row| row.get(x))
+                            .copied()
+                            .unwrap_or(Cell::new(' '));
+                        match self.differs(x, y) {
+                            true => cell.bg(Some(HIGHLIGHT)),
+                            false => cell,
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut out = String::new();
+        text::write(&cells, highlight_mode(self.color), &mut out);
+        out
+    }
+}
+
+#[cfg(feature = "color")]
+fn highlight_mode(color: ColorMode) -> ColorMode {
+    match color {
+        ColorMode::None => ColorMode::Ansi16(Default::default()),
+        color => color,
+    }
+}
+
+/// Without the `color` feature there's no way to highlight the cells.
+#[cfg(not(feature = "color"))]
+fn highlight_mode(color: ColorMode) -> ColorMode {
+    color
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    /// An image of 2x2 braille cells, lit up to `x` pixels across.
+    fn lit(x: u32) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(4, 8, |px, _| {
+            Luma([if px < x { 255 } else { 0 }])
+        }))
+    }
+
+    #[test]
+    fn counts_differing_cells() {
+        let opts = RenderOptions::default();
+
+        assert_eq!(Comparison::new(&lit(2), &lit(2), &opts).differing(), 0);
+        // the right column of cells changes
+        assert_eq!(Comparison::new(&lit(2), &lit(4), &opts).differing(), 2);
+        // a larger image, its extra cells differ
+        let wide = DynamicImage::ImageLuma8(GrayImage::new(6, 8));
+        assert_eq!(Comparison::new(&lit(0), &wide, &opts).differing(), 2);
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn highlights_differing_cells() {
+        let comparison = Comparison::new(&lit(2), &lit(4), &RenderOptions::default());
+
+        assert_eq!(comparison.diff(), "⣿\x1b[41m⣿\x1b[0m\n".repeat(2));
+    }
+
+    #[test]
+    fn lays_out_side_by_side() {
+        let comparison = Comparison::new(&lit(4), &lit(0), &RenderOptions::default());
+
+        assert_eq!(comparison.side_by_side(), "⣿⣿ │ ⠀⠀\n⣿⣿ │ ⠀⠀\n");
+    }
+}
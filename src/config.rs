@@ -0,0 +1,243 @@
+use crate::{Charset, ColorMode, Dither, ImageSize, OnOffRule, ResizeFilter, RuleExpr};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+#[cfg(not(target_arch = "wasm32"))]
+use std::env;
+use std::fmt::Display;
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Defaults for the command line flags, read from a TOML file like:
+///
+/// ```toml
+/// rule = "Otsu"
+/// charset = "blocks"
+/// color = "truecolor"
+///
+/// # bundles of settings picked with `--preset`, over the defaults above,
+/// # also setting `dither`, `contrast`, `filter` and `quiet_zone`
+/// [presets.dark]
+/// rule = "!Luma(100)"
+/// size = "120x_"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    pub defaults: Settings,
+    pub presets: HashMap<String, Settings>,
+}
+
+/// Settings that are left to the flags, or to the built-in defaults, when
+/// `None`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Settings {
+    pub rule: Option<RuleExpr>,
+    pub size: Option<ImageSize>,
+    pub charset: Option<Charset>,
+    pub color: Option<ColorMode>,
+    pub dither: Option<Dither>,
+    pub contrast: Option<f32>,
+    pub filter: Option<ResizeFilter>,
+    pub quiet_zone: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct SettingsFile {
+    rule: Option<String>,
+    size: Option<String>,
+    charset: Option<String>,
+    color: Option<String>,
+    dither: Option<String>,
+    contrast: Option<f32>,
+    filter: Option<String>,
+    quiet_zone: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(flatten)]
+    defaults: SettingsFile,
+    #[serde(default)]
+    presets: HashMap<String, SettingsFile>,
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("couldn't read the config file: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid config file: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("invalid `{0}` in the config file: {1}")]
+    InvalidValue(&'static str, String),
+    #[error("unknown preset `{0}`")]
+    UnknownPreset(String),
+}
+
+impl Config {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// `ascii-image/config.toml` in the user's config directory.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+        Some(dir.join("ascii-image").join("config.toml"))
+    }
+
+    /// Reads the file at [`Config::default_path`], if there is one.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_default() -> Result<Self, ConfigError> {
+        match Config::default_path() {
+            Some(path) if path.exists() => Config::from_file(path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    /// The defaults, overridden by the settings of `preset`; the presets of
+    /// the file hide the built-in ones of the same name.
+    pub fn settings(&self, preset: Option<&str>) -> Result<Settings, ConfigError> {
+        let preset = match preset {
+            Some(name) => self
+                .presets
+                .get(name)
+                .cloned()
+                .or_else(|| builtin_preset(name))
+                .ok_or_else(|| ConfigError::UnknownPreset(name.into()))?,
+            None => return Ok(self.defaults.clone()),
+        };
+
+        Ok(preset.or(&self.defaults))
+    }
+
+    /// Every preset, built-in ones first, then the ones of the file by name.
+    pub fn presets(&self) -> Vec<(String, Settings)> {
+        let mut file: Vec<_> = self.presets.clone().into_iter().collect();
+        file.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        BUILTIN_PRESETS
+            .iter()
+            .filter(|name| !self.presets.contains_key(**name))
+            .map(|&name| (name.to_string(), builtin_preset(name).unwrap()))
+            .chain(file)
+            .collect()
+    }
+}
+
+impl Settings {
+    /// These settings, falling back to `other` for the ones left unset.
+    pub fn or(self, other: &Settings) -> Settings {
+        Settings {
+            rule: self.rule.or_else(|| other.rule.clone()),
+            size: self.size.or(other.size),
+            charset: self.charset.or_else(|| other.charset.clone()),
+            color: self.color.or(other.color),
+            dither: self.dither.or(other.dither),
+            contrast: self.contrast.or(other.contrast),
+            filter: self.filter.or(other.filter),
+            quiet_zone: self.quiet_zone.or(other.quiet_zone),
+        }
+    }
+}
+
+pub const BUILTIN_PRESETS: [&str; 4] = ["photo", "lineart", "logo", "qr"];
+
+/// The settings of the built-in presets:
+/// - `photo`: dithered threshold, for the gradients of photographs,
+/// - `lineart`: dark strokes on a light background, drawn as the on dots,
+/// - `logo`: flat dark shapes, as chunky blocks,
+/// - `qr`: sharp square modules, dark ones on, inside a quiet zone of
+///   blank cells, for codes that scan from a screenshot of the terminal.
+pub fn builtin_preset(name: &str) -> Option<Settings> {
+    let (rule, dither, contrast, charset) = match name {
+        "photo" => (
+            OnOffRule::LumaThreshold(128).into(),
+            Dither::FloydSteinberg,
+            10.0,
+            Charset::Braille,
+        ),
+        "lineart" => (
+            RuleExpr::Not(Box::new(OnOffRule::Otsu.into())),
+            Dither::None,
+            20.0,
+            Charset::Braille,
+        ),
+        "logo" => (
+            RuleExpr::Not(Box::new(OnOffRule::Otsu.into())),
+            Dither::None,
+            0.0,
+            Charset::Quadrant,
+        ),
+        "qr" => (
+            RuleExpr::Not(Box::new(OnOffRule::LumaThreshold(128).into())),
+            Dither::None,
+            50.0,
+            Charset::HalfBlock,
+        ),
+        _ => return None,
+    };
+
+    Some(Settings {
+        rule: Some(rule),
+        dither: Some(dither),
+        contrast: Some(contrast),
+        charset: Some(charset),
+        // smoothing blurs the edges of the modules, and a code needs a
+        // margin of 4 modules to be found
+        filter: (name == "qr").then_some(ResizeFilter::Nearest),
+        quiet_zone: (name == "qr").then_some(4),
+        ..Settings::default()
+    })
+}
+
+fn parse<T: FromStr>(key: &'static str, value: Option<String>) -> Result<Option<T>, ConfigError>
+where
+    T::Err: Display,
+{
+    value
+        .map(|value| T::from_str(&value))
+        .transpose()
+        .map_err(|e| ConfigError::InvalidValue(key, e.to_string()))
+}
+
+impl TryFrom<SettingsFile> for Settings {
+    type Error = ConfigError;
+
+    fn try_from(file: SettingsFile) -> Result<Self, Self::Error> {
+        Ok(Settings {
+            rule: parse("rule", file.rule)?,
+            size: parse("size", file.size)?,
+            charset: parse("charset", file.charset)?,
+            color: parse("color", file.color)?,
+            dither: parse("dither", file.dither)?,
+            contrast: file.contrast,
+            filter: parse("filter", file.filter)?,
+            quiet_zone: file.quiet_zone,
+        })
+    }
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file: ConfigFile = toml::from_str(s)?;
+
+        Ok(Config {
+            defaults: Settings::try_from(file.defaults)?,
+            presets: file
+                .presets
+                .into_iter()
+                .map(|(name, settings)| Ok((name, Settings::try_from(settings)?)))
+                .collect::<Result<_, ConfigError>>()?,
+        })
+    }
+}
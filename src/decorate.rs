@@ -0,0 +1,429 @@
+//! Text and borders drawn around and over the cells of the image, once
+//! they're built.
+
+use crate::bitmap::Bitmap;
+use crate::braille;
+use crate::cell::Cell;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The decorations of the output, none by default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Decorations {
+    pub caption: Option<Caption>,
+    /// A tag written over the bottom right corner of the image.
+    pub watermark: Option<String>,
+    /// Blank cells between the image and its caption, and the frame.
+    pub padding: u32,
+    pub frame: FrameStyle,
+    /// Text written into the top border of the frame.
+    pub title: Option<String>,
+    /// Lines above everything else, flush left and cut at the width of the
+    /// output, e.g. the size and the format of the image.
+    pub header: Option<String>,
+}
+
+/// The border drawn around the output.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FrameStyle {
+    #[default]
+    None,
+    Single,
+    Double,
+    Ascii,
+}
+
+#[derive(Error, Debug)]
+pub enum FrameStyleParseError {
+    #[error("unknown frame style `{0}`")]
+    UnknownFrameStyle(String),
+}
+
+impl FromStr for FrameStyle {
+    type Err = FrameStyleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(FrameStyle::None),
+            "single" => Ok(FrameStyle::Single),
+            "double" => Ok(FrameStyle::Double),
+            "ascii" => Ok(FrameStyle::Ascii),
+            _ => Err(FrameStyleParseError::UnknownFrameStyle(s.into())),
+        }
+    }
+}
+
+impl FrameStyle {
+    /// The horizontal and vertical lines, then the top left, top right,
+    /// bottom left and bottom right corners.
+    fn chars(&self) -> Option<[char; 6]> {
+        match self {
+            FrameStyle::None => None,
+            FrameStyle::Single => Some(['─', '│', '┌', '┐', '└', '┘']),
+            FrameStyle::Double => Some(['═', '║', '╔', '╗', '╚', '╝']),
+            FrameStyle::Ascii => Some(['-', '|', '+', '+', '+', '+']),
+        }
+    }
+}
+
+/// Text in rows of its own above or below the image, centered and wrapped
+/// at its width.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Caption {
+    pub text: String,
+    pub position: CaptionPosition,
+    /// Draw the text in a banner font of braille dots, two columns and two
+    /// rows per character.
+    pub banner: bool,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CaptionPosition {
+    Top,
+    #[default]
+    Bottom,
+}
+
+#[derive(Error, Debug)]
+pub enum CaptionPositionParseError {
+    #[error("unknown caption position `{0}`")]
+    UnknownCaptionPosition(String),
+}
+
+impl FromStr for CaptionPosition {
+    type Err = CaptionPositionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top" => Ok(CaptionPosition::Top),
+            "bottom" => Ok(CaptionPosition::Bottom),
+            _ => Err(CaptionPositionParseError::UnknownCaptionPosition(s.into())),
+        }
+    }
+}
+
+impl Decorations {
+    /// Draws the decorations around and over the rows of `cells`: the
+    /// watermark, the caption, the padding, the frame, then the header.
+    pub(crate) fn apply(&self, cells: Vec<Vec<Cell>>) -> Vec<Vec<Cell>> {
+        let cells = self.framed_image(cells);
+        match &self.header {
+            Some(header) => header_rows(header, cells.first().map_or(0, Vec::len))
+                .into_iter()
+                .chain(cells)
+                .collect(),
+            None => cells,
+        }
+    }
+
+    fn framed_image(&self, mut cells: Vec<Vec<Cell>>) -> Vec<Vec<Cell>> {
+        let width = cells.first().map_or(0, Vec::len);
+
+        if let (Some(tag), Some(row)) = (&self.watermark, cells.last_mut()) {
+            let tag: Vec<_> = tag.chars().take(width).map(Cell::new).collect();
+            row.splice(width - tag.len().., tag);
+        }
+
+        if let Some(caption) = &self.caption {
+            let rows = caption.rows(width);
+            cells = match caption.position {
+                CaptionPosition::Top => rows.into_iter().chain(cells).collect(),
+                CaptionPosition::Bottom => cells.into_iter().chain(rows).collect(),
+            };
+        }
+
+        let cells = surround(cells, [Cell::new(' '); 6], self.padding as usize);
+        match self.frame.chars() {
+            Some(chars) => self.framed(cells, chars),
+            None => cells,
+        }
+    }
+
+    /// `cells` in a frame of `chars`, the title in its top border.
+    fn framed(&self, cells: Vec<Vec<Cell>>, chars: [char; 6]) -> Vec<Vec<Cell>> {
+        let width = cells.first().map_or(0, Vec::len);
+        let mut cells = surround(cells, chars.map(Cell::new), 1);
+
+        if let Some(title) = &self.title {
+            // a line on each side of the title, and a space between them
+            let title: Vec<_> = format!(" {} ", title)
+                .chars()
+                .take(width.saturating_sub(2))
+                .map(Cell::new)
+                .collect();
+            let end = 2 + title.len();
+            cells[0].splice(2..end, title);
+        }
+
+        cells
+    }
+}
+
+/// The lines of `header`, `width` columns wide, or as wide as they are when
+/// `width` is 0.
+fn header_rows(header: &str, width: usize) -> Vec<Vec<Cell>> {
+    header
+        .lines()
+        .map(|line| {
+            let mut row: Vec<Cell> = line.chars().map(Cell::new).collect();
+            if width > 0 {
+                row.resize(width, Cell::new(' '));
+            }
+            row
+        })
+        .collect()
+}
+
+/// `cells` inside a border `size` cells thick of the horizontal, vertical
+/// and corner cells of `border`, like [`FrameStyle::chars`].
+fn surround(cells: Vec<Vec<Cell>>, border: [Cell; 6], size: usize) -> Vec<Vec<Cell>> {
+    if size == 0 {
+        return cells;
+    }
+
+    let [horizontal, vertical, top_left, top_right, bottom_left, bottom_right] = border;
+    let width = cells.first().map_or(0, Vec::len);
+    let edge = |left, right| {
+        let mut row = vec![left; size];
+        row.extend(vec![horizontal; width]);
+        row.extend(vec![right; size]);
+        row
+    };
+
+    let mut rows = vec![edge(top_left, top_right); size];
+    for row in cells {
+        let mut line = vec![vertical; size];
+        line.extend(row);
+        line.extend(vec![vertical; size]);
+        rows.push(line);
+    }
+    rows.extend(vec![edge(bottom_left, bottom_right); size]);
+
+    rows
+}
+
+impl Caption {
+    /// The rows of the caption, `width` columns wide; as wide as the text
+    /// when `width` is 0.
+    fn rows(&self, width: usize) -> Vec<Vec<Cell>> {
+        // a character of the banner font takes two columns
+        let columns = if self.banner { 2 } else { 1 };
+        let line_width = match width {
+            0 => usize::MAX,
+            width => (width / columns).max(1),
+        };
+
+        let mut rows = Vec::new();
+        for line in wrap(&self.text, line_width) {
+            let lines = match self.banner {
+                true => banner(&line),
+                false => vec![line.chars().map(Cell::new).collect()],
+            };
+            for mut row in lines {
+                let margin = width.saturating_sub(row.len()) / 2;
+                row.splice(0..0, vec![Cell::new(' '); margin]);
+                if row.len() < width {
+                    row.resize(width, Cell::new(' '));
+                }
+                rows.push(row);
+            }
+        }
+
+        rows
+    }
+}
+
+/// The words of `text` in lines of at most `width` characters, the words
+/// longer than that cut.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        let len = line.chars().count();
+        if len > 0 && len + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        } else if len > 0 {
+            line.push(' ');
+        }
+
+        while word.len() > width {
+            lines.push(word.drain(..width).collect());
+        }
+        line.extend(word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// The glyphs of the banner font, 3x5 pixels, a row per byte from the top,
+/// the left pixel in bit 2.
+const FONT: [(char, [u8; 5]); 48] = [
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b110, 0b001, 0b010, 0b100, 0b111]),
+    ('3', [0b110, 0b001, 0b010, 0b001, 0b110]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b110, 0b001, 0b110]),
+    ('6', [0b011, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b110]),
+    (' ', [0b000, 0b000, 0b000, 0b000, 0b000]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    (',', [0b000, 0b000, 0b000, 0b010, 0b100]),
+    ('!', [0b010, 0b010, 0b010, 0b000, 0b010]),
+    ('?', [0b110, 0b001, 0b010, 0b000, 0b010]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    ('\'', [0b010, 0b010, 0b000, 0b000, 0b000]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+    ('#', [0b101, 0b111, 0b101, 0b111, 0b101]),
+];
+
+/// The glyph of `ch`, the letters in upper case, `?` for the characters
+/// the font lacks.
+fn glyph(ch: char) -> [u8; 5] {
+    let ch = ch.to_ascii_uppercase();
+    let find = |ch| FONT.iter().find(|(c, _)| *c == ch).map(|(_, glyph)| *glyph);
+
+    find(ch).or_else(|| find('?')).unwrap()
+}
+
+/// `line` in the banner font, as two rows of braille cells: every glyph
+/// followed by a column of space, with a row of space above it.
+fn banner(line: &str) -> Vec<Vec<Cell>> {
+    let glyphs: Vec<_> = line.chars().map(glyph).collect();
+    let bitmap = Bitmap::from_fn(glyphs.len() as u32 * 4, 8, |x, y| {
+        let (glyph, column) = (&glyphs[x as usize / 4], x % 4);
+        (1..=5).contains(&y) && column < 3 && glyph[y as usize - 1] & (0b100 >> column) != 0
+    });
+
+    (0..2)
+        .map(|y| {
+            (0..glyphs.len() as u32 * 2)
+                .map(|x| Cell::new(braille::region_braille(&bitmap, x, y)))
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(cells: &[Vec<Cell>]) -> Vec<String> {
+        cells
+            .iter()
+            .map(|row| row.iter().map(|c| c.ch).collect())
+            .collect()
+    }
+
+    fn image(width: usize, height: usize) -> Vec<Vec<Cell>> {
+        vec![vec![Cell::new('#'); width]; height]
+    }
+
+    #[test]
+    fn wraps_words() {
+        assert_eq!(wrap("a bb ccc", 4), ["a bb", "ccc"]);
+        assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("", 4), [""]);
+    }
+
+    #[test]
+    fn places_captions() {
+        let caption = |position| Decorations {
+            caption: Some(Caption {
+                text: "hi you".into(),
+                position,
+                banner: false,
+            }),
+            ..Decorations::default()
+        };
+
+        assert_eq!(
+            text(&caption(CaptionPosition::Bottom).apply(image(5, 1))),
+            ["#####", " hi  ", " you "]
+        );
+        assert_eq!(
+            text(&caption(CaptionPosition::Top).apply(image(6, 1))),
+            ["hi you", "######"]
+        );
+    }
+
+    #[test]
+    fn writes_the_watermark_in_the_corner() {
+        let decorations = Decorations {
+            watermark: Some("(c)".into()),
+            ..Decorations::default()
+        };
+
+        assert_eq!(text(&decorations.apply(image(5, 2))), ["#####", "##(c)"]);
+    }
+
+    #[test]
+    fn frames_and_pads() {
+        let decorations = Decorations {
+            padding: 1,
+            frame: "single".parse().unwrap(),
+            title: Some("a title".into()),
+            ..Decorations::default()
+        };
+
+        assert_eq!(
+            text(&decorations.apply(image(4, 1))),
+            ["┌─ a t─┐", "│      │", "│ #### │", "│      │", "└──────┘"]
+        );
+    }
+
+    #[test]
+    fn writes_the_header_above_the_frame() {
+        let decorations = Decorations {
+            frame: "ascii".parse().unwrap(),
+            header: Some("4x1 PNG\na long line".into()),
+            ..Decorations::default()
+        };
+
+        assert_eq!(
+            text(&decorations.apply(image(4, 1))),
+            ["4x1 PN", "a long", "+----+", "|####|", "+----+"]
+        );
+    }
+
+    #[test]
+    fn draws_banners() {
+        assert_eq!(text(&banner("Hi")), ["⣆⡆⢲⠂", "⠃⠃⠚⠂"]);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::cell::Cell;
+use crate::color::ColorMode;
+use crate::text;
+use std::fmt::Write;
+
+/// Changed cells closer than this are rewritten in one run, moving the
+/// cursor over the cells in between taking about as many bytes.
+const MAX_GAP: usize = 4;
+
+/// Appends to `out` what turns the lines of `previous` on the terminal into
+/// the lines of `cells`, the cursor being at the start of the first one,
+/// and leaves it at the start of the line below the new ones. Only the runs
+/// of cells that changed are rewritten, placing the cursor with escapes;
+/// the whole frame is when the number of lines changes.
+pub(crate) fn write(
+    previous: &[Vec<Cell>],
+    cells: &[Vec<Cell>],
+    color: ColorMode,
+    out: &mut String,
+) {
+    let height = previous.len();
+    if cells.len() != height {
+        // clearing what the previous frame leaves below the new one; the
+        // lines end with `\r` too for terminals in raw mode
+        out.push_str("\x1b[J");
+        for row in cells {
+            text::write_run(row, color, out);
+            out.push_str("\r\n");
+        }
+        return;
+    }
+
+    // the line of the cursor
+    let mut y = 0;
+    for (row, (old, new)) in previous.iter().zip(cells).enumerate() {
+        let runs = changed_runs(old, new);
+        let shorter = new.len() < old.len();
+        if runs.is_empty() && !shorter {
+            continue;
+        }
+
+        if row > y {
+            write!(out, "\x1b[{}B", row - y).unwrap();
+            y = row;
+        }
+        for (start, end) in runs {
+            write!(out, "\x1b[{}G", start + 1).unwrap();
+            text::write_run(&new[start..end], color, out);
+        }
+        if shorter {
+            write!(out, "\x1b[{}G\x1b[K", new.len() + 1).unwrap();
+        }
+    }
+
+    if height > y {
+        write!(out, "\x1b[{}B", height - y).unwrap();
+    }
+    out.push('\r');
+}
+
+/// The ranges of the cells of `new` that differ from `old`, the ones
+/// less than [`MAX_GAP`] cells apart merged.
+fn changed_runs(old: &[Cell], new: &[Cell]) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (x, cell) in new.iter().enumerate() {
+        if old.get(x) == Some(cell) {
+            continue;
+        }
+
+        match runs.last_mut() {
+            Some((_, end)) if x - *end < MAX_GAP => *end = x + 1,
+            _ => runs.push((x, x + 1)),
+        }
+    }
+
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(s: &str) -> Vec<Cell> {
+        s.chars().map(Cell::new).collect()
+    }
+
+    #[test]
+    fn rewrites_the_changes() {
+        let previous = [line("abcdefghij"), line("klmno"), line("pqrst")];
+        let cells = [line("abXdefghiY"), line("klmno"), line("pq")];
+        let mut out = String::new();
+        write(&previous, &cells, ColorMode::None, &mut out);
+        assert_eq!(out, "\x1b[3GX\x1b[10GY\x1b[2B\x1b[3G\x1b[K\x1b[1B\r");
+
+        // everything, over a frame of another height
+        let mut out = String::new();
+        write(&previous, &cells[..1], ColorMode::None, &mut out);
+        assert_eq!(out, "\x1b[JabXdefghiY\r\n");
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn rewrites_close_changes_in_one_run() {
+        let previous = [line("abcdefghij")];
+        let mut cells = [line("aXcYefghij")];
+        cells[0][1] = cells[0][1].fg(Some(image::Rgb([255, 0, 0])));
+        let mut out = String::new();
+        write(&previous, &cells, ColorMode::TrueColor, &mut out);
+        assert_eq!(out, "\x1b[2G\x1b[38;2;255;0;0mX\x1b[39mcY\x1b[0m\x1b[1B\r");
+    }
+}
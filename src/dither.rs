@@ -0,0 +1,149 @@
+use crate::par;
+use image::{DynamicImage, GrayImage, Luma};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The dithers parsed by name, see [`Dither`].
+pub const DITHERS: [&str; 5] = ["none", "floyd-steinberg", "bayer2", "bayer4", "bayer8"];
+
+/// Dithering applied to the image before the on/off rule is evaluated.
+///
+/// Dithering converts the image to pure black and white pixels, so it
+/// is best paired with a simple threshold rule.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Dither {
+    #[default]
+    None,
+    FloydSteinberg,
+    /// Ordered dithering with a Bayer matrix of the given size (2, 4 or 8).
+    Bayer(u32),
+}
+
+impl Dither {
+    pub fn apply<'a>(&self, img: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        match self {
+            Dither::None => Cow::Borrowed(img),
+            Dither::FloydSteinberg => {
+                Cow::Owned(DynamicImage::ImageLuma8(floyd_steinberg(&img.to_luma8())))
+            }
+            Dither::Bayer(n) => Cow::Owned(DynamicImage::ImageLuma8(bayer(&img.to_luma8(), *n))),
+        }
+    }
+}
+
+fn floyd_steinberg(gray: &GrayImage) -> GrayImage {
+    let (width, height) = gray.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let mut buf: Vec<f32> = gray.pixels().map(|p| p[0] as f32).collect();
+
+    for y in 0..h {
+        for x in 0..w {
+            let old = buf[y * w + x];
+            let new = if old >= 128.0 { 255.0 } else { 0.0 };
+            buf[y * w + x] = new;
+            let err = old - new;
+
+            let mut spread = |dx: isize, dy: usize, factor: f32| {
+                let nx = x as isize + dx;
+                let ny = y + dy;
+                if nx >= 0 && (nx as usize) < w && ny < h {
+                    buf[ny * w + nx as usize] += err * factor;
+                }
+            };
+
+            spread(1, 0, 7.0 / 16.0);
+            spread(-1, 1, 3.0 / 16.0);
+            spread(0, 1, 5.0 / 16.0);
+            spread(1, 1, 1.0 / 16.0);
+        }
+    }
+
+    GrayImage::from_fn(width, height, |x, y| {
+        Luma([buf[y as usize * w + x as usize] as u8])
+    })
+}
+
+/// The `n`x`n` Bayer index matrix, `n` being a power of two.
+pub(crate) fn bayer_matrix(n: u32) -> Vec<Vec<u32>> {
+    if n <= 1 {
+        return vec![vec![0]];
+    }
+
+    let half = bayer_matrix(n / 2);
+    let h = n as usize / 2;
+
+    (0..n as usize)
+        .map(|y| {
+            (0..n as usize)
+                .map(|x| {
+                    let offset = match (y >= h, x >= h) {
+                        (false, false) => 0,
+                        (false, true) => 2,
+                        (true, false) => 3,
+                        (true, true) => 1,
+                    };
+                    4 * half[y % h][x % h] + offset
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn bayer(gray: &GrayImage, n: u32) -> GrayImage {
+    let (width, height) = gray.dimensions();
+    let matrix = bayer_matrix(n);
+    let levels = (n * n) as f32;
+
+    let mut out = GrayImage::new(width, height);
+    if width == 0 {
+        return out;
+    }
+
+    par::for_each_row_mut(&mut out, width as usize, |y, row| {
+        let thresholds = &matrix[(y % n) as usize];
+        row.iter_mut().enumerate().for_each(|(x, v)| {
+            let threshold = (thresholds[x % n as usize] as f32 + 0.5) / levels * 255.0;
+            *v = if gray.get_pixel(x as u32, y)[0] as f32 > threshold {
+                255
+            } else {
+                0
+            };
+        })
+    });
+
+    out
+}
+
+/// Formats the dither the way it is parsed.
+impl fmt::Display for Dither {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dither::None => write!(f, "none"),
+            Dither::FloydSteinberg => write!(f, "floyd-steinberg"),
+            Dither::Bayer(n) => write!(f, "bayer{}", n),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DitherParseError {
+    #[error("unknown dither `{0}`")]
+    UnknownDither(String),
+}
+
+impl FromStr for Dither {
+    type Err = DitherParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Dither::None),
+            "floyd-steinberg" => Ok(Dither::FloydSteinberg),
+            "bayer2" => Ok(Dither::Bayer(2)),
+            "bayer4" => Ok(Dither::Bayer(4)),
+            "bayer8" => Ok(Dither::Bayer(8)),
+            _ => Err(DitherParseError::UnknownDither(s.into())),
+        }
+    }
+}
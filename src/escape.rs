@@ -0,0 +1,130 @@
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Marks the blank lines that chat apps and markdown renderers would
+/// otherwise collapse.
+const GUARD: char = '\u{200b}';
+
+/// How the text is wrapped to be pasted somewhere.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Escape {
+    /// As it is.
+    #[default]
+    None,
+    /// A `printf` command printing the text, colors included.
+    Shell,
+    /// A fenced code block, without colors.
+    Markdown,
+    /// A code block Discord shows the 16 colors of, if there are any.
+    Discord,
+}
+
+impl Escape {
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            Escape::None => text.to_string(),
+            Escape::Shell => {
+                let quoted = text
+                    .replace('\\', "\\\\")
+                    .replace('\'', "'\\''")
+                    .replace('\x1b', "\\033");
+                format!("printf '%b' '{}'\n", quoted)
+            }
+            Escape::Markdown => fenced("", &strip_colors(text)),
+            Escape::Discord => {
+                let language = if text.contains('\x1b') { "ansi" } else { "" };
+                fenced(language, text)
+            }
+        }
+    }
+}
+
+/// `text` in a code block of `language`, fenced with more backticks than
+/// it holds in a row, its blank lines guarded.
+fn fenced(language: &str, text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat((longest + 1).max(3));
+
+    let mut out = format!("{}{}\n", fence, language);
+    for line in text.lines() {
+        out.push_str(line);
+        if line.trim().is_empty() {
+            out.push(GUARD);
+        }
+        out.push('\n');
+    }
+    out.push_str(&fence);
+    out.push('\n');
+
+    out
+}
+
+#[cfg(feature = "color")]
+fn strip_colors(text: &str) -> String {
+    crate::color::strip_ansi(text)
+}
+
+/// Without the `color` feature there are no colors to strip.
+#[cfg(not(feature = "color"))]
+fn strip_colors(text: &str) -> String {
+    text.to_string()
+}
+
+/// Formats the escape the way it is parsed.
+impl fmt::Display for Escape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Escape::None => write!(f, "none"),
+            Escape::Shell => write!(f, "shell"),
+            Escape::Markdown => write!(f, "markdown"),
+            Escape::Discord => write!(f, "discord"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum EscapeParseError {
+    #[error("unknown escape `{0}`")]
+    UnknownEscape(String),
+}
+
+impl FromStr for Escape {
+    type Err = EscapeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Escape::None),
+            "shell" => Ok(Escape::Shell),
+            "markdown" => Ok(Escape::Markdown),
+            "discord" => Ok(Escape::Discord),
+            _ => Err(EscapeParseError::UnknownEscape(s.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_for_the_shell() {
+        assert_eq!(
+            Escape::Shell.apply("\x1b[31m'⣿\\\n"),
+            "printf '%b' '\\033[31m'\\''⣿\\\\\n'\n"
+        );
+    }
+
+    #[test]
+    fn fences_code_blocks() {
+        assert_eq!(
+            Escape::Markdown.apply("⣿ ``\n  \n⣿\n"),
+            "```\n⣿ ``\n  \u{200b}\n⣿\n```\n"
+        );
+        assert_eq!(Escape::Markdown.apply("````\n"), "`````\n````\n`````\n");
+        assert_eq!(
+            Escape::Discord.apply("\x1b[31m⣿\x1b[0m\n"),
+            "```ansi\n\x1b[31m⣿\x1b[0m\n```\n"
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{Flip, Rotation};
+use std::convert::TryInto;
+
+const ORIENTATION_TAG: u16 = 0x0112;
+const MAKE_TAG: u16 = 0x010F;
+const MODEL_TAG: u16 = 0x0110;
+const DATE_TIME_TAG: u16 = 0x0132;
+/// The pointer to the IFD of the EXIF tags proper.
+const EXIF_IFD_TAG: u16 = 0x8769;
+const DATE_TIME_ORIGINAL_TAG: u16 = 0x9003;
+/// The type of the entries holding text.
+const ASCII: u16 = 2;
+
+/// The transforms that make a JPEG upright, from its EXIF orientation tag;
+/// phones store pictures as the sensor saw them and only tag them.
+pub fn exif_orientation(jpeg: &[u8]) -> (Option<Rotation>, Option<Flip>) {
+    match orientation_tag(jpeg) {
+        Some(2) => (None, Some(Flip::Horizontal)),
+        Some(3) => (Some(Rotation::Half), None),
+        Some(4) => (None, Some(Flip::Vertical)),
+        Some(5) => (Some(Rotation::Quarter), Some(Flip::Horizontal)),
+        Some(6) => (Some(Rotation::Quarter), None),
+        Some(7) => (Some(Rotation::ThreeQuarters), Some(Flip::Horizontal)),
+        Some(8) => (Some(Rotation::ThreeQuarters), None),
+        _ => (None, None),
+    }
+}
+
+/// What the EXIF data of a photo tells about it, for `--show-meta`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExifMeta {
+    /// When the picture was taken, `YYYY:MM:DD HH:MM:SS`.
+    pub date: Option<String>,
+    /// The make and the model of the camera.
+    pub camera: Option<String>,
+}
+
+/// The date and the camera of a JPEG from its EXIF data, the ones missing
+/// left out.
+pub fn exif_meta(jpeg: &[u8]) -> ExifMeta {
+    let tiff = match exif_tiff(jpeg).and_then(Tiff::new) {
+        Some(tiff) => tiff,
+        None => return ExifMeta::default(),
+    };
+    let ifd = tiff.first_ifd();
+    let exif_ifd = ifd
+        .and_then(|ifd| tiff.entry(ifd, EXIF_IFD_TAG))
+        .and_then(|entry| tiff.u32_at(entry + 8));
+
+    let date = exif_ifd
+        .and_then(|exif_ifd| tiff.ascii(exif_ifd as usize, DATE_TIME_ORIGINAL_TAG))
+        .or_else(|| tiff.ascii(ifd?, DATE_TIME_TAG));
+    let make = ifd.and_then(|ifd| tiff.ascii(ifd, MAKE_TAG));
+    let model = ifd.and_then(|ifd| tiff.ascii(ifd, MODEL_TAG));
+    let camera = match (make, model) {
+        // models often repeat the make, e.g. `Canon` `Canon EOS 5D`
+        (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    };
+
+    ExifMeta { date, camera }
+}
+
+/// Finds the APP1 segment holding the EXIF data, before the image data.
+fn exif_tiff(jpeg: &[u8]) -> Option<&[u8]> {
+    if !jpeg.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut rest = &jpeg[2..];
+    while rest.len() >= 4 && rest[0] == 0xFF {
+        let marker = rest[1];
+        let len = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+        // start of scan, the metadata is over
+        if marker == 0xDA || len < 2 {
+            return None;
+        }
+
+        let segment = rest.get(4..2 + len)?;
+        if marker == 0xE1 && segment.starts_with(b"Exif\0\0") {
+            return Some(&segment[6..]);
+        }
+        rest = &rest[2 + len..];
+    }
+
+    None
+}
+
+/// Looks the orientation up in the first IFD of the TIFF structure.
+fn orientation_tag(jpeg: &[u8]) -> Option<u16> {
+    let tiff = Tiff::new(exif_tiff(jpeg)?)?;
+    let entry = tiff.entry(tiff.first_ifd()?, ORIENTATION_TAG)?;
+    tiff.u16_at(entry + 8)
+}
+
+/// The TIFF structure of the EXIF data, in either byte order.
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let big_endian = match data.get(..2)? {
+            b"MM" => true,
+            b"II" => false,
+            _ => return None,
+        };
+
+        Some(Tiff { data, big_endian })
+    }
+
+    fn u16_at(&self, at: usize) -> Option<u16> {
+        let bytes = self.data.get(at..at + 2)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, at: usize) -> Option<u32> {
+        let bytes = self.data.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn first_ifd(&self) -> Option<usize> {
+        self.u32_at(4).map(|ifd| ifd as usize)
+    }
+
+    /// The offset of the 12 bytes entry of `tag` in the IFD at `ifd`.
+    fn entry(&self, ifd: usize, tag: u16) -> Option<usize> {
+        let entries = self.u16_at(ifd)? as usize;
+        (0..entries)
+            .map(|i| ifd + 2 + i * 12)
+            .find(|&entry| self.u16_at(entry) == Some(tag))
+    }
+
+    /// The text of the ASCII entry of `tag`, stored in the entry itself up
+    /// to 4 bytes, elsewhere past that.
+    fn ascii(&self, ifd: usize, tag: u16) -> Option<String> {
+        let entry = self.entry(ifd, tag)?;
+        if self.u16_at(entry + 2)? != ASCII {
+            return None;
+        }
+        let count = self.u32_at(entry + 4)? as usize;
+        let at = match count {
+            0..=4 => entry + 8,
+            _ => self.u32_at(entry + 8)? as usize,
+        };
+
+        let bytes = self.data.get(at..at.checked_add(count)?)?;
+        let text = String::from_utf8_lossy(bytes);
+        let text = text.trim_end_matches('\0').trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A JPEG of nothing but a little-endian EXIF segment, with the make
+    /// and the model in IFD0 and the date in the EXIF IFD.
+    fn jpeg_with_exif() -> Vec<u8> {
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend(8u32.to_le_bytes());
+
+        // IFD0 at 8: make, model, EXIF IFD pointer, then the next IFD
+        let ifd0_len = 2 + 3 * 12 + 4;
+        let make_at = 8 + ifd0_len;
+        let model_at = make_at + 6;
+        let exif_ifd_at = model_at + 8;
+        tiff.extend(3u16.to_le_bytes());
+        for (tag, kind, count, value) in [
+            (MAKE_TAG, ASCII, 6, make_at),
+            (MODEL_TAG, ASCII, 8, model_at),
+            (EXIF_IFD_TAG, 4, 1, exif_ifd_at),
+        ] {
+            tiff.extend(tag.to_le_bytes());
+            tiff.extend(kind.to_le_bytes());
+            tiff.extend((count as u32).to_le_bytes());
+            tiff.extend((value as u32).to_le_bytes());
+        }
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(b"Canon\0");
+        tiff.extend(b"EOS 5D\0\0");
+
+        // the EXIF IFD, with the date after it
+        let date_at = exif_ifd_at + 2 + 12 + 4;
+        tiff.extend(1u16.to_le_bytes());
+        tiff.extend(DATE_TIME_ORIGINAL_TAG.to_le_bytes());
+        tiff.extend(ASCII.to_le_bytes());
+        tiff.extend(20u32.to_le_bytes());
+        tiff.extend((date_at as u32).to_le_bytes());
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(b"2021:06:01 12:30:00\0");
+
+        let mut segment = b"Exif\0\0".to_vec();
+        segment.extend(tiff);
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend((segment.len() as u16 + 2).to_be_bytes());
+        jpeg.extend(segment);
+        jpeg.extend([0xFF, 0xDA, 0x00, 0x02]);
+        jpeg
+    }
+
+    #[test]
+    fn reads_the_date_and_the_camera() {
+        assert_eq!(
+            exif_meta(&jpeg_with_exif()),
+            ExifMeta {
+                date: Some("2021:06:01 12:30:00".into()),
+                camera: Some("Canon EOS 5D".into()),
+            }
+        );
+        assert_eq!(exif_orientation(&jpeg_with_exif()), (None, None));
+    }
+
+    #[test]
+    fn leaves_out_what_is_missing() {
+        assert_eq!(exif_meta(b"\x89PNG\r\n\x1a\n"), ExifMeta::default());
+    }
+}
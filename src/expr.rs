@@ -0,0 +1,145 @@
+use crate::bitmap::Bitmap;
+use crate::levels::Levels;
+use crate::rule::{OnOffRule, Rule, RuleCtx};
+use image::DynamicImage;
+use std::fmt;
+use std::sync::Arc;
+
+/// Rules combined with `!` (not), `&` (and) and `|` (or), e.g.
+/// `Threshold(200) & !Border(30,2)`.
+#[derive(Clone, Debug)]
+pub enum RuleExpr {
+    Rule(Arc<dyn Rule>),
+    Not(Box<RuleExpr>),
+    And(Box<RuleExpr>, Box<RuleExpr>),
+    Or(Box<RuleExpr>, Box<RuleExpr>),
+}
+
+impl RuleExpr {
+    /// Applies `f` to every rule in the expression, keeping the rules it
+    /// returns `None` for.
+    pub fn map_rules<F>(&self, f: F) -> RuleExpr
+    where
+        F: Fn(&dyn Rule) -> Option<Arc<dyn Rule>> + Copy,
+    {
+        match self {
+            RuleExpr::Rule(rule) => RuleExpr::Rule(f(&**rule).unwrap_or_else(|| rule.clone())),
+            RuleExpr::Not(e) => RuleExpr::Not(Box::new(e.map_rules(f))),
+            RuleExpr::And(a, b) => {
+                RuleExpr::And(Box::new(a.map_rules(f)), Box::new(b.map_rules(f)))
+            }
+            RuleExpr::Or(a, b) => RuleExpr::Or(Box::new(a.map_rules(f)), Box::new(b.map_rules(f))),
+        }
+    }
+
+    /// See [`Rule::resolve`].
+    pub fn resolve(&self, img: &DynamicImage) -> RuleExpr {
+        self.map_rules(|rule| rule.resolve(img))
+    }
+
+    /// Moves the threshold of every rule, see [`Rule::adjusted`].
+    pub fn adjusted(&self, delta: i32) -> RuleExpr {
+        self.map_rules(|rule| rule.adjusted(delta))
+    }
+
+    /// Changes the reach of every rule, see [`Rule::adjusted_reach`].
+    pub fn adjusted_reach(&self, delta: i32) -> RuleExpr {
+        self.map_rules(|rule| rule.adjusted_reach(delta))
+    }
+}
+
+impl Default for RuleExpr {
+    fn default() -> Self {
+        OnOffRule::LumaThreshold(128).into()
+    }
+}
+
+impl From<OnOffRule> for RuleExpr {
+    fn from(rule: OnOffRule) -> Self {
+        RuleExpr::Rule(Arc::new(rule))
+    }
+}
+
+/// Expressions are equal when they are formatted the same.
+impl PartialEq for RuleExpr {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string() == other.to_string()
+    }
+}
+
+impl Rule for RuleExpr {
+    fn is_on(&self, ctx: &RuleCtx, x: u32, y: u32) -> bool {
+        match self {
+            RuleExpr::Rule(rule) => rule.is_on(ctx, x, y),
+            RuleExpr::Not(e) => !e.is_on(ctx, x, y),
+            RuleExpr::And(a, b) => a.is_on(ctx, x, y) && b.is_on(ctx, x, y),
+            RuleExpr::Or(a, b) => a.is_on(ctx, x, y) || b.is_on(ctx, x, y),
+        }
+    }
+
+    /// The operands are evaluated over the whole image before being
+    /// combined, as some rules only make sense for a whole image.
+    fn evaluate(&self, ctx: &RuleCtx) -> Bitmap {
+        match self {
+            RuleExpr::Rule(rule) => rule.evaluate(ctx),
+            RuleExpr::Not(e) => {
+                let mut bitmap = e.evaluate(ctx);
+                bitmap.invert();
+                bitmap
+            }
+            RuleExpr::And(a, b) => {
+                let mut bitmap = a.evaluate(ctx);
+                bitmap.and(&b.evaluate(ctx));
+                bitmap
+            }
+            RuleExpr::Or(a, b) => {
+                let mut bitmap = a.evaluate(ctx);
+                bitmap.or(&b.evaluate(ctx));
+                bitmap
+            }
+        }
+    }
+
+    /// The bands of the first operand that has some, turned upside down
+    /// under `!`.
+    fn levels(&self, ctx: &RuleCtx) -> Option<Levels> {
+        match self {
+            RuleExpr::Rule(rule) => rule.levels(ctx),
+            RuleExpr::Not(e) => e.levels(ctx).map(|mut levels| {
+                levels.invert();
+                levels
+            }),
+            RuleExpr::And(a, b) | RuleExpr::Or(a, b) => a.levels(ctx).or_else(|| b.levels(ctx)),
+        }
+    }
+
+    /// The farthest reach of the operands.
+    fn reach(&self) -> Option<u32> {
+        match self {
+            RuleExpr::Rule(rule) => rule.reach(),
+            RuleExpr::Not(e) => e.reach(),
+            RuleExpr::And(a, b) | RuleExpr::Or(a, b) => Some(a.reach()?.max(b.reach()?)),
+        }
+    }
+}
+
+/// Formats the expression the way it is parsed, with the fewest parentheses.
+impl fmt::Display for RuleExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleExpr::Rule(rule) => write!(f, "{}", rule),
+            RuleExpr::Not(e) => match **e {
+                RuleExpr::Rule(_) | RuleExpr::Not(_) => write!(f, "!{}", e),
+                _ => write!(f, "!({})", e),
+            },
+            RuleExpr::And(a, b) => {
+                let operand = |e: &RuleExpr| match e {
+                    RuleExpr::Or(..) => format!("({})", e),
+                    e => e.to_string(),
+                };
+                write!(f, "{} & {}", operand(a), operand(b))
+            }
+            RuleExpr::Or(a, b) => write!(f, "{} | {}", a, b),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! The C interface, declared in `include/ascii_image.h`; regenerate the
+//! header with `cbindgen --config cbindgen.toml --output include/ascii_image.h`
+//! after changing it.
+
+use crate::Preprocess;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+use std::str::FromStr;
+
+/// The options of a render; the strings are parsed like the flags of the
+/// command line, and are left to the defaults when `NULL`, so a zeroed
+/// struct renders with the defaults.
+#[repr(C)]
+pub struct RenderOptions {
+    pub rule: *const c_char,
+    pub size: *const c_char,
+    pub charset: *const c_char,
+    pub color: *const c_char,
+    pub dither: *const c_char,
+    pub edges: *const c_char,
+    pub format: *const c_char,
+    pub contrast: f32,
+    pub invert: bool,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AsciiImageStatus {
+    Ok = 0,
+    /// `data`, `opts` or `out` is `NULL`.
+    NullArgument,
+    /// An option isn't valid UTF-8, or doesn't parse.
+    InvalidOption,
+    /// The data isn't an image in a format the `image` crate reads.
+    InvalidImage,
+    /// The text has a NUL byte, or the render panicked.
+    Internal,
+}
+
+/// Renders the image encoded in the `len` bytes at `data` with `opts`, and
+/// points `out` at the text, to be freed with [`ascii_image_free`]; `out`
+/// is left alone on errors. PNG output falls back to plain text.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, the strings of `opts` must be
+/// `NULL` or NUL terminated, and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn ascii_image_render(
+    data: *const u8,
+    len: usize,
+    opts: *const RenderOptions,
+    out: *mut *mut c_char,
+) -> AsciiImageStatus {
+    if data.is_null() || opts.is_null() || out.is_null() {
+        return AsciiImageStatus::NullArgument;
+    }
+    let bytes = slice::from_raw_parts(data, len);
+    let opts = match options(&*opts) {
+        Some(opts) => opts,
+        None => return AsciiImageStatus::InvalidOption,
+    };
+
+    let text = panic::catch_unwind(AssertUnwindSafe(|| {
+        image::load_from_memory(bytes).map(|img| crate::render(&img, &opts))
+    }));
+    let text = match text {
+        Ok(Ok(text)) => text,
+        Ok(Err(_)) => return AsciiImageStatus::InvalidImage,
+        Err(_) => return AsciiImageStatus::Internal,
+    };
+
+    match CString::new(text) {
+        Ok(text) => {
+            *out = text.into_raw();
+            AsciiImageStatus::Ok
+        }
+        Err(_) => AsciiImageStatus::Internal,
+    }
+}
+
+/// Frees a text of [`ascii_image_render`]; `NULL` is ignored.
+///
+/// # Safety
+///
+/// `text` must come from [`ascii_image_render`], and not be freed already.
+#[no_mangle]
+pub unsafe extern "C" fn ascii_image_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+unsafe fn options(opts: &RenderOptions) -> Option<crate::RenderOptions> {
+    let defaults = crate::RenderOptions::default();
+
+    Some(crate::RenderOptions {
+        size: parse(opts.size)?.unwrap_or(defaults.size),
+        rule: parse(opts.rule)?.unwrap_or(defaults.rule),
+        preprocess: Preprocess {
+            contrast: opts.contrast,
+            ..defaults.preprocess
+        },
+        color: parse(opts.color)?.unwrap_or(defaults.color),
+        dither: parse(opts.dither)?.unwrap_or(defaults.dither),
+        charset: parse(opts.charset)?.unwrap_or(defaults.charset),
+        edges: parse(opts.edges)?.unwrap_or(defaults.edges),
+        format: parse(opts.format)?.unwrap_or(defaults.format),
+        invert: opts.invert,
+        ..defaults
+    })
+}
+
+/// `None` when `s` doesn't parse, `Some(None)` when it's `NULL`.
+unsafe fn parse<T: FromStr>(s: *const c_char) -> Option<Option<T>> {
+    if s.is_null() {
+        return Some(None);
+    }
+
+    let s = CStr::from_ptr(s).to_str().ok()?;
+    T::from_str(s).ok().map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageOutputFormat, Rgb, RgbImage};
+    use std::ptr;
+
+    fn png() -> Vec<u8> {
+        let img = RgbImage::from_fn(8, 8, |x, _| Rgb([(x * 32) as u8; 3]));
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(img)
+            .write_to(&mut bytes, ImageOutputFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn zeroed() -> RenderOptions {
+        RenderOptions {
+            rule: ptr::null(),
+            size: ptr::null(),
+            charset: ptr::null(),
+            color: ptr::null(),
+            dither: ptr::null(),
+            edges: ptr::null(),
+            format: ptr::null(),
+            contrast: 0.0,
+            invert: false,
+        }
+    }
+
+    #[test]
+    fn renders_like_the_library() {
+        let bytes = png();
+        let rule = CString::new("Luma(100)").unwrap();
+        let opts = RenderOptions {
+            rule: rule.as_ptr(),
+            ..zeroed()
+        };
+
+        let mut out = ptr::null_mut();
+        let status = unsafe { ascii_image_render(bytes.as_ptr(), bytes.len(), &opts, &mut out) };
+        assert_eq!(status, AsciiImageStatus::Ok);
+
+        let expected = crate::render(
+            &image::load_from_memory(&bytes).unwrap(),
+            &crate::RenderOptions {
+                rule: "Luma(100)".parse().unwrap(),
+                ..crate::RenderOptions::default()
+            },
+        );
+        assert_eq!(unsafe { CStr::from_ptr(out) }.to_str().unwrap(), expected);
+        unsafe { ascii_image_free(out) };
+    }
+
+    #[test]
+    fn reports_invalid_input() {
+        let bytes = png();
+        let rule = CString::new("Nope(1)").unwrap();
+        let opts = RenderOptions {
+            rule: rule.as_ptr(),
+            ..zeroed()
+        };
+        let mut out = ptr::null_mut();
+
+        let status = unsafe { ascii_image_render(bytes.as_ptr(), bytes.len(), &opts, &mut out) };
+        assert_eq!(status, AsciiImageStatus::InvalidOption);
+
+        let status = unsafe { ascii_image_render(bytes.as_ptr(), 4, &zeroed(), &mut out) };
+        assert_eq!(status, AsciiImageStatus::InvalidImage);
+        assert!(out.is_null());
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{AsciiImage, RuleExpr};
+use image::{DynamicImage, GenericImageView};
+
+/// The furthest a threshold is moved either way, the range of `Px`.
+const REACH: i32 = 765;
+
+/// `rule` with its threshold moved so that about `target` of the pixels of
+/// `img`, in `0..=1`, are on. The threshold is bisected, the share of the
+/// pixels on going the same way all along as it moves; rules without one
+/// are left as they are.
+pub fn fill_rule(img: &DynamicImage, rule: &RuleExpr, target: f64) -> RuleExpr {
+    let rule = rule.resolve(img);
+    let (width, height) = img.dimensions();
+    let pixels = (width as u64 * height as u64).max(1) as f64;
+    let fill = |delta: i32| {
+        let on = AsciiImage::new(img, &rule.adjusted(delta))
+            .bitmap()
+            .count_ones();
+        on as f64 / pixels
+    };
+
+    // thresholds going up turn pixels off, the offset of `Adaptive` or a
+    // `!` turn them on
+    let sign = match fill(-REACH) >= fill(REACH) {
+        true => 1,
+        false => -1,
+    };
+
+    // the first step with at most the target on
+    let (mut low, mut high) = (-REACH, REACH);
+    while low < high {
+        let mid = (low + high).div_euclid(2);
+        if fill(sign * mid) > target {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    // or the one before it, when it comes closer
+    let miss = |delta: i32| (fill(sign * delta) - target).abs();
+    let best = match low > -REACH && miss(low - 1) < miss(low) {
+        true => low - 1,
+        false => low,
+    };
+
+    rule.adjusted(sign * best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GrayImage;
+
+    fn gradient() -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(256, 1, |x, _| image::Luma([x as u8])))
+    }
+
+    #[test]
+    fn moves_the_threshold_to_the_target() {
+        let rule: RuleExpr = "Luma(128)".parse().unwrap();
+        assert_eq!(fill_rule(&gradient(), &rule, 0.25).to_string(), "Luma(192)");
+
+        let rule: RuleExpr = "Otsu".parse().unwrap();
+        assert_eq!(fill_rule(&gradient(), &rule, 0.75).to_string(), "Luma(64)");
+    }
+
+    #[test]
+    fn moves_negated_thresholds_the_other_way() {
+        let rule: RuleExpr = "!Luma(128)".parse().unwrap();
+        assert_eq!(fill_rule(&gradient(), &rule, 0.25).to_string(), "!Luma(64)");
+    }
+}
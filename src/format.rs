@@ -0,0 +1,71 @@
+use crate::raster::PngOptions;
+use crate::svg::SvgOptions;
+use crate::terminal::detect_format;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The document format the rendered cells are written as.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    /// Lines of text, colored with ANSI escapes.
+    #[default]
+    Text,
+    /// An HTML `<pre>` block, colored with inline styles.
+    Html,
+    /// An SVG document with the characters (or braille dots) laid out on a
+    /// grid.
+    Svg(SvgOptions),
+    /// The grid of cells as JSON, with the character, the bitmask of the on
+    /// pixels and the average color of every cell.
+    Json,
+    /// The on pixels as sixel graphics, for the terminals that show them.
+    Sixel,
+    /// The on pixels as an image shown with the graphics protocol of kitty.
+    Kitty,
+    /// The on pixels as an image shown with the inline images of iTerm2.
+    Iterm2,
+    /// A PNG image with the characters drawn in an embedded 8x8 font.
+    Png(PngOptions),
+    /// The frames of an animation as an asciicast (asciinema v2) file, see
+    /// [`Recorder`](crate::Recorder).
+    Asciicast,
+    /// The frames of an animation drawn over each other with ANSI escapes,
+    /// with their delays, to be replayed with `cat`.
+    AnsiDump,
+}
+
+impl OutputFormat {
+    /// Whether the format records the frames of animations, rather than
+    /// the first one.
+    pub fn records(&self) -> bool {
+        matches!(self, OutputFormat::Asciicast | OutputFormat::AnsiDump)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum OutputFormatParseError {
+    #[error("unknown output format `{0}`")]
+    UnknownFormat(String),
+}
+
+impl FromStr for OutputFormat {
+    type Err = OutputFormatParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "html" => Ok(OutputFormat::Html),
+            "svg" => Ok(OutputFormat::Svg(SvgOptions::default())),
+            "json" => Ok(OutputFormat::Json),
+            "sixel" => Ok(OutputFormat::Sixel),
+            "kitty" => Ok(OutputFormat::Kitty),
+            "iterm2" => Ok(OutputFormat::Iterm2),
+            // picked once, from the environment of the terminal
+            "auto" => Ok(detect_format()),
+            "png" => Ok(OutputFormat::Png(PngOptions::default())),
+            "asciicast" => Ok(OutputFormat::Asciicast),
+            "ansi-dump" => Ok(OutputFormat::AnsiDump),
+            _ => Err(OutputFormatParseError::UnknownFormat(s.into())),
+        }
+    }
+}
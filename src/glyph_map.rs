@@ -0,0 +1,154 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A user-defined charset, read from a TOML file like:
+///
+/// ```toml
+/// cell = "2x2"
+///
+/// # glyphs for exact on/off patterns, as one digit per pixel, row by row
+/// # from the top left
+/// [patterns]
+/// "1100" = "▀"
+/// "0011" = "▄"
+///
+/// # glyphs for the other cells, by the fraction of pixels that are on;
+/// # the entry with the largest `min` not above the fill ratio is used
+/// [[fill]]
+/// min = 0.0
+/// glyph = " "
+///
+/// [[fill]]
+/// min = 0.5
+/// glyph = "▒"
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlyphMap {
+    cell_width: u32,
+    cell_height: u32,
+    patterns: HashMap<u32, char>,
+    fill: Vec<(f32, char)>,
+}
+
+#[derive(Deserialize)]
+struct GlyphMapFile {
+    cell: Option<String>,
+    #[serde(default)]
+    patterns: HashMap<String, String>,
+    #[serde(default)]
+    fill: Vec<FillEntry>,
+}
+
+#[derive(Deserialize)]
+struct FillEntry {
+    min: f32,
+    glyph: String,
+}
+
+#[derive(Error, Debug)]
+pub enum GlyphMapError {
+    #[error("couldn't read the glyph map")]
+    Io(#[from] std::io::Error),
+    #[error("invalid glyph map")]
+    Toml(#[from] toml::de::Error),
+    #[error("invalid cell size `{0}`, expected WxH with at most 32 pixels")]
+    InvalidCell(String),
+    #[error("invalid pattern `{0}`, expected {1} digits of 0 or 1")]
+    InvalidPattern(String, u32),
+    #[error("glyph `{0}` must be exactly one character")]
+    InvalidGlyph(String),
+}
+
+impl GlyphMap {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, GlyphMapError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    pub fn cell_size(&self) -> (u32, u32) {
+        (self.cell_width, self.cell_height)
+    }
+
+    /// The glyph for a cell, given the bitmask of its on pixels (row by row
+    /// from the top left pixel at bit 0).
+    pub fn glyph(&self, mask: u32) -> char {
+        if let Some(&ch) = self.patterns.get(&mask) {
+            return ch;
+        }
+
+        let ratio = mask.count_ones() as f32 / (self.cell_width * self.cell_height) as f32;
+        self.fill
+            .iter()
+            .take_while(|&&(min, _)| min <= ratio)
+            .last()
+            .map_or(' ', |&(_, ch)| ch)
+    }
+}
+
+fn single_char(s: &str) -> Result<char, GlyphMapError> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => Ok(ch),
+        _ => Err(GlyphMapError::InvalidGlyph(s.into())),
+    }
+}
+
+impl FromStr for GlyphMap {
+    type Err = GlyphMapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file: GlyphMapFile = toml::from_str(s)?;
+
+        let (cell_width, cell_height) = match &file.cell {
+            None => (2, 4),
+            Some(cell) => {
+                let invalid = || GlyphMapError::InvalidCell(cell.clone());
+                let (w, h) = cell.split_once('x').ok_or_else(invalid)?;
+                let w = u32::from_str(w).map_err(|_| invalid())?;
+                let h = u32::from_str(h).map_err(|_| invalid())?;
+                if w == 0 || h == 0 || w * h > 32 {
+                    return Err(invalid());
+                }
+                (w, h)
+            }
+        };
+        let pixels = cell_width * cell_height;
+
+        let patterns = file
+            .patterns
+            .iter()
+            .map(|(pattern, glyph)| {
+                if pattern.len() != pixels as usize
+                    || !pattern.chars().all(|c| c == '0' || c == '1')
+                {
+                    return Err(GlyphMapError::InvalidPattern(pattern.clone(), pixels));
+                }
+                let mask = pattern
+                    .chars()
+                    .enumerate()
+                    .filter(|&(_, c)| c == '1')
+                    .fold(0, |mask, (bit, _)| mask | 1 << bit);
+
+                Ok((mask, single_char(glyph)?))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let mut fill = file
+            .fill
+            .iter()
+            .map(|entry| Ok((entry.min, single_char(&entry.glyph)?)))
+            .collect::<Result<Vec<_>, GlyphMapError>>()?;
+        fill.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        Ok(GlyphMap {
+            cell_width,
+            cell_height,
+            patterns,
+            fill,
+        })
+    }
+}
@@ -0,0 +1,85 @@
+//! The inline image protocols of kitty and iTerm2, both sent a PNG.
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Kitty takes the data in chunks of at most this many bytes.
+const KITTY_CHUNK: usize = 4096;
+
+fn base64(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
+/// Displays the PNG `png` at the cursor with the kitty graphics protocol.
+pub(crate) fn kitty(png: &[u8]) -> String {
+    let data = base64(png);
+    let mut out = String::with_capacity(data.len() + data.len() / KITTY_CHUNK * 16 + 32);
+
+    let chunks = data.as_bytes().chunks(KITTY_CHUNK);
+    let last = chunks.len().saturating_sub(1);
+    for (i, chunk) in chunks.enumerate() {
+        // `q=2` keeps the terminal from answering
+        let keys = if i == 0 { "a=T,f=100,q=2," } else { "" };
+        let more = (i < last) as u8;
+        out.push_str(&format!("\x1b_G{}m={};", keys, more));
+        out.push_str(std::str::from_utf8(chunk).unwrap());
+        out.push_str("\x1b\\");
+    }
+
+    out.push('\n');
+    out
+}
+
+/// Displays the PNG `png` at the cursor with the inline images of iTerm2.
+pub(crate) fn iterm2(png: &[u8]) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={}:{}\x07\n",
+        png.len(),
+        base64(png)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_base64() {
+        let encoded: Vec<_> = ["", "f", "fo", "foo", "foob", "fooba", "foobar"]
+            .iter()
+            .map(|s| base64(s.as_bytes()))
+            .collect();
+
+        assert_eq!(
+            encoded,
+            ["", "Zg==", "Zm8=", "Zm9v", "Zm9vYg==", "Zm9vYmE=", "Zm9vYmFy"]
+        );
+    }
+
+    #[test]
+    fn splits_kitty_chunks() {
+        let out = kitty(&[0; 4000]);
+        let chunks: Vec<_> = out.trim_end().split("\x1b\\").collect();
+
+        // 4000 bytes are 5336 in base64, the last chunk empty after the split
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[0].starts_with("\x1b_Ga=T,f=100,q=2,m=1;"));
+        assert!(chunks[1].starts_with("\x1b_Gm=0;"));
+        assert_eq!(chunks[2], "");
+    }
+}
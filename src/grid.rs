@@ -0,0 +1,101 @@
+use crate::cell::Cell;
+use crate::par;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// What becomes of the pixels at the right and bottom edges of an image
+/// whose size isn't a multiple of the cell size.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum EdgePolicy {
+    /// Emit the partial cells, the pixels past the image being off.
+    #[default]
+    Pad,
+    /// Leave the partial cells out.
+    Crop,
+}
+
+#[derive(Error, Debug)]
+pub enum EdgePolicyParseError {
+    #[error("unknown edge policy `{0}`")]
+    UnknownEdgePolicy(String),
+}
+
+impl FromStr for EdgePolicy {
+    type Err = EdgePolicyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pad" => Ok(EdgePolicy::Pad),
+            "crop" => Ok(EdgePolicy::Crop),
+            _ => Err(EdgePolicyParseError::UnknownEdgePolicy(s.into())),
+        }
+    }
+}
+
+/// The columns and rows of cells covering an image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CellGrid {
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl CellGrid {
+    pub fn new(
+        (width, height): (u32, u32),
+        (cell_width, cell_height): (u32, u32),
+        edges: EdgePolicy,
+    ) -> Self {
+        let cells = |pixels: u32, cell: u32| match edges {
+            EdgePolicy::Pad => pixels.div_ceil(cell),
+            EdgePolicy::Crop => pixels / cell,
+        };
+
+        CellGrid {
+            columns: cells(width, cell_width),
+            rows: cells(height, cell_height),
+        }
+    }
+
+    /// The cells `f` builds for every (column, row), the rows built in
+    /// parallel.
+    pub fn build<F>(&self, f: F) -> Vec<Vec<Cell>>
+    where
+        F: Fn(u32, u32) -> Cell + Sync,
+    {
+        par::map_rows(self.rows, |y| (0..self.columns).map(|x| f(x, y)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_multiples_have_no_extra_cells() {
+        for &edges in &[EdgePolicy::Pad, EdgePolicy::Crop] {
+            let grid = CellGrid::new((8, 12), (2, 4), edges);
+            assert_eq!((grid.columns, grid.rows), (4, 3));
+        }
+    }
+
+    #[test]
+    fn partial_cells_are_padded_or_cropped() {
+        let grid = CellGrid::new((9, 13), (2, 4), EdgePolicy::Pad);
+        assert_eq!((grid.columns, grid.rows), (5, 4));
+
+        let grid = CellGrid::new((9, 13), (2, 4), EdgePolicy::Crop);
+        assert_eq!((grid.columns, grid.rows), (4, 3));
+    }
+
+    #[test]
+    fn builds_rows_of_columns() {
+        let cells = CellGrid::new((3, 2), (1, 1), EdgePolicy::Pad)
+            .build(|x, y| Cell::new(std::char::from_digit(y * 3 + x, 10).unwrap()));
+        let text: Vec<String> = cells
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.ch).collect())
+            .collect();
+
+        assert_eq!(text, ["012", "345"]);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::bitmap::Bitmap;
+use crate::braille::region_braille;
+use crate::rule::otsu_threshold;
+use image::DynamicImage;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The channels a histogram is drawn of.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HistogramChannels {
+    /// The luma the rules threshold, with the threshold Otsu picks marked.
+    Luma,
+    /// Red, green and blue, one chart each.
+    Rgb,
+}
+
+#[derive(Error, Debug)]
+pub enum HistogramChannelsParseError {
+    #[error("unknown histogram channels `{0}`, expected luma or rgb")]
+    UnknownChannels(String),
+}
+
+impl FromStr for HistogramChannels {
+    type Err = HistogramChannelsParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "luma" => Ok(HistogramChannels::Luma),
+            "rgb" => Ok(HistogramChannels::Rgb),
+            _ => Err(HistogramChannelsParseError::UnknownChannels(s.into())),
+        }
+    }
+}
+
+/// The charts drawn by [`render_histogram`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HistogramOptions {
+    pub channels: HistogramChannels,
+    /// Columns of cells of every chart, two bars each.
+    pub width: u32,
+    /// Rows of cells of every chart, four dots each.
+    pub height: u32,
+}
+
+impl Default for HistogramOptions {
+    fn default() -> Self {
+        HistogramOptions {
+            channels: HistogramChannels::Luma,
+            width: 64,
+            height: 8,
+        }
+    }
+}
+
+/// Draws the histogram of the channels of `img` as braille bar charts,
+/// each under its name and over an axis from 0 to 255; the bars are scaled
+/// to the highest of their chart.
+pub fn render_histogram(img: &DynamicImage, opts: &HistogramOptions) -> String {
+    let mut out = String::new();
+    match opts.channels {
+        HistogramChannels::Luma => {
+            let mut counts = [0u64; 256];
+            img.to_luma8()
+                .pixels()
+                .for_each(|p| counts[p[0] as usize] += 1);
+            let threshold = otsu_threshold(img);
+
+            out.push_str(&format!("luma, Otsu threshold {}\n", threshold));
+            write_chart(&counts, opts, Some(threshold), &mut out);
+        }
+        HistogramChannels::Rgb => {
+            let mut counts = [[0u64; 256]; 3];
+            img.to_rgb8().pixels().for_each(|p| {
+                for (channel, &v) in p.0.iter().enumerate() {
+                    counts[channel][v as usize] += 1;
+                }
+            });
+
+            for (name, counts) in ["red", "green", "blue"].iter().zip(&counts) {
+                out.push_str(name);
+                out.push('\n');
+                write_chart(counts, opts, None, &mut out);
+            }
+        }
+    }
+
+    out
+}
+
+/// Writes the bars of `counts`, then the axis beneath them with `mark`
+/// pointed at.
+fn write_chart(counts: &[u64; 256], opts: &HistogramOptions, mark: Option<u8>, out: &mut String) {
+    let (width, height) = (opts.width.max(1), opts.height.max(1));
+    let (bars, dots) = (width * 2, height * 4);
+
+    let mut bins = vec![0u64; bars as usize];
+    for (v, &n) in counts.iter().enumerate() {
+        bins[v * bars as usize / 256] += n;
+    }
+    let highest = bins.iter().copied().max().unwrap_or(0).max(1);
+    // rounded up, so that no value present goes without a dot
+    let bar_heights: Vec<u64> = bins
+        .iter()
+        .map(|&n| (n * dots as u64).div_ceil(highest))
+        .collect();
+
+    let bitmap = Bitmap::from_fn(bars, dots, |x, y| {
+        ((dots - y) as u64) <= bar_heights[x as usize]
+    });
+    for y in 0..height {
+        out.extend((0..width).map(|x| region_braille(&bitmap, x, y)));
+        out.push('\n');
+    }
+
+    out.push_str(&axis(width, mark));
+    out.push('\n');
+}
+
+/// The axis under a chart `width` cells wide: its ends labeled when there
+/// is room, and a `^` under the cell of `mark`.
+fn axis(width: u32, mark: Option<u8>) -> String {
+    let mut axis = vec![' '; width as usize];
+    if width >= 5 {
+        axis[0] = '0';
+        axis[width as usize - 3..].copy_from_slice(&['2', '5', '5']);
+    }
+    if let Some(mark) = mark {
+        axis[mark as usize * width as usize / 256] = '^';
+    }
+
+    axis.into_iter().collect::<String>().trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GrayImage;
+
+    #[test]
+    fn draws_the_bars_of_the_values_present() {
+        let img = DynamicImage::ImageLuma8(GrayImage::from_raw(3, 1, vec![0, 0, 255]).unwrap());
+        let opts = HistogramOptions {
+            width: 2,
+            height: 1,
+            ..HistogramOptions::default()
+        };
+
+        // the 0s fill the first bar, the 255 half of the last one
+        assert_eq!(
+            render_histogram(&img, &opts),
+            "luma, Otsu threshold 1\n\u{2847}\u{28a0}\n^\n"
+        );
+    }
+
+    #[test]
+    fn labels_the_ends_of_the_axis() {
+        assert_eq!(axis(8, None), "0    255");
+        assert_eq!(axis(8, Some(128)), "0   ^255");
+        assert_eq!(axis(2, None), "");
+    }
+}
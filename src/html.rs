@@ -0,0 +1,61 @@
+use crate::cell::Cell;
+use image::Rgb;
+use std::fmt::Write;
+
+fn css_color(c: Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", c[0], c[1], c[2])
+}
+
+fn style(cell: &Cell) -> String {
+    let mut style = String::new();
+    if let Some(fg) = cell.fg {
+        write!(style, "color:{};", css_color(fg)).unwrap();
+    }
+    if let Some(bg) = cell.bg {
+        write!(style, "background-color:{};", css_color(bg)).unwrap();
+    }
+
+    style
+}
+
+fn push_escaped(out: &mut String, ch: char) {
+    match ch {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        ch => out.push(ch),
+    }
+}
+
+/// Writes the cells as a `<pre>` block, merging runs of cells with the same
+/// colors into a single `<span>`.
+pub(crate) fn write(cells: &[Vec<Cell>]) -> String {
+    let mut out = String::from("<pre style=\"font-family:monospace;line-height:1\">\n");
+
+    for row in cells {
+        let mut start = 0;
+        while start < row.len() {
+            let len = row[start..]
+                .iter()
+                .take_while(|c| c.fg == row[start].fg && c.bg == row[start].bg)
+                .count();
+
+            let style = style(&row[start]);
+            if !style.is_empty() {
+                write!(out, "<span style=\"{}\">", style).unwrap();
+            }
+            row[start..start + len]
+                .iter()
+                .for_each(|cell| push_escaped(&mut out, cell.ch));
+            if !style.is_empty() {
+                out.push_str("</span>");
+            }
+
+            start += len;
+        }
+        out.push('\n');
+    }
+
+    out.push_str("</pre>\n");
+    out
+}
@@ -0,0 +1,88 @@
+use crate::cell::Cell;
+use image::Rgb;
+use std::fmt::Write;
+
+/// Appends `s` as a JSON string.
+pub(crate) fn push_string(out: &mut String, s: &str) {
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            ch if ch.is_control() => write!(out, "\\u{:04x}", ch as u32).unwrap(),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+/// Writes the cells as a JSON object, a row of cells per line:
+///
+/// ```json
+/// {"columns":2,"rows":1,"cell_size":[2,4],"cells":[
+/// [{"char":"⣿","codepoint":10495,"mask":255,"color":[12,34,56]},...]
+/// ]}
+/// ```
+///
+/// `cell` gives the bitmask of the on pixels of the cell at (column, row),
+/// row by row from the top left pixel at bit 0, and its average color.
+pub(crate) fn write<F>(cells: &[Vec<Cell>], cell_size: (u32, u32), cell: F) -> String
+where
+    F: Fn(u32, u32) -> (u32, Rgb<u8>),
+{
+    let columns = cells.first().map_or(0, Vec::len);
+    let mut out = format!(
+        "{{\"columns\":{},\"rows\":{},\"cell_size\":[{},{}],\"cells\":[\n",
+        columns,
+        cells.len(),
+        cell_size.0,
+        cell_size.1
+    );
+
+    for (y, row) in cells.iter().enumerate() {
+        out.push('[');
+        for (x, c) in row.iter().enumerate() {
+            let (mask, color) = cell(x as u32, y as u32);
+            if x > 0 {
+                out.push(',');
+            }
+
+            out.push_str("{\"char\":");
+            push_string(&mut out, c.ch.encode_utf8(&mut [0; 4]));
+            write!(
+                out,
+                ",\"codepoint\":{},\"mask\":{},\"color\":[{},{},{}]}}",
+                c.ch as u32, mask, color[0], color[1], color[2]
+            )
+            .unwrap();
+        }
+        out.push(']');
+        if y + 1 < cells.len() {
+            out.push(',');
+        }
+        out.push('\n');
+    }
+
+    out.push_str("]}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_characters() {
+        let cells = vec![vec![Cell::new('"'), Cell::new('\\'), Cell::new('\n')]];
+        let json = write(&cells, (1, 1), |x, _| (x, Rgb([x as u8, 0, 255])));
+
+        assert_eq!(
+            json,
+            "{\"columns\":3,\"rows\":1,\"cell_size\":[1,1],\"cells\":[\n\
+             [{\"char\":\"\\\"\",\"codepoint\":34,\"mask\":0,\"color\":[0,0,255]},\
+             {\"char\":\"\\\\\",\"codepoint\":92,\"mask\":1,\"color\":[1,0,255]},\
+             {\"char\":\"\\u000a\",\"codepoint\":10,\"mask\":2,\"color\":[2,0,255]}]\n\
+             ]}\n"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+/// The band of every pixel of an image, for the rules that split it into
+/// more than on and off, row by row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Levels {
+    width: u32,
+    height: u32,
+    /// How many bands there are, the bands being `0..count`.
+    count: u8,
+    bands: Vec<u8>,
+}
+
+impl Levels {
+    /// The pixels in the bands `f` returns for them, which must be less
+    /// than `count`.
+    pub fn from_fn<F>(width: u32, height: u32, count: u8, f: F) -> Self
+    where
+        F: Fn(u32, u32) -> u8,
+    {
+        let bands = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y))
+            .collect();
+
+        Levels {
+            width,
+            height,
+            count,
+            bands,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn count(&self) -> u8 {
+        self.count
+    }
+
+    /// The band of the pixel at (`x`, `y`); pixels outside are in the
+    /// lowest one.
+    pub fn get(&self, x: u32, y: u32) -> u8 {
+        if x >= self.width || y >= self.height {
+            return 0;
+        }
+
+        self.bands[(y * self.width + x) as usize]
+    }
+
+    /// Turns the bands upside down, the lowest one becoming the highest.
+    pub fn invert(&mut self) {
+        let top = self.count.saturating_sub(1);
+        self.bands.iter_mut().for_each(|band| *band = top - *band);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AsciiImage, Charset, RuleExpr};
+    use image::{DynamicImage, GrayImage, Luma};
+
+    #[test]
+    fn posterizes() {
+        // cells of every quarter of the luma
+        let img = DynamicImage::ImageLuma8(GrayImage::from_fn(8, 4, |x, _| {
+            Luma([x as u8 / 2 * 64 + 10])
+        }));
+        let rule: RuleExpr = "Bands(4)".parse().unwrap();
+        let ascii = AsciiImage::new(&img, &rule);
+
+        assert_eq!(ascii.levels().map(|levels| levels.get(7, 0)), Some(3));
+        assert_eq!(ascii.bitmap().bits(0, 0, 8), 0b1111_1100);
+        let ramp = Charset::Ascii {
+            ramp: " .:-=+*#%@".chars().collect(),
+        };
+        assert_eq!(ascii.to_text(&ramp), " -*@\n");
+        assert_eq!(ascii.inverted().to_text(&ramp), "@*- \n");
+    }
+}
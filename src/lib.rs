@@ -0,0 +1,1069 @@
+use image::{DynamicImage, GenericImageView, Pixel, Rgb, RgbImage, Rgba, RgbaImage};
+use std::borrow::Cow;
+use std::fmt;
+use std::time::Duration;
+
+mod alpha;
+mod animation;
+mod bitmap;
+mod blocks;
+mod braille;
+mod canny;
+mod cell;
+mod charset;
+mod chroma;
+#[cfg(feature = "clipboard")]
+mod clipboard;
+mod color;
+mod compare;
+mod config;
+mod decorate;
+mod delta;
+mod dither;
+mod escape;
+mod exif;
+mod expr;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod fill;
+mod format;
+mod glyph_map;
+mod graphics;
+mod grid;
+mod histogram;
+mod html;
+mod json;
+mod levels;
+mod lines;
+mod mask;
+mod montage;
+mod morph;
+#[cfg(feature = "net")]
+mod net;
+#[cfg(feature = "color")]
+mod palette;
+mod par;
+mod parser;
+#[cfg(feature = "pdf")]
+mod pdf;
+mod pixel_art;
+mod preprocess;
+#[cfg(feature = "python")]
+mod python;
+mod quiet_zone;
+mod raster;
+mod raw;
+mod record;
+mod region;
+mod registry;
+mod rule;
+mod screen;
+#[cfg(feature = "serve")]
+mod serve;
+mod shade;
+mod sixel;
+mod size;
+mod stats;
+mod stream;
+mod svg;
+mod terminal;
+mod text;
+mod threshold;
+mod trim;
+#[cfg(feature = "tui")]
+mod tuner;
+#[cfg(feature = "svg-input")]
+mod vector;
+#[cfg(feature = "video")]
+mod video;
+#[cfg(feature = "tui")]
+mod viewer;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use alpha::{AlphaPolicy, AlphaPolicyParseError};
+pub use animation::{
+    decode_gif, Frame, FrameRange, FrameRangeParseError, FrameSelection, Playback, Selected,
+};
+pub use bitmap::Bitmap;
+pub use cell::Cell;
+pub use charset::{Charset, CharsetParseError, CHARSETS, DEFAULT_RAMP};
+pub use chroma::ChromaKey;
+#[cfg(feature = "clipboard")]
+pub use clipboard::{read_clipboard_image, write_clipboard_text, ClipboardError};
+#[cfg(feature = "color")]
+pub use color::strip_ansi;
+pub use color::{
+    parse_hex_color, Background, BackgroundParseError, ColorMode, ColorModeParseError, ColorSample,
+    ColorSampleParseError, HexColorParseError,
+};
+pub use compare::Comparison;
+pub use config::{builtin_preset, Config, ConfigError, Settings, BUILTIN_PRESETS};
+pub use decorate::{
+    Caption, CaptionPosition, CaptionPositionParseError, Decorations, FrameStyle,
+    FrameStyleParseError,
+};
+pub use dither::{Dither, DitherParseError, DITHERS};
+pub use escape::{Escape, EscapeParseError};
+pub use exif::{exif_meta, exif_orientation, ExifMeta};
+pub use expr::RuleExpr;
+pub use fill::fill_rule;
+pub use format::{OutputFormat, OutputFormatParseError};
+pub use glyph_map::{GlyphMap, GlyphMapError};
+pub use grid::{CellGrid, EdgePolicy, EdgePolicyParseError};
+pub use histogram::{
+    render_histogram, HistogramChannels, HistogramChannelsParseError, HistogramOptions,
+};
+pub use levels::Levels;
+pub use mask::Mask;
+pub use montage::{
+    render_contact_sheet, render_montage, render_sweep, ContactSheet, ContactSheetParseError,
+    Montage, MontageParseError, Sweep, SweepParseError,
+};
+pub use morph::{Morph, MorphOp, MorphParseError};
+#[cfg(feature = "net")]
+pub use net::{fetch, FetchError, FetchOptions};
+#[cfg(feature = "color")]
+pub use palette::{
+    builtin_palette, ColorMetric, ColorMetricParseError, Palette, PaletteError, BUILTIN_PALETTES,
+};
+pub use parser::RuleExprParseError;
+#[cfg(feature = "pdf")]
+pub use pdf::{is_pdf, rasterize_pdf, PdfError, PdfOptions};
+pub use pixel_art::{detect_grid, PixelGrid, PixelGridParseError};
+pub use preprocess::{
+    orient, Crop, CropParseError, Flip, FlipParseError, Preprocess, Rotation, RotationParseError,
+};
+pub use raster::PngOptions;
+pub use raw::{PixelLayout, RawError, RawImage, RawImageParseError};
+pub use record::{record, Recorder};
+pub use region::{Region, RegionParseError};
+pub use registry::{register_rule, rule_help, RuleConstructor};
+pub use rule::{otsu_threshold, AdaptiveKernel, OnOffRule, Rule, RuleCtx};
+#[cfg(feature = "serve")]
+pub use serve::{serve, ServeOptions};
+pub use shade::{Shade, ShadeParseError};
+pub use size::{
+    CellAspect, CellAspectParseError, ImageSize, ImageSizeParseError, ResizeFilter,
+    ResizeFilterParseError,
+};
+pub use stats::{render_stats, LumaSummary, RenderStats, RuleStats};
+pub use stream::{render_streamed, StreamError};
+pub use svg::SvgOptions;
+pub use terminal::{
+    detect_capabilities, detect_cell_aspect, detect_format, detect_synchronized_output,
+    Capabilities,
+};
+pub use threshold::{threshold_luma, threshold_luma_scalar};
+#[cfg(feature = "tui")]
+pub use tuner::Tuner;
+#[cfg(feature = "svg-input")]
+pub use vector::{is_svg, rasterize_svg, SvgInputError};
+#[cfg(feature = "video")]
+pub use video::{is_video, Video, VideoError};
+#[cfg(feature = "tui")]
+pub use viewer::Viewer;
+
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    pub size: ImageSize,
+    /// The shape of the terminal cells the output is shown in.
+    pub cell_aspect: CellAspect,
+    pub filter: ResizeFilter,
+    /// Scale the image as pixel art on the given grid, by whole factors
+    /// and with no blending, instead of resizing it with the filter.
+    pub pixel_art: Option<PixelGrid>,
+    /// Blank cells left around the output on every side, for QR codes to
+    /// scan.
+    pub quiet_zone: u32,
+    pub decorations: Decorations,
+    pub rule: RuleExpr,
+    /// Move the threshold of the rule so that about this share of the
+    /// pixels, in `0..=1`, end up on, for photos exposed differently to
+    /// look alike; see [`fill_rule`].
+    pub target_fill: Option<f64>,
+    /// Parts of the image turned on by rules of their own instead.
+    pub regions: Vec<Region>,
+    /// The stencil the pixels on are kept under.
+    pub mask: Option<Mask>,
+    pub chroma_key: Option<ChromaKey>,
+    /// Erosions and dilations of the pixels on after the rule, in turn.
+    pub morph: Vec<Morph>,
+    /// Whether the pixels are turned on by the rule or by the brightness of
+    /// their cell.
+    pub shade: Shade,
+    pub preprocess: Preprocess,
+    /// Crop the image to its on pixels before resizing it, for small
+    /// subjects on large canvases.
+    pub auto_crop: bool,
+    pub color: ColorMode,
+    /// How the color of a cell is derived from its pixels.
+    pub color_sample: ColorSample,
+    /// The background of the cells in color modes.
+    pub background: Background,
+    /// The colors the output colors are quantized to.
+    #[cfg(feature = "color")]
+    pub palette: Option<Palette>,
+    /// Spread the error of the colors quantized to the palette over the
+    /// next cells.
+    #[cfg(feature = "color")]
+    pub palette_dither: bool,
+    pub dither: Dither,
+    pub alpha: AlphaPolicy,
+    pub charset: Charset,
+    /// Whether the partial cells at the right and bottom edges are emitted.
+    pub edges: EdgePolicy,
+    /// Leave out the blank rows and columns around the text, HTML, SVG and
+    /// PNG outputs, and the blank cells ending the lines of text.
+    pub trim: bool,
+    pub format: OutputFormat,
+    /// Swap the on and off pixels, whatever the rule; transparent pixels
+    /// stay off.
+    pub invert: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            size: ImageSize::Default,
+            cell_aspect: CellAspect::default(),
+            filter: ResizeFilter::default(),
+            pixel_art: None,
+            quiet_zone: 0,
+            decorations: Decorations::default(),
+            rule: RuleExpr::default(),
+            target_fill: None,
+            regions: Vec::new(),
+            mask: None,
+            chroma_key: None,
+            morph: Vec::new(),
+            shade: Shade::Threshold,
+            preprocess: Preprocess::default(),
+            auto_crop: false,
+            color: ColorMode::None,
+            color_sample: ColorSample::Average,
+            background: Background::Transparent,
+            #[cfg(feature = "color")]
+            palette: None,
+            #[cfg(feature = "color")]
+            palette_dither: false,
+            dither: Dither::None,
+            alpha: AlphaPolicy::Ignore,
+            charset: Charset::Braille,
+            edges: EdgePolicy::Pad,
+            trim: false,
+            format: OutputFormat::Text,
+            invert: false,
+        }
+    }
+}
+
+/// The on/off state of every pixel of an image, ready to be encoded as text,
+/// along with the image that colors and brightness are sampled from.
+pub struct AsciiImage<'a> {
+    width: u32,
+    height: u32,
+    bitmap: Bitmap,
+    /// The bands of the pixels, for the rules with more than on and off.
+    levels: Option<Levels>,
+    /// The average color of every band, in color modes.
+    band_colors: Vec<Rgb<u8>>,
+    source: &'a DynamicImage,
+    color: ColorMode,
+    color_sample: ColorSample,
+    background: Background,
+    #[cfg(feature = "color")]
+    palette: Option<(Palette, bool)>,
+    edges: EdgePolicy,
+    trim: bool,
+    decorations: Decorations,
+}
+
+impl<'a> AsciiImage<'a> {
+    pub fn new<R: Rule + ?Sized>(img: &'a DynamicImage, rule: &R) -> Self {
+        let ctx = RuleCtx::new(img);
+        let mut ascii = AsciiImage::from_bitmap(img, rule.evaluate(&ctx));
+        ascii.levels = rule.levels(&ctx);
+
+        ascii
+    }
+
+    /// The image with the pixels of `bitmap` on, the size of `img`.
+    ///
+    /// # Panics
+    ///
+    /// When `bitmap` isn't the size of `img`.
+    pub fn from_bitmap(img: &'a DynamicImage, bitmap: Bitmap) -> Self {
+        let (width, height) = img.dimensions();
+        assert_eq!(
+            (bitmap.width(), bitmap.height()),
+            (width, height),
+            "bitmap of a different size than the image"
+        );
+
+        AsciiImage {
+            width,
+            height,
+            bitmap,
+            levels: None,
+            band_colors: Vec::new(),
+            source: img,
+            color: ColorMode::None,
+            color_sample: ColorSample::Average,
+            background: Background::Transparent,
+            #[cfg(feature = "color")]
+            palette: None,
+            edges: EdgePolicy::Pad,
+            trim: false,
+            decorations: Decorations::default(),
+        }
+    }
+
+    /// Samples the colors of every cell from `img`, to be emitted in the
+    /// given color mode; with bands, every band is in its average color.
+    pub fn colored(mut self, img: &'a DynamicImage, color: ColorMode) -> Self {
+        self.source = img;
+        self.color = color;
+        self.band_colors = match &self.levels {
+            Some(levels) if color != ColorMode::None => band_colors(img, levels),
+            _ => Vec::new(),
+        };
+
+        self
+    }
+
+    /// Derives the colors of the cells from their pixels the given way.
+    pub fn sampled_by(mut self, color_sample: ColorSample) -> Self {
+        self.color_sample = color_sample;
+
+        self
+    }
+
+    /// Fills the background of the cells in color modes; the half blocks,
+    /// drawn in two colors, keep theirs.
+    pub fn with_background(mut self, background: Background) -> Self {
+        self.background = background;
+
+        self
+    }
+
+    /// Quantizes the colors of the cells to `palette`, spreading the error
+    /// over the next cells when `dither`ing.
+    #[cfg(feature = "color")]
+    pub fn quantized(mut self, palette: &Palette, dither: bool) -> Self {
+        self.palette = Some((palette.clone(), dither));
+
+        self
+    }
+
+    /// Turns off the pixels that `policy` forces off in `img`.
+    pub fn masked_by_alpha(mut self, img: &DynamicImage, policy: AlphaPolicy) -> Self {
+        if policy == AlphaPolicy::TreatAsOff {
+            self.bitmap
+                .and(&Bitmap::from_fn(self.width, self.height, |x, y| {
+                    !policy.forces_off(img, x, y)
+                }));
+        }
+
+        self
+    }
+
+    /// Applies the morphological operations to the pixels on, in turn.
+    pub fn morphed(mut self, ops: &[Morph]) -> Self {
+        for op in ops {
+            self.bitmap = op.apply(&self.bitmap);
+        }
+
+        self
+    }
+
+    /// Turns off the pixels outside of `mask`, placed at `(left, top)`.
+    pub fn masked_by(mut self, mask: &Bitmap, (left, top): (u32, u32)) -> Self {
+        self.bitmap
+            .and(&Bitmap::from_fn(self.width, self.height, |x, y| {
+                x >= left && y >= top && mask.get(x - left, y - top)
+            }));
+
+        self
+    }
+
+    /// Turns off the pixels within the margins (left, top, right, bottom)
+    /// of the edges.
+    pub fn with_quiet_zone(mut self, (left, top, right, bottom): (u32, u32, u32, u32)) -> Self {
+        let (width, height) = (self.width, self.height);
+        self.bitmap.and(&Bitmap::from_fn(width, height, |x, y| {
+            x >= left && y >= top && x + right < width && y + bottom < height
+        }));
+
+        self
+    }
+
+    /// Emits or leaves out the partial cells at the right and bottom edges.
+    pub fn with_edges(mut self, edges: EdgePolicy) -> Self {
+        self.edges = edges;
+
+        self
+    }
+
+    /// Leaves out the blank rows and columns around the text, HTML, SVG and
+    /// PNG outputs, before the decorations, and the blank cells ending the
+    /// lines of text.
+    pub fn trimmed(mut self, trim: bool) -> Self {
+        self.trim = trim;
+
+        self
+    }
+
+    /// Draws the decorations around and over the cells of the text, HTML,
+    /// SVG and PNG outputs.
+    pub fn decorated(mut self, decorations: &Decorations) -> Self {
+        self.decorations = decorations.clone();
+
+        self
+    }
+
+    /// Swaps the on and off pixels, and turns the bands upside down.
+    pub fn inverted(mut self) -> Self {
+        self.bitmap.invert();
+        if let Some(levels) = &mut self.levels {
+            levels.invert();
+        }
+
+        self
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn is_on(&self, x: u32, y: u32) -> Option<bool> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        Some(self.bitmap.get(x, y))
+    }
+
+    /// The on/off state of every pixel.
+    pub fn bitmap(&self) -> &Bitmap {
+        &self.bitmap
+    }
+
+    /// The band of every pixel, for the rules with more than on and off.
+    pub fn levels(&self) -> Option<&Levels> {
+        self.levels.as_ref()
+    }
+
+    pub fn to_braille(&self) -> String {
+        self.to_text(&Charset::Braille)
+    }
+
+    /// Encodes the image as lines of text, with ANSI escapes for the colors.
+    pub fn to_text(&self, charset: &Charset) -> String {
+        let mut out = String::new();
+        self.write_text(charset, &mut out);
+        out
+    }
+
+    /// [`AsciiImage::to_text`], appending the text to `out`.
+    pub fn write_text(&self, charset: &Charset, out: &mut String) {
+        text::write(&self.text_cells(charset), self.color, out)
+    }
+
+    /// The cells of the lines of [`AsciiImage::to_text`].
+    fn text_cells(&self, charset: &Charset) -> Vec<Vec<Cell>> {
+        let mut cells = self.decorated_cells(charset);
+        if self.trim {
+            trim::trim_line_ends(&mut cells);
+        }
+
+        cells
+    }
+
+    /// Encodes the image as an HTML `<pre>` block.
+    pub fn to_html(&self, charset: &Charset) -> String {
+        html::write(&self.decorated_cells(charset))
+    }
+
+    /// Encodes the image as JSON: the grid of cells, each with its
+    /// character, the bitmask of its on pixels and its average color.
+    pub fn to_json(&self, charset: &Charset) -> String {
+        let (w, h) = charset.cell_size();
+        json::write(&self.cells(charset), (w, h), |x, y| {
+            (
+                self.cell_mask(x, y, w, h),
+                color::region_average(self.source, x * w, y * h, w, h),
+            )
+        })
+    }
+
+    /// Encodes the on pixels as sixel graphics, a sixel pixel per pixel, in
+    /// their colors quantized to the xterm palette of the color mode (256
+    /// colors for true color, its grays for gray), or in white without
+    /// colors; the off pixels are left transparent.
+    pub fn to_sixel(&self) -> String {
+        let on = |x, y| self.bitmap.get(x, y);
+
+        match self.color {
+            ColorMode::None => sixel::write(self.width, self.height, &[Rgb([255; 3])], |x, y| {
+                on(x, y).then_some(0)
+            }),
+            #[cfg(feature = "color")]
+            mode => {
+                let (colors, nearest, metric): (_, fn(_, _) -> u8, _) = match mode {
+                    ColorMode::Ansi16(metric) => {
+                        (palette::xterm_16_colors(), palette::nearest_16, metric)
+                    }
+                    ColorMode::Ansi256(metric) => {
+                        (palette::xterm_256_colors(), palette::nearest_256, metric)
+                    }
+                    ColorMode::Gray => (
+                        palette::xterm_grays(),
+                        |c, _| palette::nearest_gray(c),
+                        ColorMetric::default(),
+                    ),
+                    _ => (
+                        palette::xterm_256_colors(),
+                        palette::nearest_256,
+                        ColorMetric::default(),
+                    ),
+                };
+                let rgb = self.source.to_rgb8();
+
+                sixel::write(self.width, self.height, colors, |x, y| {
+                    on(x, y).then(|| nearest(*rgb.get_pixel(x, y), metric))
+                })
+            }
+        }
+    }
+
+    /// Displays the image with the graphics protocol of kitty, see
+    /// [`AsciiImage::on_pixels`]; nothing for an empty image.
+    pub fn to_kitty(&self) -> String {
+        self.on_pixels_png()
+            .map_or_else(String::new, |png| graphics::kitty(&png))
+    }
+
+    /// Displays the image with the inline images of iTerm2, see
+    /// [`AsciiImage::on_pixels`]; nothing for an empty image.
+    pub fn to_iterm2(&self) -> String {
+        self.on_pixels_png()
+            .map_or_else(String::new, |png| graphics::iterm2(&png))
+    }
+
+    /// The on pixels in their colors, or in white without colors, and the
+    /// off pixels transparent.
+    pub fn on_pixels(&self) -> RgbaImage {
+        let rgb = (self.color != ColorMode::None).then(|| self.source.to_rgb8());
+
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            match (self.bitmap.get(x, y), &rgb) {
+                (false, _) => Rgba([0; 4]),
+                (true, Some(rgb)) => self.toned(*rgb.get_pixel(x, y)).to_rgba(),
+                (true, None) => Rgba([255; 4]),
+            }
+        })
+    }
+
+    /// [`AsciiImage::on_pixels`] as a PNG, `None` for an empty image, which
+    /// PNG can't hold.
+    fn on_pixels_png(&self) -> Option<Vec<u8>> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+
+        let mut png = Vec::new();
+        image::png::PngEncoder::new(&mut png)
+            .encode(
+                &self.on_pixels(),
+                self.width,
+                self.height,
+                image::ColorType::Rgba8,
+            )
+            .expect("encoding a PNG in memory");
+
+        Some(png)
+    }
+
+    /// Encodes the image as an SVG document.
+    pub fn to_svg(&self, charset: &Charset, opts: &SvgOptions) -> String {
+        let cells = self.decorated_cells(charset);
+        if opts.dots && *charset == Charset::Braille {
+            svg::write_dots(&cells, opts)
+        } else {
+            svg::write_text(&cells, opts)
+        }
+    }
+
+    /// Draws the image onto a PNG-ready image.
+    pub fn to_png(&self, charset: &Charset, opts: &PngOptions) -> RgbImage {
+        raster::write(&self.decorated_cells(charset), opts)
+    }
+
+    /// Encodes the image into rows of cells, one per output character.
+    pub fn cells(&self, charset: &Charset) -> Vec<Vec<Cell>> {
+        #[cfg_attr(not(feature = "color"), allow(unused_mut))]
+        let mut cells = self.unquantized_cells(charset);
+        #[cfg(feature = "color")]
+        if let Some((palette, dither)) = &self.palette {
+            palette.quantize(&mut cells, self.color.metric(), *dither);
+        }
+
+        cells
+    }
+
+    fn unquantized_cells(&self, charset: &Charset) -> Vec<Vec<Cell>> {
+        match charset {
+            Charset::Braille => self.grid((2, 4), |x, y| self.braille_cell(x, y)),
+            Charset::Ascii { ramp } => self.grid((2, 4), |x, y| self.ascii_cell(ramp, x, y)),
+            Charset::Lines => self.grid((2, 4), |x, y| {
+                Cell::new(lines::region_line(&self.bitmap, x, y)).fg(self.sample(
+                    x * 2,
+                    y * 4,
+                    2,
+                    4,
+                ))
+            }),
+            Charset::HalfBlock => self.grid((1, 2), |x, y| self.half_block_cell(x, y)),
+            Charset::Quadrant => self.grid((2, 2), |x, y| {
+                Cell::new(blocks::quadrant(self.cell_mask(x, y, 2, 2) as u8)).fg(self.sample(
+                    x * 2,
+                    y * 2,
+                    2,
+                    2,
+                ))
+            }),
+            Charset::Sextant => self.grid((2, 3), |x, y| {
+                Cell::new(blocks::sextant(self.cell_mask(x, y, 2, 3) as u8)).fg(self.sample(
+                    x * 2,
+                    y * 3,
+                    2,
+                    3,
+                ))
+            }),
+            Charset::Glyphs(map) => {
+                let (w, h) = map.cell_size();
+                self.grid((w, h), |x, y| {
+                    Cell::new(map.glyph(self.cell_mask(x, y, w, h))).fg(self.sample(
+                        x * w,
+                        y * h,
+                        w,
+                        h,
+                    ))
+                })
+            }
+        }
+    }
+
+    /// [`AsciiImage::cells`], trimmed, with the decorations.
+    fn decorated_cells(&self, charset: &Charset) -> Vec<Vec<Cell>> {
+        let cells = self.cells(charset);
+        self.decorations.apply(match self.trim {
+            true => trim::trim(cells),
+            false => cells,
+        })
+    }
+
+    fn braille_cell(&self, x: u32, y: u32) -> Cell {
+        Cell::new(braille::region_braille(&self.bitmap, x, y)).fg(self.sample(x * 2, y * 4, 2, 4))
+    }
+
+    /// The character of `ramp` matching the average brightness of the 2x4
+    /// cell, or its band with bands, the first character being the darkest.
+    fn ascii_cell(&self, ramp: &[char], x: u32, y: u32) -> Cell {
+        let index = match (&self.levels, self.band(x * 2, y * 4, 2, 4)) {
+            (Some(levels), Some(band)) => {
+                band as usize * ramp.len().saturating_sub(1) / (levels.count().max(2) - 1) as usize
+            }
+            _ => {
+                let avg = color::region_average(self.source, x * 2, y * 4, 2, 4);
+                avg.to_luma()[0] as usize * ramp.len() / 256
+            }
+        };
+        let ch = ramp.get(index).copied().unwrap_or(' ');
+
+        Cell::new(ch).fg(self.sample(x * 2, y * 4, 2, 4))
+    }
+
+    /// A half block for the 1x2 cell; in color modes the top pixel is the
+    /// foreground and the bottom pixel the background, otherwise the block
+    /// follows the on/off state of both pixels.
+    fn half_block_cell(&self, x: u32, y: u32) -> Cell {
+        if self.color != ColorMode::None {
+            return Cell::new('▀')
+                .fg(self.sample(x, y * 2, 1, 1))
+                .bg(self.sample(x, y * 2 + 1, 1, 1));
+        }
+
+        let top = self.bitmap.get(x, y * 2);
+        let bottom = self.bitmap.get(x, y * 2 + 1);
+        Cell::new(match (top, bottom) {
+            (true, true) => '█',
+            (true, false) => '▀',
+            (false, true) => '▄',
+            (false, false) => ' ',
+        })
+    }
+
+    /// Bitmask of the on pixels of the `w`x`h` cell at (`x`, `y`), row by
+    /// row from the top left pixel at bit 0.
+    fn cell_mask(&self, x: u32, y: u32, w: u32, h: u32) -> u32 {
+        (0..h).fold(0, |mask, dy| {
+            mask | self.bitmap.bits(x * w, y * h + dy, w) << (dy * w)
+        })
+    }
+
+    /// The band of the given region with bands: the average band of its on
+    /// pixels, of all of them when none is on.
+    fn band(&self, x: u32, y: u32, w: u32, h: u32) -> Option<u8> {
+        let levels = self.levels.as_ref()?;
+        let average = |on_only: bool| {
+            let bands: Vec<u8> = (y..y + h.min(levels.height().saturating_sub(y)))
+                .flat_map(|py| {
+                    (x..x + w.min(levels.width().saturating_sub(x))).map(move |px| (px, py))
+                })
+                .filter(|&(px, py)| !on_only || self.bitmap.get(px, py))
+                .map(|(px, py)| levels.get(px, py))
+                .collect();
+            let sum: u32 = bands.iter().map(|&band| band as u32).sum();
+
+            (!bands.is_empty()).then(|| (sum as f32 / bands.len() as f32).round() as u8)
+        };
+
+        Some(average(true).or_else(|| average(false)).unwrap_or(0))
+    }
+
+    /// Color of the given region of the source image, sampled the way of
+    /// [`AsciiImage::sampled_by`], or the color of its band with bands, if
+    /// colors are emitted at all.
+    fn sample(&self, x: u32, y: u32, w: u32, h: u32) -> Option<Rgb<u8>> {
+        if self.color == ColorMode::None {
+            return None;
+        }
+        if let Some(band) = self.band(x, y, w, h) {
+            return self.band_colors.get(band as usize).copied();
+        }
+
+        Some(
+            self.color_sample
+                .sample(self.source, |px, py| self.bitmap.get(px, py), x, y, w, h),
+        )
+    }
+
+    /// The cells of the image, the rows built in parallel, on the
+    /// background in color modes.
+    fn grid<F>(&self, (w, h): (u32, u32), f: F) -> Vec<Vec<Cell>>
+    where
+        F: Fn(u32, u32) -> Cell + Sync,
+    {
+        CellGrid::new((self.width, self.height), (w, h), self.edges).build(|x, y| {
+            let mut cell = f(x, y);
+            if cell.bg.is_none() && self.color != ColorMode::None {
+                cell.bg = self
+                    .background
+                    .of(|| color::region_average(self.source, x * w, y * h, w, h));
+            }
+
+            cell.fg(cell.fg.map(|c| self.toned(c)))
+                .bg(cell.bg.map(|c| self.toned(c)))
+        })
+    }
+
+    /// `c` in the tones of the color mode: its step of gray for gray, as is
+    /// otherwise.
+    fn toned(&self, c: Rgb<u8>) -> Rgb<u8> {
+        match self.color {
+            #[cfg(feature = "color")]
+            ColorMode::Gray => palette::xterm_grays()[palette::nearest_gray(c) as usize],
+            _ => c,
+        }
+    }
+}
+
+/// The average color of the pixels of `img` in every band of `levels`, the
+/// bands without pixels in gray of their brightness.
+fn band_colors(img: &DynamicImage, levels: &Levels) -> Vec<Rgb<u8>> {
+    let mut sums = vec![([0u64; 3], 0u64); levels.count() as usize];
+    for (x, y, px) in img.pixels() {
+        let (sum, count) = &mut sums[levels.get(x, y) as usize];
+        px.to_rgb()
+            .0
+            .iter()
+            .zip(sum.iter_mut())
+            .for_each(|(&v, s)| *s += v as u64);
+        *count += 1;
+    }
+
+    let top = levels.count().saturating_sub(1).max(1) as u32;
+    sums.iter()
+        .enumerate()
+        .map(|(band, (sum, count))| match count {
+            0 => Rgb([(band as u32 * 255 / top) as u8; 3]),
+            _ => Rgb(sum.map(|s| (s / count) as u8)),
+        })
+        .collect()
+}
+
+/// The steps of a render, each reported with its duration by
+/// [`render_observed`] once done.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// Geometric transforms, resizing, filters and tone adjustments.
+    Resize,
+    /// Alpha handling, dithering and the evaluation of the rule.
+    Rule,
+    /// Encoding the on/off pixels in the output format.
+    Render,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Resize => write!(f, "resize"),
+            Stage::Rule => write!(f, "rule"),
+            Stage::Render => write!(f, "render"),
+        }
+    }
+}
+
+fn with_ascii<R, F>(
+    img: &DynamicImage,
+    opts: &RenderOptions,
+    on_stage: &mut dyn FnMut(Stage, Duration),
+    f: F,
+) -> R
+where
+    F: FnOnce(&AsciiImage) -> R,
+{
+    let start = Stopwatch::start();
+    let input_size = img.dimensions();
+    let img = opts.preprocess.transform(img);
+    let source_size = img.dimensions();
+    let img = match opts.auto_crop {
+        true => auto_cropped(img, opts),
+        false => img,
+    };
+    let cell = opts.charset.cell_size();
+    let img = match opts.pixel_art {
+        Some(grid) => Cow::Owned(pixel_art::scale(
+            &img,
+            grid,
+            opts.size,
+            cell,
+            opts.cell_aspect,
+        )),
+        None => opts.size.apply(&img, cell, opts.cell_aspect, opts.filter),
+    };
+    let img = opts.preprocess.filter(&img);
+    let img = opts.preprocess.adjust_tones(&img);
+    let resized = img.dimensions();
+    let margins = quiet_zone::margins(img.dimensions(), cell, opts.quiet_zone);
+    let img = match opts.quiet_zone {
+        0 => img,
+        _ => Cow::Owned(quiet_zone::pad(&img, margins)),
+    };
+    let (img, subject) = match opts.chroma_key {
+        Some(key) => {
+            let (keyed, subject) = key.apply(&img);
+            (Cow::Owned(keyed), Some(subject))
+        }
+        None => (img, None),
+    };
+    on_stage(Stage::Resize, start.elapsed());
+
+    let start = Stopwatch::start();
+    let composited = opts.alpha.apply(&img);
+    let dithered = opts.dither.apply(&composited);
+
+    let rule = match opts.target_fill {
+        // the share is of the pixels on once inverted
+        Some(target) if opts.invert => Cow::Owned(fill_rule(&dithered, &opts.rule, 1.0 - target)),
+        Some(target) => Cow::Owned(fill_rule(&dithered, &opts.rule, target)),
+        None => Cow::Borrowed(&opts.rule),
+    };
+    let mut ascii = match opts.shade {
+        Shade::Threshold if !opts.regions.is_empty() => {
+            let bitmap = region::evaluate_regions(
+                &dithered,
+                &rule,
+                &opts.regions,
+                source_size,
+                resized,
+                (margins.0, margins.1),
+            );
+            AsciiImage::from_bitmap(&dithered, bitmap)
+        }
+        Shade::Threshold => AsciiImage::new(&dithered, &*rule),
+        Shade::Density => {
+            AsciiImage::from_bitmap(&dithered, Shade::density(&composited.to_luma8(), cell))
+        }
+    };
+    if opts.invert {
+        ascii = ascii.inverted();
+    }
+    if !opts.morph.is_empty() {
+        ascii = ascii.morphed(&opts.morph);
+    }
+    if opts.quiet_zone > 0 {
+        ascii = ascii.with_quiet_zone(margins);
+    }
+    if let Some(subject) = &subject {
+        ascii = ascii.masked_by(subject, (0, 0));
+    }
+    if let Some(mask) = &opts.mask {
+        let mask = mask.bitmap(input_size, &opts.preprocess, resized);
+        ascii = ascii.masked_by(&mask, (margins.0, margins.1));
+    }
+    #[cfg(feature = "color")]
+    if let Some(palette) = &opts.palette {
+        ascii = ascii.quantized(palette, opts.palette_dither);
+    }
+    let ascii = ascii
+        .masked_by_alpha(&img, opts.alpha)
+        .colored(&composited, opts.color)
+        .sampled_by(opts.color_sample)
+        .with_background(opts.background)
+        .with_edges(opts.edges)
+        .trimmed(opts.trim)
+        .decorated(&opts.decorations);
+    on_stage(Stage::Rule, start.elapsed());
+
+    let start = Stopwatch::start();
+    let result = f(&ascii);
+    on_stage(Stage::Render, start.elapsed());
+
+    result
+}
+
+/// `img` cropped to the pixels the rule of `opts` turns on, as it is when
+/// none is.
+fn auto_cropped<'a>(img: Cow<'a, DynamicImage>, opts: &RenderOptions) -> Cow<'a, DynamicImage> {
+    let bounds = {
+        let composited = opts.alpha.apply(&img);
+        let mut ascii = AsciiImage::new(&composited, &opts.rule);
+        if opts.invert {
+            ascii = ascii.inverted();
+        }
+        ascii.masked_by_alpha(&img, opts.alpha).bitmap().bounds()
+    };
+
+    match bounds {
+        Some((x, y, width, height)) => Cow::Owned(img.crop_imm(x, y, width, height)),
+        None => img,
+    }
+}
+
+/// Times the stages; `wasm32-unknown-unknown` has no clock, there every
+/// stage takes no time.
+struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+}
+
+impl Stopwatch {
+    fn start() -> Self {
+        Stopwatch {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        #[cfg(target_arch = "wasm32")]
+        return Duration::default();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.start.elapsed()
+    }
+}
+
+/// Renders the image in the text based format of `opts`; PNG output is
+/// binary, use [`render_png`] for it (here it falls back to plain text).
+pub fn render(img: &DynamicImage, opts: &RenderOptions) -> String {
+    render_observed(img, opts, &mut |_, _| {})
+}
+
+/// [`render`], calling `on_stage` after every stage.
+pub fn render_observed(
+    img: &DynamicImage,
+    opts: &RenderOptions,
+    on_stage: &mut dyn FnMut(Stage, Duration),
+) -> String {
+    let mut out = String::new();
+    render_into_observed(img, opts, &mut out, on_stage);
+    out
+}
+
+/// [`render`], appending the text to `out` instead; reusing `out` across
+/// renders saves allocating the text every time.
+pub fn render_into(img: &DynamicImage, opts: &RenderOptions, out: &mut String) {
+    render_into_observed(img, opts, out, &mut |_, _| {})
+}
+
+/// [`render_into`], calling `on_stage` after every stage.
+pub fn render_into_observed(
+    img: &DynamicImage,
+    opts: &RenderOptions,
+    out: &mut String,
+    on_stage: &mut dyn FnMut(Stage, Duration),
+) {
+    with_ascii(img, opts, on_stage, |ascii| write_output(ascii, opts, out))
+}
+
+/// Appends the image in the text based format of `opts` to `out`.
+fn write_output(ascii: &AsciiImage, opts: &RenderOptions, out: &mut String) {
+    match &opts.format {
+        OutputFormat::Text | OutputFormat::Png(_) => ascii.write_text(&opts.charset, out),
+        OutputFormat::Html => out.push_str(&ascii.to_html(&opts.charset)),
+        OutputFormat::Svg(svg) => out.push_str(&ascii.to_svg(&opts.charset, svg)),
+        OutputFormat::Json => out.push_str(&ascii.to_json(&opts.charset)),
+        OutputFormat::Sixel => out.push_str(&ascii.to_sixel()),
+        OutputFormat::Kitty => out.push_str(&ascii.to_kitty()),
+        OutputFormat::Iterm2 => out.push_str(&ascii.to_iterm2()),
+        // a still image, recorded as a single frame
+        OutputFormat::Asciicast | OutputFormat::AnsiDump => {
+            let mut recorder = Recorder::new(opts);
+            recorder.push_text(&ascii.to_text(&opts.charset), Duration::ZERO);
+            out.push_str(&recorder.finish());
+        }
+    }
+}
+
+/// The cells of the lines of text [`render`] writes, to draw them on the
+/// terminal some other way.
+pub(crate) fn render_cells(img: &DynamicImage, opts: &RenderOptions) -> Vec<Vec<Cell>> {
+    with_ascii(img, opts, &mut |_, _| {}, |ascii| {
+        ascii.text_cells(&opts.charset)
+    })
+}
+
+/// Renders the image, then draws the characters onto an image.
+pub fn render_png(img: &DynamicImage, opts: &RenderOptions, png: &PngOptions) -> RgbImage {
+    render_png_observed(img, opts, png, &mut |_, _| {})
+}
+
+/// [`render_png`], calling `on_stage` after every stage.
+pub fn render_png_observed(
+    img: &DynamicImage,
+    opts: &RenderOptions,
+    png: &PngOptions,
+    on_stage: &mut dyn FnMut(Stage, Duration),
+) -> RgbImage {
+    with_ascii(img, opts, on_stage, |ascii| {
+        ascii.to_png(&opts.charset, png)
+    })
+}
+
+pub fn render_braille(img: &DynamicImage, opts: &RenderOptions) -> String {
+    render(
+        img,
+        &RenderOptions {
+            charset: Charset::Braille,
+            ..opts.clone()
+        },
+    )
+}
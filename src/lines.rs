@@ -0,0 +1,88 @@
+use crate::bitmap::Bitmap;
+use std::f32::consts::PI;
+
+/// Below this coherence, the gradients of a cell point every way: a corner,
+/// a crossing or a dot.
+const MIN_COHERENCE: f32 = 0.3;
+
+/// The line character following the edges of the on pixels of the 2x4 cell
+/// at (`x`, `y`) of `bitmap`: `-`, `_`, `|`, `/` or `\` by the direction of
+/// the edges, `+` where they go every way, and a space for the cells with
+/// no on pixel or no edge.
+pub(crate) fn region_line(bitmap: &Bitmap, x: u32, y: u32) -> char {
+    let (x, y) = (x * 2, y * 4);
+    let on =
+        |px: i64, py: i64| (px >= 0 && py >= 0 && bitmap.get(px as u32, py as u32)) as i32 as f32;
+
+    // the structure tensor of the Sobel gradients over the cell, which
+    // adds up the gradients on both sides of a thin line instead of
+    // cancelling them out
+    let (mut xx, mut yy, mut xy) = (0f32, 0f32, 0f32);
+    let mut rows = [false; 4];
+    for dy in 0..4 {
+        for dx in 0..2 {
+            let (px, py) = ((x + dx) as i64, (y + dy) as i64);
+            rows[dy as usize] |= on(px, py) > 0.0;
+
+            let gx = (on(px + 1, py - 1) + 2.0 * on(px + 1, py) + on(px + 1, py + 1))
+                - (on(px - 1, py - 1) + 2.0 * on(px - 1, py) + on(px - 1, py + 1));
+            let gy = (on(px - 1, py + 1) + 2.0 * on(px, py + 1) + on(px + 1, py + 1))
+                - (on(px - 1, py - 1) + 2.0 * on(px, py - 1) + on(px + 1, py - 1));
+            xx += gx * gx;
+            yy += gy * gy;
+            xy += gx * gy;
+        }
+    }
+
+    let energy = xx + yy;
+    if !rows.contains(&true) || energy == 0.0 {
+        return ' ';
+    }
+    if ((xx - yy).powi(2) + 4.0 * xy * xy).sqrt() / energy < MIN_COHERENCE {
+        return '+';
+    }
+
+    // the edges run across the gradients, the y axis pointing down
+    let gradient = 0.5 * (2.0 * xy).atan2(xx - yy);
+    let edge = (gradient + PI / 2.0).rem_euclid(PI);
+    match ((edge / (PI / 4.0)).round() as u8) % 4 {
+        // a line at the bottom of the cell
+        0 if !rows[..2].contains(&true) => '_',
+        0 => '-',
+        1 => '\\',
+        2 => '|',
+        _ => '/',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The line characters of the cells of `rows`, `#` being on.
+    fn lines(rows: &[&str]) -> String {
+        let bitmap = Bitmap::from_fn(rows[0].len() as u32, rows.len() as u32, |x, y| {
+            rows[y as usize].as_bytes()[x as usize] == b'#'
+        });
+
+        (0..bitmap.width() / 2)
+            .map(|x| region_line(&bitmap, x, 0))
+            .collect()
+    }
+
+    #[test]
+    fn follows_the_edges() {
+        assert_eq!(lines(&[".#....", ".#....", ".#....", ".#...."]), "|  ");
+        assert_eq!(lines(&["......", "######", "......", "......"]), "---");
+        assert_eq!(lines(&["......", "......", "......", "######"]), "___");
+        assert_eq!(
+            lines(&["#.......", ".#......", "..#.....", "...#...."]),
+            "\\\\  "
+        );
+        assert_eq!(
+            lines(&["...#....", "..#.....", ".#......", "#......."]),
+            "//  "
+        );
+        assert_eq!(lines(&["......", "..##..", "..##..", "......"]), " + ");
+    }
+}
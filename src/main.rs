@@ -1,245 +1,2284 @@
-use clap::Clap;
-use image::imageops::FilterType;
-use image::{DynamicImage, GenericImageView, Pixel};
-use itertools::Itertools;
+use asciidraw::{
+    builtin_palette, decode_gif, detect_capabilities, detect_cell_aspect,
+    detect_synchronized_output, exif_meta, exif_orientation, is_video, orient, parse_hex_color,
+    record, render_contact_sheet, render_histogram, render_montage, render_observed,
+    render_png_observed, render_stats, render_streamed, render_sweep, rule_help, serve, strip_ansi,
+    AlphaPolicy, Background, Caption, CaptionPosition, CellAspect, Charset, ChromaKey, ColorMetric,
+    ColorMode, ColorSample, Comparison, Config, ContactSheet, Crop, Decorations, Dither,
+    EdgePolicy, Escape, ExifMeta, Flip, Frame, FrameRange, FrameSelection, FrameStyle, GlyphMap,
+    HistogramChannels, HistogramOptions, ImageSize, ImageSizeParseError, Mask, Montage, Morph,
+    OutputFormat, Palette, PixelGrid, Playback, PngOptions, Preprocess, RawImage, Recorder, Region,
+    RenderOptions, ResizeFilter, Rotation, RuleExpr, RuleExprParseError, ServeOptions, Settings,
+    Shade, Stage, SvgOptions, Sweep, Tuner, Video, Viewer, CHARSETS, DITHERS,
+};
+#[cfg(feature = "net")]
+use asciidraw::{fetch, FetchOptions};
+#[cfg(feature = "pdf")]
+use asciidraw::{is_pdf, rasterize_pdf, PdfOptions};
+#[cfg(feature = "svg-input")]
+use asciidraw::{is_svg, rasterize_svg};
+#[cfg(feature = "clipboard")]
+use asciidraw::{read_clipboard_image, write_clipboard_text};
+use clap::{App, AppSettings, ArgSettings, Clap, ErrorKind, FromArgMatches, IntoApp};
+use clap_generate::generate;
+use clap_generate::generators::{Bash, Fish, PowerShell, Zsh};
+use image::png::PngDecoder;
+use image::{DynamicImage, GenericImageView, ImageError, ImageFormat, Rgb};
+use indicatif::{ProgressBar, ProgressStyle};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
 use rayon::prelude::*;
-use regex::Regex;
-use std::convert::TryFrom;
+use serde::Deserialize;
+use serde_json::json;
+use std::borrow::Cow;
+use std::env;
 use std::error::Error;
-use std::num::ParseIntError;
-use std::path::PathBuf;
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::mem;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
-enum ImageSize {
-    Default,
+/// Whether ANSI escapes are kept in the output.
+enum Escapes {
+    /// Keep them on stdout, strip them from files but ANSI dumps.
+    Auto,
+    Keep,
+    Strip,
+}
+
+#[derive(Error, Debug)]
+enum EscapesParseError {
+    #[error("unknown escapes policy `{0}`")]
+    UnknownEscapes(String),
+}
+
+impl FromStr for Escapes {
+    type Err = EscapesParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Escapes::Auto),
+            "keep" => Ok(Escapes::Keep),
+            "strip" => Ok(Escapes::Strip),
+            _ => Err(EscapesParseError::UnknownEscapes(s.into())),
+        }
+    }
+}
+
+/// A shell `completions` are generated for.
+#[derive(Copy, Clone)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Pwsh,
+}
+
+#[derive(Error, Debug)]
+enum ShellParseError {
+    #[error("unknown shell `{0}`, expected bash, zsh, fish or powershell")]
+    UnknownShell(String),
+}
+
+impl FromStr for Shell {
+    type Err = ShellParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "powershell" | "pwsh" => Ok(Shell::Pwsh),
+            _ => Err(ShellParseError::UnknownShell(s.into())),
+        }
+    }
+}
+
+/// How errors are reported on stderr.
+#[derive(Copy, Clone)]
+enum ErrorFormat {
+    Text,
+    /// A line of JSON, `{"error": ..., "kind": ..., "code": ...}`.
+    Json,
+}
+
+#[derive(Error, Debug)]
+enum ErrorFormatParseError {
+    #[error("unknown error format `{0}`")]
+    UnknownErrorFormat(String),
+}
+
+impl FromStr for ErrorFormat {
+    type Err = ErrorFormatParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(ErrorFormatParseError::UnknownErrorFormat(s.into())),
+        }
+    }
+}
+
+/// Why the command failed, each kind with its own exit code.
+#[derive(Error, Debug)]
+enum CliError {
+    /// The flags are wrong.
+    #[error("{0}")]
+    Usage(clap::Error),
+    #[error("couldn't decode the image: {0}")]
+    Decode(ImageError),
+    #[error("{0}")]
+    UnsupportedFormat(String),
+    #[error("invalid size: {0}")]
+    Size(String),
+    #[error("invalid rule: {0}")]
+    Rule(String),
+    #[error(transparent)]
+    Io(io::Error),
+    #[error("{0}")]
+    Other(Box<dyn Error>),
+}
+
+impl CliError {
+    /// An error of the flags found once they're parsed, against the input.
+    fn usage(message: &str) -> Self {
+        CliError::Usage(clap::Error::with_description(
+            format!("{}\n\nFor more information try --help\n", message),
+            ErrorKind::ValueValidation,
+        ))
+    }
+
+    fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Other(_) => 1,
+            CliError::Usage(_) => 2,
+            CliError::Io(_) => 3,
+            CliError::Decode(_) => 4,
+            CliError::UnsupportedFormat(_) => 5,
+            CliError::Size(_) => 6,
+            CliError::Rule(_) => 7,
+        }
+    }
+
+    /// The name of the kind in `--error-format json`.
+    fn kind(&self) -> &'static str {
+        match self {
+            CliError::Other(_) => "other",
+            CliError::Usage(_) => "usage",
+            CliError::Io(_) => "io",
+            CliError::Decode(_) => "decode",
+            CliError::UnsupportedFormat(_) => "unsupported-format",
+            CliError::Size(_) => "size",
+            CliError::Rule(_) => "rule",
+        }
+    }
+}
+
+/// The values of `--size`, `--scale` and `--rule` that don't parse are told
+/// apart from the other errors of the flags.
+impl From<clap::Error> for CliError {
+    fn from(e: clap::Error) -> Self {
+        match (e.kind, e.info.as_slice()) {
+            (ErrorKind::ValueValidation, [arg, _, cause]) => {
+                if arg.contains("--size") || arg.contains("--scale") {
+                    return CliError::Size(cause.clone());
+                }
+                if arg.contains("--rule") {
+                    return CliError::Rule(cause.clone());
+                }
+                CliError::Usage(e)
+            }
+            _ => CliError::Usage(e),
+        }
+    }
+}
+
+impl From<ImageError> for CliError {
+    fn from(e: ImageError) -> Self {
+        match e {
+            ImageError::Unsupported(e) => CliError::UnsupportedFormat(e.to_string()),
+            ImageError::IoError(e) => CliError::Io(e),
+            e => CliError::Decode(e),
+        }
+    }
+}
+
+impl From<Box<dyn Error>> for CliError {
+    fn from(e: Box<dyn Error>) -> Self {
+        let e = match e.downcast::<CliError>() {
+            Ok(e) => return *e,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<ImageError>() {
+            Ok(e) => return CliError::from(*e),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<ImageSizeParseError>() {
+            Ok(e) => return CliError::Size(e.to_string()),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<RuleExprParseError>() {
+            Ok(e) => return CliError::Rule(e.to_string()),
+            Err(e) => e,
+        };
+        match e.downcast::<io::Error>() {
+            Ok(e) => CliError::Io(*e),
+            Err(e) => CliError::Other(e),
+        }
+    }
+}
+
+fn parse_cell_size<T: FromStr>(s: &str) -> Result<(T, T), String> {
+    let invalid = || format!("invalid cell size `{}`, expected WxH", s);
+    let (w, h) = s.split_once('x').ok_or_else(invalid)?;
+
+    Ok((
+        T::from_str(w).map_err(|_| invalid())?,
+        T::from_str(h).map_err(|_| invalid())?,
+    ))
+}
+
+fn parse_image_format(s: &str) -> Result<ImageFormat, String> {
+    // formats are named after their usual extension
+    ImageFormat::from_path(Path::new("input").with_extension(s))
+        .map_err(|_| format!("unknown image format `{}`", s))
+}
+
+fn parse_gamma(s: &str) -> Result<f32, String> {
+    match f32::from_str(s) {
+        Ok(gamma) if gamma > 0.0 && gamma.is_finite() => Ok(gamma),
+        _ => Err(format!("invalid gamma `{}`", s)),
+    }
+}
+
+fn parse_sigma(s: &str) -> Result<f32, String> {
+    match f32::from_str(s) {
+        Ok(sigma) if sigma > 0.0 && sigma.is_finite() => Ok(sigma),
+        _ => Err(format!("invalid blur sigma `{}`", s)),
+    }
+}
+
+fn parse_fill(s: &str) -> Result<f64, String> {
+    match f64::from_str(s) {
+        Ok(fill) if (0.0..=1.0).contains(&fill) => Ok(fill),
+        _ => Err(format!("invalid fill `{}`, expected 0 to 1", s)),
+    }
+}
+
+fn parse_tolerance(s: &str) -> Result<f32, String> {
+    match f32::from_str(s) {
+        Ok(tolerance) if tolerance >= 0.0 && tolerance.is_finite() => Ok(tolerance),
+        _ => Err(format!("invalid key tolerance `{}`", s)),
+    }
+}
+
+fn parse_fps(s: &str) -> Result<f32, String> {
+    match f32::from_str(s) {
+        Ok(fps) if fps > 0.0 && fps.is_finite() => Ok(fps),
+        _ => Err(format!("invalid frame rate `{}`", s)),
+    }
+}
+
+#[cfg(feature = "pdf")]
+fn parse_page(s: &str) -> Result<u32, String> {
+    match u32::from_str(s) {
+        Ok(page) if page > 0 => Ok(page),
+        _ => Err(format!("invalid page `{}`", s)),
+    }
+}
+
+#[cfg(feature = "pdf")]
+fn parse_dpi(s: &str) -> Result<f32, String> {
+    match f32::from_str(s) {
+        Ok(dpi) if dpi > 0.0 && dpi.is_finite() => Ok(dpi),
+        _ => Err(format!("invalid resolution `{}`", s)),
+    }
+}
+
+fn parse_padding(s: &str) -> Result<u32, String> {
+    match u32::from_str(s) {
+        Ok(padding) if padding <= 256 => Ok(padding),
+        _ => Err(format!("invalid padding `{}`, expected 0 to 256", s)),
+    }
+}
+
+fn parse_every(s: &str) -> Result<usize, String> {
+    match usize::from_str(s) {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("invalid frame step `{}`", s)),
+    }
+}
+
+// The flags that control how images are turned into characters, shared
+// by all the commands.
+#[derive(Clap)]
+struct RenderArgs {
+    /// Config file with the defaults of these flags, instead of
+    /// `~/.config/ascii-image/config.toml`.
+    #[clap(long, parse(from_os_str))]
+    config: Option<PathBuf>,
+
+    /// Settings bundle, built-in (photo, lineart, logo, qr) or from the
+    /// config file, overridden by the flags; see `preset list`.
+    #[clap(long)]
+    preset: Option<String>,
+
+    /// Size of the image in pixels, `WxH` with `_` for a side that keeps
+    /// the aspect ratio [default: _]
+    #[clap(short, long, parse(try_from_str))]
+    size: Option<ImageSize>,
+
+    #[clap(long, conflicts_with = "size", parse(try_from_str = ImageSize::parse_scale))]
+    scale: Option<ImageSize>,
+
+    /// Shape of the terminal cells, `W:H`, the height of the output scaled
+    /// to keep circles round [default: measured when writing to the
+    /// terminal, else 1:2]
+    #[clap(long, parse(try_from_str))]
+    cell_aspect: Option<CellAspect>,
+
+    /// Filter the image is resized with: nearest, triangle, catmullrom,
+    /// gaussian or lanczos3; nearest keeps the hard edges of pixel art and
+    /// QR codes [default: triangle]
+    #[clap(long, parse(try_from_str))]
+    filter: Option<ResizeFilter>,
+
+    /// Scale the image as pixel art, every pixel of its grid becoming the
+    /// same whole number of dots, with no blending.
+    #[clap(long)]
+    pixel_art: bool,
+
+    /// Size of the pixels of the pixel art in image pixels, or auto to
+    /// detect it [default: auto]
+    #[clap(long, requires = "pixel-art", parse(try_from_str))]
+    pixel_grid: Option<PixelGrid>,
+
+    /// Blank cells left around the output on every side, for QR codes to
+    /// scan [default: 0]
+    #[clap(long)]
+    quiet_zone: Option<u32>,
+
+    /// Text in rows of its own beneath the image, or above it with
+    /// `--caption-pos top`.
+    #[clap(long)]
+    caption: Option<String>,
+
+    /// [default: bottom]
+    #[clap(long, requires = "caption", parse(try_from_str))]
+    caption_pos: Option<CaptionPosition>,
+
+    /// Draw the caption in a banner font of braille dots.
+    #[clap(long, requires = "caption")]
+    banner: bool,
+
+    /// A tag written over the bottom right corner of the image.
+    #[clap(long)]
+    watermark: Option<String>,
+
+    /// Border around the output: single, double, ascii or none.
+    #[clap(long, default_value = "none", parse(try_from_str))]
+    frame: FrameStyle,
+
+    /// Text written into the top border of the frame.
+    #[clap(long)]
+    frame_title: Option<String>,
+
+    /// Blank cells between the output and its frame, on every side, up to
+    /// 256.
+    #[clap(long, default_value = "0", parse(try_from_str = parse_padding))]
+    padding: u32,
+
+    /// The rule deciding which pixels are on, rules can be combined with
+    /// `!`, `&` and `|`; the rules are listed in `--help` [default:
+    /// Luma(128)]
+    #[clap(short, long, parse(try_from_str))]
+    rule: Option<RuleExpr>,
+
+    /// Turn the pixels on by the rule (threshold), or as many in every cell
+    /// as its brightness asks for (density).
+    #[clap(long, default_value = "threshold", parse(try_from_str))]
+    shade: Shade,
+
+    /// Swap the on and off cells after the rule, for dark on light images.
+    #[clap(long)]
+    invert: bool,
+
+    /// Move the threshold of the rule so that about this share of the dots,
+    /// from 0 to 1, are on, e.g. 0.35, for photos exposed differently to
+    /// look alike.
+    #[clap(long, parse(try_from_str = parse_fill))]
+    target_fill: Option<f64>,
+
+    /// Turn the pixels of a rectangle on by a rule of its own,
+    /// `X,Y,WxH:RULE` in pixels of the input, e.g. `0,0,100x100:Sobel(60)`;
+    /// repeated for more, the last one winning where they overlap.
+    #[clap(
+        long = "region",
+        number_of_values = 1,
+        conflicts_with = "auto-crop",
+        parse(try_from_str)
+    )]
+    regions: Vec<Region>,
+
+    /// An image whose opaque, or else bright, pixels are the only ones the
+    /// input can turn on under, stretched over it and rotated, cropped and
+    /// resized along with it.
+    #[clap(long, conflicts_with = "auto-crop", parse(from_os_str))]
+    mask: Option<PathBuf>,
+
+    /// Treat the pixels near this color as the background, e.g. `#00ff00`
+    /// for green screens: transparent to the rule and always off.
+    #[clap(long, parse(try_from_str = parse_hex_color))]
+    chroma_key: Option<Rgb<u8>>,
+
+    /// How far from the key color the background goes, as a distance in
+    /// RGB, from 0 to 441.
+    #[clap(long, default_value = "30", parse(try_from_str = parse_tolerance))]
+    key_tolerance: f32,
+
+    /// Erode, dilate, open or close the dots on after the rule, `OP:RADIUS`,
+    /// e.g. `open:1` to remove the specks or `dilate:1` to thicken thin
+    /// strokes; repeated for more, applied in turn.
+    #[clap(long, number_of_values = 1, parse(try_from_str))]
+    morph: Vec<Morph>,
+
+    /// [default: the most colors the terminal shows, none when not writing
+    /// to a terminal]
+    #[clap(long, parse(try_from_str))]
+    color: Option<ColorMode>,
+
+    /// How the color of a cell is derived in color modes: average,
+    /// dominant, center or on-pixels (the average of the dots alone).
+    #[clap(long, default_value = "average", parse(try_from_str))]
+    color_sample: ColorSample,
+
+    /// Quantize the colors to a palette, to match the theme of the
+    /// terminal: gruvbox, nord, solarized or a file of a #rrggbb color per
+    /// line.
+    #[clap(long)]
+    palette: Option<String>,
+
+    /// Spread the error of the colors quantized to the palette over the
+    /// next cells.
+    #[clap(long, requires = "palette")]
+    palette_dither: bool,
+
+    /// Background of the cells in color modes: a color, auto for the
+    /// average color of the cell dimmed, or transparent.
+    #[clap(long, default_value = "transparent", parse(try_from_str))]
+    bg: Background,
+
+    /// Rotate the image clockwise, by 90, 180 or 270 degrees.
+    #[clap(long, parse(try_from_str))]
+    rotate: Option<Rotation>,
+
+    /// Mirror the image, h (left and right) or v (top and bottom).
+    #[clap(long, parse(try_from_str))]
+    flip: Option<Flip>,
+
+    /// Render only a region of the image, `X,Y,WxH`.
+    #[clap(long, parse(try_from_str))]
+    crop: Option<Crop>,
+
+    /// Render only a region of the given size in the middle of the image.
+    #[clap(long, conflicts_with = "crop", parse(try_from_str = Crop::parse_center))]
+    crop_center: Option<Crop>,
+
+    /// Crop the image to the pixels the rule turns on, for small subjects
+    /// on large canvases.
+    #[clap(long)]
+    auto_crop: bool,
+
+    #[clap(long, default_value = "0", allow_hyphen_values = true)]
+    brightness: i32,
+
+    /// Contrast change in percent [default: 0]
+    #[clap(long, allow_hyphen_values = true)]
+    contrast: Option<f32>,
+
+    #[clap(long, default_value = "1", parse(try_from_str = parse_gamma))]
+    gamma: f32,
+
+    /// Blur the image before the rule, reduces the speckles on photos.
+    #[clap(long, parse(try_from_str = parse_sigma))]
+    blur: Option<f32>,
+
+    /// Sharpen the image before the rule.
+    #[clap(long)]
+    sharpen: bool,
+
+    #[clap(long, default_value = "rgb", parse(try_from_str))]
+    color_metric: ColorMetric,
+
+    /// [default: none]
+    #[clap(long, possible_values = &DITHERS, parse(try_from_str))]
+    dither: Option<Dither>,
+
+    #[clap(long, default_value = "ignore", parse(try_from_str))]
+    alpha: AlphaPolicy,
+
+    /// Emit (pad) or leave out (crop) the partial cells at the right and
+    /// bottom edges.
+    #[clap(long, default_value = "pad", parse(try_from_str))]
+    edges: EdgePolicy,
+
+    /// Leave out the blank rows and columns around the output, and the
+    /// blank cells ending its lines, for pasting.
+    #[clap(long)]
+    trim: bool,
+
+    /// [default: braille, or ascii when the terminal doesn't show braille]
+    #[clap(long, possible_values = &CHARSETS, parse(try_from_str))]
+    charset: Option<Charset>,
+
+    #[clap(long)]
+    ramp: Option<String>,
+
+    #[clap(long, conflicts_with = "charset", parse(from_os_str))]
+    glyph_map: Option<PathBuf>,
+}
+
+impl RenderArgs {
+    /// The options of the flags, over the config file; when the output goes
+    /// to a `terminal`, the color and the charset left unset are the ones
+    /// it is detected to show.
+    fn options(&self, terminal: bool) -> Result<RenderOptions, Box<dyn Error>> {
+        let config = match &self.config {
+            Some(path) => Config::from_file(path)?,
+            None => Config::load_default()?,
+        };
+        let settings = Settings {
+            rule: self.rule.clone(),
+            size: self.scale.or(self.size),
+            charset: self.charset.clone(),
+            color: self.color,
+            dither: self.dither,
+            contrast: self.contrast,
+            filter: self.filter,
+            quiet_zone: self.quiet_zone,
+        }
+        .or(&config.settings(self.preset.as_deref())?);
+        let settings = if terminal && (settings.color.is_none() || settings.charset.is_none()) {
+            let caps = detect_capabilities();
+            settings.or(&Settings {
+                color: Some(caps.color),
+                charset: Some(caps.charset()),
+                ..Settings::default()
+            })
+        } else {
+            settings
+        };
+        let charset = settings.charset.unwrap_or_default();
+
+        let cell_aspect = match self.cell_aspect {
+            Some(aspect) => aspect,
+            None if terminal => detect_cell_aspect().unwrap_or_default(),
+            None => CellAspect::default(),
+        };
+
+        Ok(RenderOptions {
+            size: settings.size.unwrap_or_default(),
+            cell_aspect,
+            filter: settings.filter.unwrap_or_default(),
+            pixel_art: match self.pixel_art {
+                true => Some(self.pixel_grid.unwrap_or(PixelGrid::Detect)),
+                false => None,
+            },
+            quiet_zone: settings.quiet_zone.unwrap_or(0),
+            decorations: Decorations {
+                caption: self.caption.as_ref().map(|text| Caption {
+                    text: text.clone(),
+                    position: self.caption_pos.unwrap_or_default(),
+                    banner: self.banner,
+                }),
+                watermark: self.watermark.clone(),
+                padding: self.padding,
+                frame: self.frame,
+                title: self.frame_title.clone(),
+                header: None,
+            },
+            rule: settings.rule.unwrap_or_default(),
+            target_fill: self.target_fill,
+            regions: self.regions.clone(),
+            mask: self.mask.as_deref().map(Mask::open).transpose()?,
+            chroma_key: self.chroma_key.map(|color| ChromaKey {
+                color,
+                tolerance: self.key_tolerance,
+            }),
+            morph: self.morph.clone(),
+            shade: self.shade,
+            preprocess: Preprocess {
+                rotate: self.rotate,
+                flip: self.flip,
+                crop: self.crop.or(self.crop_center),
+                brightness: self.brightness,
+                contrast: settings.contrast.unwrap_or(0.0),
+                gamma: self.gamma,
+                blur: self.blur,
+                sharpen: self.sharpen,
+            },
+            auto_crop: self.auto_crop,
+            color: settings
+                .color
+                .unwrap_or_default()
+                .with_metric(self.color_metric),
+            color_sample: self.color_sample,
+            background: self.bg,
+            palette: self
+                .palette
+                .as_deref()
+                .map(|name| builtin_palette(name).map_or_else(|| Palette::from_file(name), Ok))
+                .transpose()?,
+            palette_dither: self.palette_dither,
+            dither: settings.dither.unwrap_or_default(),
+            alpha: self.alpha,
+            charset: match (&self.glyph_map, &self.ramp) {
+                (Some(path), _) => Charset::Glyphs(GlyphMap::from_file(path)?),
+                (None, Some(ramp)) => charset.with_ramp(ramp),
+                (None, None) => charset,
+            },
+            edges: self.edges,
+            trim: self.trim,
+            format: OutputFormat::Text,
+            invert: self.invert,
+        })
+    }
+}
+
+#[derive(Clap)]
+enum Command {
+    /// Live view of a webcam.
+    Cam(CamOpts),
+    /// Interactive, full screen view of an image.
+    View(ViewOpts),
+    /// Manage the presets of `--preset`.
+    Preset(PresetOpts),
+    /// Print figures about the render of an image as JSON: the threshold
+    /// picked by automatic rules, the luma histogram, the size in cells,
+    /// the share of dots on and the time taken by every rule and stage.
+    Stats(StatsOpts),
+    /// Draw the histogram of an image as braille bar charts, of its luma
+    /// with the threshold Otsu picks marked, or of its red, green and blue,
+    /// to help pick a threshold.
+    Histogram(HistogramOpts),
+    /// Render two images alike side by side, or with `--diff` the second
+    /// one with the cells differing from the first highlighted.
+    Compare(CompareOpts),
+    /// Render the images sent over HTTP: `POST /render` with the image as
+    /// the body, the query setting `rule`, `size`, `charset`, `color`,
+    /// `dither`, `contrast`, `edges`, `invert` and `format` (text, html, svg
+    /// or json) over the flags.
+    Serve(ServeOpts),
+    /// Print the completions of the flags for a shell: bash, zsh, fish or
+    /// powershell, e.g. `asciidraw completions bash >
+    /// /etc/bash_completion.d/asciidraw`.
+    Completions(CompletionsOpts),
+}
+
+#[derive(Clap)]
+struct CompletionsOpts {
+    #[clap(parse(try_from_str))]
+    shell: Shell,
+}
+
+#[derive(Clap)]
+struct PresetOpts {
+    #[clap(subcommand)]
+    command: PresetCommand,
+}
+
+#[derive(Clap)]
+enum PresetCommand {
+    /// Print the flags every preset stands for.
+    List(ListPresetsOpts),
+}
+
+#[derive(Clap)]
+struct ListPresetsOpts {
+    /// Config file with more presets, instead of
+    /// `~/.config/ascii-image/config.toml`.
+    #[clap(long, parse(from_os_str))]
+    config: Option<PathBuf>,
+}
+
+#[derive(Clap)]
+struct ViewOpts {
+    #[clap(parse(from_os_str))]
+    input: PathBuf,
+
+    #[clap(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Clap)]
+struct StatsOpts {
+    #[clap(parse(from_os_str))]
+    input: PathBuf,
+
+    #[clap(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Clap)]
+struct HistogramOpts {
+    #[clap(parse(from_os_str))]
+    input: PathBuf,
+
+    /// The channels drawn: luma, or rgb for a chart of each.
+    #[clap(long, default_value = "luma", parse(try_from_str))]
+    channels: HistogramChannels,
+
+    /// Columns of cells of every chart.
+    #[clap(long, default_value = "64")]
+    width: u32,
+
+    /// Rows of cells of every chart.
+    #[clap(long, default_value = "8")]
+    height: u32,
+}
+
+#[derive(Clap)]
+struct CompareOpts {
+    #[clap(parse(from_os_str))]
+    left: PathBuf,
+
+    #[clap(parse(from_os_str))]
+    right: PathBuf,
+
+    /// Print the second image only, the cells differing from the first
+    /// highlighted, and how many differ on stderr.
+    #[clap(long)]
+    diff: bool,
+
+    #[clap(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Clap)]
+struct ServeOpts {
+    /// The address to listen on; other machines reach the server on
+    /// `0.0.0.0` only.
+    #[clap(long, default_value = "127.0.0.1")]
+    host: String,
+
+    #[clap(long, default_value = "8080")]
+    port: u16,
+
+    /// The requests handled at once, the others waiting their turn.
+    #[clap(long, default_value = "8")]
+    workers: usize,
+
+    /// Render the images at the `url=` of the queries too, letting the
+    /// clients make the server fetch from any address it reaches.
+    #[clap(long)]
+    allow_urls: bool,
+
+    #[clap(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Clap)]
+struct CamOpts {
+    /// The camera to capture, as understood by ffmpeg on this platform.
+    #[clap(long)]
+    device: Option<String>,
+
+    #[clap(long, default_value = "15", parse(try_from_str = parse_fps))]
+    fps: f32,
+
+    #[clap(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Clap)]
+#[clap(setting = AppSettings::ArgRequiredElseHelp)]
+struct Opts {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// The images to render, `-` for stdin; glob patterns are expanded.
+    #[clap(parse(from_os_str))]
+    input: Vec<PathBuf>,
+
+    /// Format of the input image, for when it can't be guessed from its
+    /// contents (e.g. `png`, `tga`).
+    #[clap(long, parse(try_from_str = parse_image_format))]
+    input_format: Option<ImageFormat>,
+
+    /// Read the input as raw pixels, `WxH:rgb8`, `WxH:rgba8` or `WxH:gray8`,
+    /// the rows one after the other, e.g. framebuffers piped in.
+    #[clap(long, conflicts_with = "input-format", parse(try_from_str))]
+    raw: Option<RawImage>,
+
+    /// Seconds allowed to download a remote input.
+    #[cfg(feature = "net")]
+    #[clap(long, default_value = "30")]
+    timeout: u64,
+
+    /// Largest remote input downloaded, in bytes.
+    #[cfg(feature = "net")]
+    #[clap(long, default_value = "52428800")]
+    max_size: u64,
+
+    #[cfg(feature = "net")]
+    #[clap(long, default_value = "5")]
+    max_redirects: u32,
+
+    /// The page of a PDF input to render, from 1.
+    #[cfg(feature = "pdf")]
+    #[clap(long, default_value = "1", parse(try_from_str = parse_page))]
+    page: u32,
+
+    /// Dots per inch the page of a PDF input is rasterized at, before
+    /// being resized.
+    #[cfg(feature = "pdf")]
+    #[clap(long, default_value = "150", parse(try_from_str = parse_dpi))]
+    dpi: f32,
+
+    #[clap(flatten)]
+    render: RenderArgs,
+
+    #[clap(short, long, default_value = "text", parse(try_from_str))]
+    format: OutputFormat,
+
+    #[clap(long, default_value = "monospace")]
+    svg_font: String,
+
+    #[clap(long, default_value = "8x16", parse(try_from_str = parse_cell_size))]
+    svg_cell: (f32, f32),
+
+    #[clap(long, default_value = "#000000", parse(try_from_str = parse_hex_color))]
+    svg_fg: Rgb<u8>,
+
+    #[clap(long, parse(try_from_str = parse_hex_color))]
+    svg_bg: Option<Rgb<u8>>,
+
+    #[clap(long)]
+    svg_dots: bool,
+
+    #[clap(long, default_value = "8x16", parse(try_from_str = parse_cell_size))]
+    png_cell: (u32, u32),
+
+    #[clap(long, default_value = "#000000", parse(try_from_str = parse_hex_color))]
+    png_fg: Rgb<u8>,
+
+    #[clap(long, default_value = "#ffffff", parse(try_from_str = parse_hex_color))]
+    png_bg: Rgb<u8>,
+
+    /// Render the image in the clipboard instead of the input.
+    #[clap(long, conflicts_with = "input")]
+    from_clipboard: bool,
+
+    #[clap(short, long, parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// Adjust the rule and dither interactively, then print the command
+    /// line that renders the image with them.
+    #[clap(long, conflicts_with_all = &["out-dir", "watch"])]
+    tune: bool,
+
+    /// Decode and render a PNG input a row of cells at a time, keeping
+    /// memory bounded for huge images; the image is rendered at its own
+    /// size, without filters or dithering.
+    #[clap(
+        long,
+        conflicts_with_all = &["out-dir", "watch", "tune", "from-clipboard", "to-clipboard", "raw"]
+    )]
+    stream: bool,
+
+    /// Render the input again every time the file changes.
+    #[clap(long, conflicts_with_all = &["out-dir", "from-clipboard"])]
+    watch: bool,
+
+    /// Render the images of the JSON lines of stdin, e.g.
+    /// `{"path": "cat.png", "rule": "Otsu", "output": "cat.txt"}`, over the
+    /// flags, printing a line of JSON with the `text`, the `output` or the
+    /// `error` of each, until stdin ends.
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "input", "output", "out-dir", "watch", "stream", "tune", "montage",
+            "contact-sheet", "from-clipboard", "to-clipboard",
+        ]
+    )]
+    stdin_jsonl: bool,
+
+    /// Lay out every input in one output, `cols=N` tiles per row, with
+    /// `,captions` their file names beneath them; the size is shared by
+    /// the whole grid. Text output only.
+    #[clap(long, conflicts_with_all = &["out-dir", "watch", "tune", "stream"], parse(try_from_str))]
+    montage: Option<Montage>,
+
+    /// Preview an animated GIF or a video as a grid of `ROWSxCOLS` frames
+    /// sampled evenly across it, under their times. Text output only.
+    #[clap(
+        long,
+        conflicts_with_all = &["out-dir", "watch", "tune", "stream", "montage"],
+        parse(try_from_str)
+    )]
+    contact_sheet: Option<ContactSheet>,
+
+    /// Render the image at every `Luma` threshold from START to END by STEP,
+    /// `START..END:STEP`, side by side under their rules, to find the one
+    /// that suits it; the size is shared by the whole row. Text output only.
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "rule", "out-dir", "watch", "tune", "stream", "montage", "contact-sheet",
+            "stdin-jsonl",
+        ],
+        parse(try_from_str)
+    )]
+    sweep: Option<Sweep>,
+
+    /// Render every input to a file of the same name in this directory.
+    #[clap(long, conflicts_with_all = &["output", "from-clipboard"], parse(from_os_str))]
+    out_dir: Option<PathBuf>,
+
+    /// Copy the rendered text to the clipboard instead of printing it.
+    #[clap(long, conflicts_with = "output")]
+    to_clipboard: bool,
+
+    #[clap(long, requires = "output")]
+    append: bool,
+
+    #[clap(long, default_value = "auto", parse(try_from_str))]
+    escapes: Escapes,
+
+    /// Wrap the text for pasting: none, shell (a printf command), markdown
+    /// (a code block) or discord (a code block with its colors).
+    #[clap(long, default_value = "none", parse(try_from_str))]
+    escape: Escape,
+
+    /// Play animated GIFs over and over.
+    #[clap(long = "loop")]
+    looping: bool,
+
+    #[clap(long, parse(try_from_str = parse_fps))]
+    fps_cap: Option<f32>,
+
+    /// Render the frames of an animation in this range, as `start..end`
+    /// with either end left out, or the one at this index, from 0.
+    #[clap(long, parse(try_from_str))]
+    frames: Option<FrameRange>,
+
+    /// Render the frame of an animation at this index alone, from 0.
+    #[clap(long, conflicts_with = "frames")]
+    frame_index: Option<usize>,
+
+    /// Render every nth frame of an animation, the others lasting on.
+    #[clap(long, parse(try_from_str = parse_every))]
+    every: Option<usize>,
+
+    /// Print the man page of the flags and the commands, in roff, e.g.
+    /// `asciidraw --man > asciidraw.1`.
+    #[clap(long, conflicts_with = "input")]
+    man: bool,
+
+    /// Write the size and the format of the image above it, with the date
+    /// and the camera of the EXIF data of JPEGs.
+    #[clap(
+        long,
+        overrides_with = "no-meta",
+        conflicts_with_all = &[
+            "out-dir", "watch", "stream", "tune", "montage", "contact-sheet", "stdin-jsonl",
+            "sweep",
+        ]
+    )]
+    show_meta: bool,
+
+    /// Write the art alone, the default.
+    #[clap(long, overrides_with = "show-meta")]
+    no_meta: bool,
+
+    /// Report errors as text, or as a line of JSON with their `kind` and
+    /// exit `code`: 2 for the flags, 3 for I/O, 4 for images that don't
+    /// decode, 5 for unsupported formats, 6 for sizes, 7 for rules, 1 for
+    /// the rest.
+    #[clap(long, default_value = "text", parse(try_from_str))]
+    error_format: ErrorFormat,
+
+    /// Show a progress bar on stderr, over the stages of the render or
+    /// over the images with --out-dir.
+    #[clap(long)]
+    progress: bool,
+
+    /// Print the time spent in every stage on stderr, summed over the
+    /// images with --out-dir.
+    #[clap(long)]
+    timing: bool,
+}
+
+impl Opts {
+    /// The frames of animations to render.
+    fn frame_selection(&self) -> FrameSelection {
+        FrameSelection {
+            range: match self.frame_index {
+                Some(index) => FrameRange::single(index),
+                None => self.frames.unwrap_or_default(),
+            },
+            every: self.every.unwrap_or(1),
+        }
+    }
+}
+
+/// Reads the bytes of the input image, `-` being stdin, along with its
+/// format: the given one, or else guessed from the contents, or else from
+/// the file extension.
+fn read_input(opts: &Opts, input: &Path) -> Result<(Vec<u8>, Option<ImageFormat>), Box<dyn Error>> {
+    let url = input
+        .to_str()
+        .filter(|s| s.starts_with("http://") || s.starts_with("https://"));
+
+    let bytes = if let Some(url) = url {
+        fetch_url(opts, url)?
+    } else if input == Path::new("-") {
+        let mut bytes = Vec::new();
+        io::stdin().lock().read_to_end(&mut bytes)?;
+        bytes
+    } else {
+        fs::read(input)?
+    };
+
+    let format = opts
+        .input_format
+        .or_else(|| image::guess_format(&bytes).ok())
+        .or_else(|| ImageFormat::from_path(input).ok());
+
+    Ok((bytes, format))
+}
+
+#[cfg(feature = "net")]
+fn fetch_url(opts: &Opts, url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let fetch_opts = FetchOptions {
+        timeout: Duration::from_secs(opts.timeout),
+        max_size: opts.max_size,
+        max_redirects: opts.max_redirects,
+    };
+
+    Ok(fetch(url, &fetch_opts)?)
+}
+
+#[cfg(not(feature = "net"))]
+fn fetch_url(_: &Opts, url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    Err(format!("can't download `{}`, built without the `net` feature", url).into())
+}
+
+/// The page of `bytes` picked by `--page` rasterized, if they are a PDF
+/// document.
+#[cfg(feature = "pdf")]
+fn open_pdf(opts: &Opts, bytes: &[u8]) -> Result<Option<DynamicImage>, Box<dyn Error>> {
+    if !is_pdf(bytes) {
+        return Ok(None);
+    }
+    let pdf_opts = PdfOptions {
+        page: opts.page,
+        dpi: opts.dpi,
+    };
+
+    Ok(Some(rasterize_pdf(bytes, &pdf_opts)?))
+}
+
+#[cfg(not(feature = "pdf"))]
+fn open_pdf(_: &Opts, bytes: &[u8]) -> Result<Option<DynamicImage>, Box<dyn Error>> {
+    match bytes.starts_with(b"%PDF-") {
+        true => Err(CliError::UnsupportedFormat(
+            "can't render PDF documents, built without the `pdf` feature".into(),
+        )
+        .into()),
+        false => Ok(None),
+    }
+}
 
-    Sized { width: u32, height: u32 },
+/// Opens the input image, the first of the selected frames of animations;
+/// unless `animated` is [`Animated::FirstFrame`], they are played or
+/// recorded instead, returning `None` once done.
+fn open_input(
+    opts: &Opts,
+    input: &Path,
+    render_opts: &RenderOptions,
+    animated: Animated,
+) -> Result<Option<DynamicImage>, Box<dyn Error>> {
+    Ok(open_input_meta(opts, input, render_opts, animated)?.map(|(img, _)| img))
 }
 
-#[derive(Error, Debug)]
-enum ImageSizeParseError {
-    #[error("couldn't parse an int in the image size")]
-    ParseIntError(#[from] ParseIntError),
-    #[error("unknown size format `{0}`")]
-    UnknownSizeFormat(String),
+/// What is known of an input besides its pixels, for `--show-meta`.
+#[derive(Default)]
+struct InputMeta {
+    format: Option<String>,
+    exif: ExifMeta,
 }
 
-impl FromStr for ImageSize {
-    type Err = ImageSizeParseError;
+impl InputMeta {
+    fn of_format(format: &str) -> Self {
+        InputMeta {
+            format: Some(format.into()),
+            ..InputMeta::default()
+        }
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s == "_" {
-            return Ok(Self::Default);
-        }
-        let mut it = s.split("x");
-        if let Some(w_str) = it.next() {
-            if let Some(h_str) = it.next() {
-                return Ok(Self::Sized {
-                    width: u32::from_str(w_str)?,
-                    height: u32::from_str(h_str)?,
-                });
+    /// The line above the art: the size of `img`, then the format, the date
+    /// and the camera when known.
+    fn header(&self, img: &DynamicImage) -> String {
+        let (width, height) = img.dimensions();
+        let mut parts = vec![format!("{}x{}", width, height)];
+        parts.extend(self.format.clone());
+        parts.extend(self.exif.date.clone());
+        parts.extend(self.exif.camera.clone());
+
+        parts.join(", ")
+    }
+}
+
+/// [`open_input`], along with what is known of the input.
+fn open_input_meta(
+    opts: &Opts,
+    input: &Path,
+    render_opts: &RenderOptions,
+    animated: Animated,
+) -> Result<Option<(DynamicImage, InputMeta)>, Box<dyn Error>> {
+    let playback = Playback {
+        looping: opts.looping,
+        fps_cap: opts.fps_cap,
+        synchronized: detect_synchronized_output(),
+    };
+    let selection = opts.frame_selection();
+    let video = is_video(input);
+    match animated {
+        Animated::Play if video => {
+            let open = || match Video::open(input) {
+                Ok(video) => Ok(selection.select(video)),
+                Err(err) => Err(io::Error::other(err)),
+            };
+            playback.stream(open, render_opts, &mut io::stdout().lock())?;
+
+            return Ok(None);
+        }
+        Animated::Record(output) if video => {
+            let mut recorder = Recorder::new(render_opts);
+            for frame in selection.select(Video::open(input)?) {
+                recorder.push(&frame?);
+            }
+            write_text(opts, &recorder.finish(), output)?;
+
+            return Ok(None);
+        }
+        _ => {}
+    }
+
+    if let Some(raw) = &opts.raw {
+        let (bytes, _) = read_input(opts, input)?;
+        let meta = InputMeta::of_format(&format!("raw {}", raw.layout));
+        // the input doesn't match the size and the layout of the flag
+        let img = raw
+            .decode(bytes)
+            .map_err(|e| CliError::usage(&format!("--raw {}: {}", raw, e)))?;
+        return Ok(Some((img, meta)));
+    }
+
+    let (bytes, format) = if video {
+        (Vec::new(), None)
+    } else {
+        read_input(opts, input)?
+    };
+    let selects = selection != FrameSelection::default();
+    if (animated != Animated::FirstFrame || selects) && format == Some(ImageFormat::Gif) {
+        let frames = gif_frames(&bytes, selection)?;
+        match animated {
+            Animated::Play if frames.len() > 1 => {
+                playback.play(&frames, render_opts, &mut io::stdout().lock())?;
+
+                return Ok(None);
+            }
+            Animated::Record(output) => {
+                write_text(opts, &record(&frames, render_opts), output)?;
+
+                return Ok(None);
             }
+            _ => {}
         }
 
-        Err(ImageSizeParseError::UnknownSizeFormat(s.into()))
+        return match frames.into_iter().next() {
+            Some(frame) => Ok(Some((frame.image, InputMeta::of_format("GIF")))),
+            None => Err("no frames of the animation selected".into()),
+        };
     }
+
+    // anywhere else, videos are rendered as their first selected frame
+    let opened = if video {
+        match selection.select(Video::open(input)?).next() {
+            Some(frame) => (frame?.image, InputMeta::of_format("video")),
+            None => return Err("no frames of the video selected".into()),
+        }
+    } else if let Some(page) = open_pdf(opts, &bytes)? {
+        (page, InputMeta::of_format("PDF"))
+    } else if let Some(img) = open_svg(input, &bytes, render_opts)? {
+        (img, InputMeta::of_format("SVG"))
+    } else {
+        let format = format.ok_or_else(|| {
+            CliError::UnsupportedFormat(
+                "couldn't tell the format of the input, use --input-format".into(),
+            )
+        })?;
+        let meta = InputMeta {
+            format: Some(format!("{:?}", format).to_uppercase()),
+            exif: match format {
+                ImageFormat::Jpeg => exif_meta(&bytes),
+                _ => ExifMeta::default(),
+            },
+        };
+        (decode(&bytes, format)?, meta)
+    };
+
+    Ok(Some(opened))
 }
 
-#[derive(Clap)]
-struct Opts {
-    #[clap(required = true, parse(from_os_str))]
-    input: PathBuf,
+/// What [`open_input`] does with an animated input, a GIF or a video.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Animated<'a> {
+    /// Renders its first frame, as any other input.
+    FirstFrame,
+    /// Plays it in place, on the terminal.
+    Play,
+    /// Records it to the output, in the recording format.
+    Record(Option<&'a Path>),
+}
 
-    #[clap(short, long, default_value = "_", parse(try_from_str))]
-    size: ImageSize,
-
-    #[clap(short, long, default_value = "Threshold(100)", parse(try_from_str))]
-    rule: OnOffRule,
-}
-
-/// UTF8 of first (empty) braille character
-const OFF_0: u32 = 0x2800;
-
-fn region_braille<F>(x: u32, y: u32, f: F) -> u32
-where
-    F: Fn((u32, u32)) -> Option<bool>,
-{
-    OFF_0
-        + [
-            (0, 0),
-            (1, 0),
-            (2, 0),
-            (0, 1),
-            (1, 1),
-            (2, 1),
-            (3, 0),
-            (3, 1),
-        ]
-        .iter()
-        .map(|&(dy, dx)| (y * 4 + dy, x * 2 + dx))
-        .enumerate()
-        .map(|(index, v)| {
-            // println!("index: {}, off: {:?}", index, v);
-            ((f(v).unwrap_or(false) as u8) << index) as u32
-        })
-        .sum::<u32>()
+impl<'a> Animated<'a> {
+    /// Records the animations to `output` in the recording formats, or
+    /// else does `otherwise` with them.
+    fn record_or(render_opts: &RenderOptions, output: Option<&'a Path>, otherwise: Self) -> Self {
+        match render_opts.format.records() {
+            true => Animated::Record(output),
+            false => otherwise,
+        }
+    }
 }
 
-#[derive(Copy, Clone)]
-enum OnOffRule {
-    PxThreshold(i32),
-    InvertedPxThreshold(i32),
-    Border(i32, i32),
+/// The selected frames of a GIF, an error telling how many it has when none
+/// of them are.
+fn gif_frames(bytes: &[u8], selection: FrameSelection) -> Result<Vec<Frame>, Box<dyn Error>> {
+    let frames = decode_gif(bytes)?;
+    if selection.count(frames.len()) == 0 {
+        return Err(CliError::usage(&format!(
+            "no frames selected out of the {} of the animation",
+            frames.len()
+        ))
+        .into());
+    }
+
+    let frames = selection.select(frames.into_iter().map(Ok));
+    Ok(frames.collect::<io::Result<_>>()?)
+}
+
+/// Decodes an image, turning JPEGs upright as their EXIF data says.
+fn decode(bytes: &[u8], format: ImageFormat) -> Result<DynamicImage, Box<dyn Error>> {
+    let img = image::load_from_memory_with_format(bytes, format)?;
+    if format != ImageFormat::Jpeg {
+        return Ok(img);
+    }
+
+    let (rotate, flip) = exif_orientation(bytes);
+    Ok(orient(Cow::Owned(img), rotate, flip).into_owned())
+}
+
+/// `bytes` rasterized at the size of the output, if they are an SVG
+/// document rather than an image that mentions one.
+#[cfg(feature = "svg-input")]
+fn open_svg(
+    input: &Path,
+    bytes: &[u8],
+    render_opts: &RenderOptions,
+) -> Result<Option<DynamicImage>, Box<dyn Error>> {
+    let svg = is_svg(bytes) || has_svg_extension(input);
+    if !svg || image::guess_format(bytes).is_ok() {
+        return Ok(None);
+    }
+
+    Ok(Some(rasterize_svg(bytes, render_opts)?))
+}
+
+#[cfg(not(feature = "svg-input"))]
+fn open_svg(
+    input: &Path,
+    _: &[u8],
+    _: &RenderOptions,
+) -> Result<Option<DynamicImage>, Box<dyn Error>> {
+    match has_svg_extension(input) {
+        true => Err(CliError::UnsupportedFormat(
+            "can't render SVG documents, built without the `svg-input` feature".into(),
+        )
+        .into()),
+        false => Ok(None),
+    }
+}
+
+fn has_svg_extension(input: &Path) -> bool {
+    input
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg") || ext.eq_ignore_ascii_case("svgz"))
+}
+
+#[cfg(feature = "clipboard")]
+fn read_clipboard() -> Result<DynamicImage, Box<dyn Error>> {
+    Ok(read_clipboard_image()?)
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn read_clipboard() -> Result<DynamicImage, Box<dyn Error>> {
+    Err("can't read the clipboard, built without the `clipboard` feature".into())
+}
+
+#[cfg(feature = "clipboard")]
+fn write_clipboard(text: &str) -> Result<(), Box<dyn Error>> {
+    Ok(write_clipboard_text(text)?)
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn write_clipboard(_: &str) -> Result<(), Box<dyn Error>> {
+    Err("can't write to the clipboard, built without the `clipboard` feature".into())
+}
+
+fn list_presets(opts: &PresetOpts) -> Result<(), Box<dyn Error>> {
+    let PresetCommand::List(list) = &opts.command;
+    let config = match &list.config {
+        Some(path) => Config::from_file(path)?,
+        None => Config::load_default()?,
+    };
+
+    for (name, settings) in config.presets() {
+        let flags = [
+            ("--rule", settings.rule.map(|v| v.to_string())),
+            ("--size", settings.size.map(|v| v.to_string())),
+            ("--charset", settings.charset.map(|v| v.to_string())),
+            ("--color", settings.color.map(|v| v.to_string())),
+            ("--dither", settings.dither.map(|v| v.to_string())),
+            ("--contrast", settings.contrast.map(|v| v.to_string())),
+            ("--filter", settings.filter.map(|v| v.to_string())),
+            ("--quiet-zone", settings.quiet_zone.map(|v| v.to_string())),
+        ];
+        let flags = flags
+            .iter()
+            .filter_map(|(flag, value)| Some(format!("{} {}", flag, shell_quote(value.as_ref()?))))
+            .collect::<Vec<_>>();
+
+        println!("{}: {}", name, flags.join(" "));
+    }
+
+    Ok(())
+}
+
+/// The man page of `app`, its flags, the `rules` of `--rule` then its
+/// commands.
+fn man_page(app: &App, rules: &str) -> String {
+    // roff takes a leading `.` or `'` as a request, and `\` as an escape
+    let roff = |text: &str| {
+        text.replace('\\', "\\e")
+            .lines()
+            .map(|line| match line.starts_with(&['.', '\''][..]) {
+                true => format!("\\&{}", line),
+                false => line.into(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let name = app.get_name();
+    let mut page = format!(".TH {} 1\n", name.to_uppercase());
+    page += &format!(".SH NAME\n{} \\- render images as text\n", name);
+    page += &format!(
+        ".SH SYNOPSIS\n\\fB{}\\fR [\\fIOPTIONS\\fR] [\\fIINPUT\\fR]...\n\\fB{}\\fR \\fICOMMAND\\fR\n",
+        name, name
+    );
+
+    page += ".SH OPTIONS\n";
+    for arg in app.get_arguments() {
+        if arg.is_set(ArgSettings::Hidden) || arg.get_index().is_some() {
+            continue;
+        }
+        let value = match arg.is_set(ArgSettings::TakesValue) {
+            true => format!(" \\fI{}\\fR", arg.get_name().to_uppercase()),
+            false => String::new(),
+        };
+        let flag = match (arg.get_short(), arg.get_long()) {
+            (Some(short), Some(long)) => format!("\\fB\\-{}\\fR, \\fB\\-\\-{}\\fR", short, long),
+            (Some(short), None) => format!("\\fB\\-{}\\fR", short),
+            (None, Some(long)) => format!("\\fB\\-\\-{}\\fR", long),
+            (None, None) => continue,
+        };
+        page += &format!(".TP\n{}{}\n", flag, value);
+        if let Some(about) = arg.get_about() {
+            page += &format!("{}\n", roff(about));
+        }
+    }
+
+    page += &format!(
+        ".SH RULES\n.nf\n{}\n.fi\n",
+        roff(rules.trim_start_matches("RULES:\n"))
+    );
+
+    page += ".SH COMMANDS\n";
+    for command in app.get_subcommands() {
+        page += &format!(".TP\n\\fB{}\\fR\n", command.get_name());
+        if let Some(about) = command.get_about() {
+            page += &format!("{}\n", roff(about));
+        }
+    }
+
+    page
+}
+
+fn run_compare(compare: &CompareOpts) -> Result<(), Box<dyn Error>> {
+    let open = |path: &Path| -> Result<_, Box<dyn Error>> {
+        decode(&fs::read(path)?, ImageFormat::from_path(path)?)
+    };
+    let render_opts = compare.render.options(io::stdout().is_terminal())?;
+    let comparison = Comparison::new(&open(&compare.left)?, &open(&compare.right)?, &render_opts);
+
+    if compare.diff {
+        print!("{}", comparison.diff());
+        eprintln!("{} cells differ", comparison.differing());
+    } else {
+        print!("{}", comparison.side_by_side());
+    }
+
+    Ok(())
+}
+
+fn run_cam(cam: &CamOpts) -> Result<(), Box<dyn Error>> {
+    let playback = Playback {
+        looping: false,
+        fps_cap: Some(cam.fps),
+        synchronized: detect_synchronized_output(),
+    };
+    let open = || Video::camera(cam.device.as_deref(), cam.fps).map_err(io::Error::other);
+    playback.stream(
+        open,
+        &cam.render.options(io::stdout().is_terminal())?,
+        &mut io::stdout().lock(),
+    )?;
+
+    Ok(())
 }
 
-fn absdiff(a: u8, b: u8) -> u8 {
-    if a > b {
-        a - b
+/// `app` with the rules listed after the flags in the `--help` of every
+/// command taking `--rule`.
+fn with_rule_help<'help>(app: App<'help>, rules: &'help str) -> App<'help> {
+    let has_rule = app.get_arguments().any(|arg| arg.get_name() == "rule");
+    let mut app = if has_rule {
+        app.after_long_help(rules)
     } else {
-        b - a
+        app
+    };
+    for subcommand in app.get_subcommands_mut() {
+        *subcommand = with_rule_help(mem::take(subcommand), rules);
     }
+
+    app
+}
+
+fn main() {
+    let rules = rule_help();
+    let opts = match with_rule_help(Opts::into_app(), &rules).try_get_matches() {
+        Ok(matches) => Opts::from_arg_matches(&matches),
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) => fail(e.into(), raw_error_format()),
+    };
+
+    let error_format = opts.error_format;
+    if let Err(e) = run(opts) {
+        fail(e.into(), error_format);
+    }
+}
+
+/// Reports `e` on stderr and exits with its code.
+fn fail(e: CliError, format: ErrorFormat) -> ! {
+    match (format, &e) {
+        // clap's messages have their own prefix and usage
+        (ErrorFormat::Text, CliError::Usage(usage)) => eprint!("{}", usage),
+        (ErrorFormat::Text, e) => eprintln!("error: {}", e),
+        (ErrorFormat::Json, e) => eprintln!(
+            "{}",
+            json!({ "error": e.to_string().trim_end(), "kind": e.kind(), "code": e.exit_code() })
+        ),
+    }
+
+    process::exit(e.exit_code())
+}
+
+/// The `--error-format` among the raw arguments, for the errors of the
+/// flags themselves.
+fn raw_error_format() -> ErrorFormat {
+    let args: Vec<String> = env::args_os()
+        .filter_map(|arg| arg.into_string().ok())
+        .collect();
+    let value = args
+        .windows(2)
+        .find(|pair| pair[0] == "--error-format")
+        .map(|pair| pair[1].as_str())
+        .or_else(|| {
+            args.iter()
+                .find_map(|arg| arg.strip_prefix("--error-format="))
+        });
+
+    value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(ErrorFormat::Text)
 }
 
-impl OnOffRule {
-    fn is_on(&self, img: &DynamicImage, x: u32, y: u32) -> bool {
-        if !img.in_bounds(x, y) {
-            return false;
+fn run(opts: Opts) -> Result<(), Box<dyn Error>> {
+    match &opts.command {
+        Some(Command::Cam(cam)) => return run_cam(cam),
+        Some(Command::Preset(preset)) => return list_presets(preset),
+        Some(Command::Stats(stats)) => {
+            let format = ImageFormat::from_path(&stats.input)?;
+            let img = decode(&fs::read(&stats.input)?, format)?;
+            print!(
+                "{}",
+                render_stats(&img, &stats.render.options(false)?).to_json()
+            );
+            return Ok(());
         }
-        match self {
-            OnOffRule::PxThreshold(threshold) => {
-                *threshold <= img.get_pixel(x, y).0.iter().map(|&v| v as i32).sum::<i32>()
-            }
-            OnOffRule::InvertedPxThreshold(threshold) => {
-                *threshold
-                    >= img
-                        .get_pixel(x, y)
-                        .to_rgb()
-                        .0
-                        .iter()
-                        .map(|&v| v as i32)
-                        .sum::<i32>()
+        Some(Command::Histogram(histogram)) => {
+            let format = ImageFormat::from_path(&histogram.input)?;
+            let img = decode(&fs::read(&histogram.input)?, format)?;
+            let histogram_opts = HistogramOptions {
+                channels: histogram.channels,
+                width: histogram.width,
+                height: histogram.height,
+            };
+            print!("{}", render_histogram(&img, &histogram_opts));
+            return Ok(());
+        }
+        Some(Command::Compare(compare)) => return run_compare(compare),
+        Some(Command::Serve(serve_opts)) => {
+            let listener = TcpListener::bind((serve_opts.host.as_str(), serve_opts.port))?;
+            eprintln!("listening on http://{}/render", listener.local_addr()?);
+            let options = ServeOptions {
+                workers: serve_opts.workers,
+                allow_urls: serve_opts.allow_urls,
+            };
+            return Ok(serve(
+                listener,
+                &serve_opts.render.options(false)?,
+                &options,
+            )?);
+        }
+        Some(Command::Completions(completions)) => {
+            let mut app = Opts::into_app();
+            let stdout = &mut io::stdout();
+            match completions.shell {
+                Shell::Bash => generate::<Bash, _>(&mut app, "asciidraw", stdout),
+                Shell::Zsh => generate::<Zsh, _>(&mut app, "asciidraw", stdout),
+                Shell::Fish => generate::<Fish, _>(&mut app, "asciidraw", stdout),
+                Shell::Pwsh => generate::<PowerShell, _>(&mut app, "asciidraw", stdout),
             }
-            OnOffRule::Border(threshold, distance) => {
-                let px = img.get_pixel(x, y);
+            return Ok(());
+        }
+        Some(Command::View(view)) => {
+            let format = ImageFormat::from_path(&view.input)?;
+            let img = decode(&fs::read(&view.input)?, format)?;
+            return Ok(Viewer::new(&img, &view.render.options(true)?).run()?);
+        }
+        None => {}
+    }
+    if opts.man {
+        print!("{}", man_page(&Opts::into_app(), &rule_help()));
+        return Ok(());
+    }
 
-                [(-1, 0), (1, 0), (0, -1), (0, 1)]
-                    .iter()
-                    .cartesian_product(1..=*distance)
-                    .map(|(&(dx, dy), d)| (dx * d, dy * d))
-                    .any(|(dx, dy)| {
-                        let nx = u32::try_from(x as i32 + dx).unwrap_or(0);
-                        let ny = u32::try_from(y as i32 + dy).unwrap_or(0);
-                        if !img.in_bounds(nx, ny) {
-                            return false;
-                        }
-
-                        let df = img
-                            .get_pixel(nx, ny)
-                            .0
-                            .iter()
-                            .zip(px.0.iter())
-                            .map(|(&a, &b)| absdiff(a, b) as i32)
-                            .max()
-                            .unwrap_or(0);
-
-                        df >= *threshold
-                    })
+    let inputs = expand_inputs(&opts.input)?;
+    if inputs.is_empty() && !opts.from_clipboard && !opts.stdin_jsonl {
+        return Err(CliError::Usage(clap::Error::with_description(
+            "the input image is required\n\nFor more information try --help\n".into(),
+            ErrorKind::MissingRequiredArgument,
+        ))
+        .into());
+    }
+
+    let render_opts = RenderOptions {
+        format: match &opts.format {
+            OutputFormat::Svg(_) => OutputFormat::Svg(SvgOptions {
+                font: opts.svg_font.clone(),
+                cell_size: opts.svg_cell,
+                fg: opts.svg_fg,
+                bg: opts.svg_bg,
+                dots: opts.svg_dots,
+            }),
+            OutputFormat::Png(_) => OutputFormat::Png(PngOptions {
+                cell_size: opts.png_cell,
+                fg: opts.png_fg,
+                bg: opts.png_bg,
+            }),
+            format => format.clone(),
+        },
+        ..opts.render.options(
+            opts.output.is_none()
+                && opts.out_dir.is_none()
+                && !opts.to_clipboard
+                && io::stdout().is_terminal(),
+        )?
+    };
+
+    if let Some(out_dir) = &opts.out_dir {
+        return run_batch(&opts, &inputs, out_dir, &render_opts);
+    }
+    if opts.stdin_jsonl {
+        return run_stdin_jsonl(&opts, &render_opts);
+    }
+    if let Some(montage) = &opts.montage {
+        return run_montage(&opts, &inputs, montage, &render_opts);
+    }
+    if inputs.len() > 1 {
+        return Err("several inputs need --out-dir or --montage".into());
+    }
+    if let Some(sheet) = &opts.contact_sheet {
+        return run_contact_sheet(&opts, &inputs[0], sheet, &render_opts);
+    }
+    if let Some(sweep) = &opts.sweep {
+        return run_sweep(&opts, &inputs[0], sweep, &render_opts);
+    }
+    if opts.watch {
+        return run_watch(&opts, &inputs[0], &render_opts);
+    }
+    if opts.stream {
+        return run_stream(&opts, &inputs[0], &render_opts);
+    }
+    if opts.tune {
+        let img = match inputs.first() {
+            Some(input) => open_input(&opts, input, &render_opts, Animated::FirstFrame)?.unwrap(),
+            None => read_clipboard()?,
+        };
+        if let Some(tuned) = Tuner::new(&img, &render_opts).run()? {
+            println!("{}", invocation(&tuned));
+        }
+
+        return Ok(());
+    }
+
+    let bar = opts.progress.then(|| {
+        let bar = ProgressBar::new(4).with_style(progress_style());
+        bar.set_message("decode");
+        bar
+    });
+    let mut timings = Timings::default();
+
+    let start = Instant::now();
+    let (img, meta) = if opts.from_clipboard {
+        (read_clipboard()?, InputMeta::default())
+    } else {
+        // animations are played in place, on the terminal only
+        let play =
+            opts.output.is_none() && !opts.to_clipboard && render_opts.format == OutputFormat::Text;
+        let animated = Animated::record_or(
+            &render_opts,
+            opts.output.as_deref(),
+            match play {
+                true => Animated::Play,
+                false => Animated::FirstFrame,
+            },
+        );
+        match open_input_meta(&opts, &inputs[0], &render_opts, animated)? {
+            Some(opened) => opened,
+            None => return Ok(()),
+        }
+    };
+    timings.decode = start.elapsed();
+    let render_opts = match opts.show_meta && !opts.no_meta {
+        true => RenderOptions {
+            decorations: Decorations {
+                header: Some(meta.header(&img)),
+                ..render_opts.decorations.clone()
+            },
+            ..render_opts
+        },
+        false => render_opts,
+    };
+    if let Some(bar) = &bar {
+        bar.inc(1);
+        bar.set_message(Stage::Resize.to_string());
+    }
+
+    emit(
+        &opts,
+        &img,
+        &render_opts,
+        opts.output.as_deref(),
+        &mut |stage, elapsed| {
+            timings.record(stage, elapsed);
+            if let Some(bar) = &bar {
+                bar.inc(1);
+                match stage {
+                    Stage::Resize => bar.set_message(Stage::Rule.to_string()),
+                    Stage::Rule => bar.set_message(Stage::Render.to_string()),
+                    Stage::Render => bar.finish_and_clear(),
+                }
             }
+        },
+    )?;
+
+    if opts.timing {
+        timings.report();
+    }
+
+    Ok(())
+}
+
+/// Time spent in every stage, for `--timing`.
+#[derive(Copy, Clone, Default)]
+struct Timings {
+    decode: Duration,
+    resize: Duration,
+    rule: Duration,
+    render: Duration,
+}
+
+impl Timings {
+    fn record(&mut self, stage: Stage, elapsed: Duration) {
+        match stage {
+            Stage::Resize => self.resize += elapsed,
+            Stage::Rule => self.rule += elapsed,
+            Stage::Render => self.render += elapsed,
+        }
+    }
+
+    fn add(mut self, other: Timings) -> Timings {
+        self.decode += other.decode;
+        self.resize += other.resize;
+        self.rule += other.rule;
+        self.render += other.render;
+        self
+    }
+
+    fn report(&self) {
+        let stages = [
+            ("decode", self.decode),
+            ("resize", self.resize),
+            ("rule", self.rule),
+            ("render", self.render),
+        ];
+        let total: Duration = stages.iter().map(|&(_, elapsed)| elapsed).sum();
+
+        for (name, elapsed) in stages.iter().chain(Some(&("total", total))) {
+            eprintln!("{:<8}{:>10.1} ms", name, elapsed.as_secs_f64() * 1000.0);
         }
     }
 }
 
-#[derive(Error, Debug)]
-enum OnOffRuleParseError {
-    #[error("number parse error")]
-    ParseIntError(#[from] ParseIntError),
+fn progress_style() -> ProgressStyle {
+    ProgressStyle::with_template("{bar:40} {pos}/{len} {msg}").unwrap()
+}
+
+/// Renders the image to `output`, or else to the clipboard or stdout.
+fn emit(
+    opts: &Opts,
+    img: &DynamicImage,
+    render_opts: &RenderOptions,
+    output: Option<&Path>,
+    on_stage: &mut dyn FnMut(Stage, Duration),
+) -> Result<(), Box<dyn Error>> {
+    if let OutputFormat::Png(png) = &render_opts.format {
+        let path = match (output, opts.append) {
+            (Some(path), false) => path,
+            _ => return Err("PNG output needs --output, and can't be appended".into()),
+        };
+        render_png_observed(img, render_opts, png, on_stage)
+            .save_with_format(path, ImageFormat::Png)?;
+
+        return Ok(());
+    }
 
-    #[error("unknown format for on off rule: `{0}`")]
-    UnknownFormat(String),
+    write_text(opts, &render_observed(img, render_opts, on_stage), output)
 }
 
-impl FromStr for OnOffRule {
-    type Err = OnOffRuleParseError;
+/// Writes `text` to `output`, or else to the clipboard or stdout.
+fn write_text(opts: &Opts, text: &str, output: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let text = if strips_escapes(opts, output) {
+        Cow::Owned(strip_ansi(text))
+    } else {
+        Cow::Borrowed(text)
+    };
+    let text = match opts.escape {
+        Escape::None => text,
+        escape => Cow::Owned(escape.apply(&text)),
+    };
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let re = Regex::new(r"^Threshold\((\d+)\)$").unwrap();
+    match output {
+        None if opts.to_clipboard => write_clipboard(&text)?,
+        Some(path) => OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(opts.append)
+            .truncate(!opts.append)
+            .open(path)?
+            .write_all(text.as_bytes())?,
+        None => {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(text.as_bytes())?;
+            stdout.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A line of `--stdin-jsonl`: the image to render, and the settings of the
+/// config file to render it with over the flags.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Job {
+    path: PathBuf,
+    /// The file to write the render to, instead of the `text` of the result.
+    output: Option<PathBuf>,
+    rule: Option<String>,
+    size: Option<String>,
+    charset: Option<String>,
+    color: Option<String>,
+    dither: Option<String>,
+    contrast: Option<f32>,
+    filter: Option<String>,
+    quiet_zone: Option<u32>,
+    format: Option<String>,
+    invert: Option<bool>,
+}
+
+impl Job {
+    /// `flags`, the options of the flags `render`, with the settings of the
+    /// job over them.
+    fn options(
+        &self,
+        render: &RenderArgs,
+        flags: &RenderOptions,
+    ) -> Result<RenderOptions, Box<dyn Error>> {
+        fn parse<T>(key: &str, value: &Option<String>) -> Result<Option<T>, String>
+        where
+            T: FromStr,
+            T::Err: Display,
+        {
+            value
+                .as_deref()
+                .map(T::from_str)
+                .transpose()
+                .map_err(|e| format!("invalid `{}`: {}", key, e))
+        }
+
+        let flags = flags.clone();
+        Ok(RenderOptions {
+            rule: parse("rule", &self.rule)?.unwrap_or(flags.rule),
+            size: parse("size", &self.size)?.unwrap_or(flags.size),
+            charset: parse("charset", &self.charset)?.unwrap_or(flags.charset),
+            color: parse::<ColorMode>("color", &self.color)?
+                .map_or(flags.color, |color| color.with_metric(render.color_metric)),
+            dither: parse("dither", &self.dither)?.unwrap_or(flags.dither),
+            preprocess: Preprocess {
+                contrast: self.contrast.unwrap_or(flags.preprocess.contrast),
+                ..flags.preprocess
+            },
+            filter: parse("filter", &self.filter)?.unwrap_or(flags.filter),
+            quiet_zone: self.quiet_zone.unwrap_or(flags.quiet_zone),
+            format: parse("format", &self.format)?.unwrap_or(flags.format),
+            invert: self.invert.unwrap_or(flags.invert),
+            ..flags
+        })
+    }
+}
+
+/// Renders the [`Job`] of every line of stdin, printing its result as a line
+/// of JSON once done: `{"path": ..., "text": ...}`, `{"path": ...,
+/// "output": ...}` once written to its output, or `{"path": ...,
+/// "error": ...}`. A failed job doesn't stop the others.
+fn run_stdin_jsonl(opts: &Opts, render_opts: &RenderOptions) -> Result<(), Box<dyn Error>> {
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = match serde_json::from_str::<Job>(&line) {
+            Ok(job) => match run_job(opts, &job, render_opts) {
+                Ok(Some(text)) => json!({ "path": job.path, "text": text }),
+                Ok(None) => json!({ "path": job.path, "output": job.output }),
+                Err(e) => json!({ "path": job.path, "error": e.to_string() }),
+            },
+            Err(e) => json!({ "error": format!("invalid job: {}", e) }),
+        };
+        writeln!(stdout, "{}", result)?;
+        stdout.flush()?;
+    }
+
+    Ok(())
+}
 
-        if re.is_match(s) {
-            let thr = re.captures(s).unwrap().iter().nth(1).unwrap().unwrap();
+/// Renders `job`, returning the text unless it is written to its output.
+fn run_job(
+    opts: &Opts,
+    job: &Job,
+    render_opts: &RenderOptions,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let render_opts = job.options(&opts.render, render_opts)?;
+    let img = open_input(opts, &job.path, &render_opts, Animated::FirstFrame)?.unwrap();
 
-            return Ok(OnOffRule::PxThreshold(i32::from_str(thr.as_str())?));
+    match &job.output {
+        Some(output) => {
+            emit(opts, &img, &render_opts, Some(output), &mut |_, _| {})?;
+            Ok(None)
         }
+        None if matches!(render_opts.format, OutputFormat::Png(_)) => {
+            Err("PNG output needs an `output`".into())
+        }
+        None => Ok(Some(render_observed(&img, &render_opts, &mut |_, _| {}))),
+    }
+}
+
+/// Renders every input into one grid, see [`render_montage`].
+fn run_montage(
+    opts: &Opts,
+    inputs: &[PathBuf],
+    montage: &Montage,
+    render_opts: &RenderOptions,
+) -> Result<(), Box<dyn Error>> {
+    if render_opts.format != OutputFormat::Text {
+        return Err("--montage only writes text".into());
+    }
+
+    let images = inputs
+        .iter()
+        .map(|input| {
+            let caption = input
+                .file_name()
+                .unwrap_or(input.as_os_str())
+                .to_string_lossy();
+            Ok((
+                caption,
+                open_input(opts, input, render_opts, Animated::FirstFrame)?.unwrap(),
+            ))
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+    let images: Vec<_> = images
+        .iter()
+        .map(|(caption, img)| (caption.as_ref(), img))
+        .collect();
+
+    write_text(
+        opts,
+        &render_montage(&images, montage, render_opts),
+        opts.output.as_deref(),
+    )
+}
 
-        let re = Regex::new(r"^InvertedThreshold\((\d+)\)$").unwrap();
+/// Renders the image at every threshold of `sweep` in a row, see
+/// [`render_sweep`].
+fn run_sweep(
+    opts: &Opts,
+    input: &Path,
+    sweep: &Sweep,
+    render_opts: &RenderOptions,
+) -> Result<(), Box<dyn Error>> {
+    if render_opts.format != OutputFormat::Text {
+        return Err("--sweep only writes text".into());
+    }
+
+    let img = open_input(opts, input, render_opts, Animated::FirstFrame)?.unwrap();
+    write_text(
+        opts,
+        &render_sweep(&img, sweep, render_opts),
+        opts.output.as_deref(),
+    )
+}
+
+/// Renders the frames of an animation into one grid, see
+/// [`render_contact_sheet`].
+fn run_contact_sheet(
+    opts: &Opts,
+    input: &Path,
+    sheet: &ContactSheet,
+    render_opts: &RenderOptions,
+) -> Result<(), Box<dyn Error>> {
+    if render_opts.format != OutputFormat::Text {
+        return Err("--contact-sheet only writes text".into());
+    }
+
+    let selection = opts.frame_selection();
+    let text = if is_video(input) {
+        let count = selection.count(Video::frame_count(input)?);
+        let frames = selection.select(Video::open(input)?);
+        render_contact_sheet(frames, count, sheet, render_opts)?
+    } else {
+        let (bytes, format) = read_input(opts, input)?;
+        if format != Some(ImageFormat::Gif) {
+            return Err("--contact-sheet needs an animated GIF or a video".into());
+        }
+        let frames = gif_frames(&bytes, selection)?;
+        let count = frames.len();
+        render_contact_sheet(
+            frames.into_iter().map(Ok::<_, io::Error>),
+            count,
+            sheet,
+            render_opts,
+        )?
+    };
 
-        if re.is_match(s) {
-            let thr = re.captures(s).unwrap().iter().nth(1).unwrap().unwrap();
+    write_text(opts, &text, opts.output.as_deref())
+}
 
-            return Ok(OnOffRule::InvertedPxThreshold(i32::from_str(thr.as_str())?));
+fn strips_escapes(opts: &Opts, output: Option<&Path>) -> bool {
+    match opts.escapes {
+        Escapes::Auto => {
+            (output.is_some() || opts.to_clipboard) && opts.format != OutputFormat::AnsiDump
         }
+        Escapes::Keep => false,
+        Escapes::Strip => true,
+    }
+}
+
+/// Renders a PNG input to `--output` or stdout as it's decoded, see
+/// [`render_streamed`].
+fn run_stream(
+    opts: &Opts,
+    input: &Path,
+    render_opts: &RenderOptions,
+) -> Result<(), Box<dyn Error>> {
+    let reader: Box<dyn Read> = if input == Path::new("-") {
+        Box::new(io::stdin())
+    } else {
+        Box::new(File::open(input)?)
+    };
+    let decoder = PngDecoder::new(BufReader::new(reader))
+        .map_err(|e| format!("--stream needs a PNG input: {}", e))?;
+
+    // the escapes can't be stripped as the text goes out, leave out the
+    // colors instead; the bands can't be resized, so the cell aspect is
+    // only the one asked for
+    let render_opts = RenderOptions {
+        color: if strips_escapes(opts, opts.output.as_deref()) {
+            ColorMode::None
+        } else {
+            render_opts.color
+        },
+        cell_aspect: opts.render.cell_aspect.unwrap_or_default(),
+        ..render_opts.clone()
+    };
 
-        let re = Regex::new(r"^Border\((\d+),(\d+)\)$").unwrap();
+    match &opts.output {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(opts.append)
+                .truncate(!opts.append)
+                .open(path)?;
+            render_streamed(decoder, &render_opts, &mut BufWriter::new(file))?
+        }
+        None => render_streamed(decoder, &render_opts, &mut io::stdout().lock())?,
+    }
 
-        if re.is_match(s) {
-            let captures = re.captures(s).unwrap();
-            let mut captures_iter = captures.iter();
-            let border_threshold = captures_iter.nth(1).unwrap().unwrap();
-            let border_size = captures_iter.next().unwrap().unwrap();
+    Ok(())
+}
 
-            return Ok(OnOffRule::Border(
-                i32::from_str(border_threshold.as_str())?,
-                i32::from_str(border_size.as_str())?,
-            ));
+/// The command line this program was run with, with the rule and dither
+/// of `tuned` instead of the original ones.
+fn invocation(tuned: &RenderOptions) -> String {
+    let mut args = env::args();
+    let mut words = vec![args.next().unwrap_or_else(|| "asciidraw".into())];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tune" => {}
+            "-r" | "--rule" | "--dither" => {
+                args.next();
+            }
+            arg if arg.starts_with("--rule=") || arg.starts_with("--dither=") => {}
+            arg if arg.starts_with("-r") && !arg.starts_with("--") => {}
+            _ => words.push(arg),
         }
+    }
+    words.push("--rule".into());
+    words.push(tuned.rule.to_string());
+    words.push("--dither".into());
+    words.push(tuned.dither.to_string());
+
+    words
+        .iter()
+        .map(|word| shell_quote(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-        Err(OnOffRuleParseError::UnknownFormat(s.into()))
+fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c));
+    if plain {
+        word.into()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
     }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let opts: Opts = Opts::parse();
-    let img = image::open(opts.input)?;
+/// Renders the input again every time it changes, until interrupted.
+fn run_watch(opts: &Opts, input: &Path, render_opts: &RenderOptions) -> Result<(), Box<dyn Error>> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+
+    // editors often replace the file instead of writing to it, watch its
+    // directory to keep following it
+    let dir = match input.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    loop {
+        if opts.output.is_none() && !opts.to_clipboard {
+            print!("\x1b[2J\x1b[H");
+        }
+        // the file may be caught half written, the next change fixes it
+        let rendered = open_input(opts, input, render_opts, Animated::FirstFrame).and_then(|img| {
+            emit(
+                opts,
+                &img.unwrap(),
+                render_opts,
+                opts.output.as_deref(),
+                &mut |_, _| {},
+            )
+        });
+        if let Err(e) = rendered {
+            eprintln!("{}: {}", input.display(), e);
+        }
+        io::stdout().flush()?;
+
+        loop {
+            let event: Event = rx.recv()??;
+            let changed = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name() == input.file_name());
+            if changed {
+                break;
+            }
+        }
+        // let the writes settle before reading the file again
+        thread::sleep(Duration::from_millis(100));
+        while rx.try_recv().is_ok() {}
+    }
+}
 
-    let img = match &opts.size {
-        ImageSize::Default => img,
-        ImageSize::Sized { width, height } => {
-            if *width != img.width() || *height != img.height() {
-                img.resize(*width, *height, FilterType::Triangle)
-            } else {
-                img
+/// Expands the glob patterns among the inputs, keeping the other inputs
+/// as they are.
+fn expand_inputs(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut expanded = Vec::new();
+    for input in inputs {
+        match input.to_str() {
+            Some(pattern) if pattern.contains(&['*', '?', '['][..]) => {
+                let matches = glob::glob(pattern)?.collect::<Result<Vec<_>, _>>()?;
+                if matches.is_empty() {
+                    return Err(format!("no file matches `{}`", pattern).into());
+                }
+                expanded.extend(matches);
             }
+            _ => expanded.push(input.clone()),
         }
+    }
+
+    Ok(expanded)
+}
+
+/// Renders every input to a file of the same name in `out_dir`, in
+/// parallel, then prints a summary of the failures.
+fn run_batch(
+    opts: &Opts,
+    inputs: &[PathBuf],
+    out_dir: &Path,
+    render_opts: &RenderOptions,
+) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(out_dir)?;
+    let extension = match render_opts.format {
+        OutputFormat::Text => "txt",
+        OutputFormat::Html => "html",
+        OutputFormat::Svg(_) => "svg",
+        OutputFormat::Json => "json",
+        OutputFormat::Sixel => "six",
+        OutputFormat::Kitty => "kitty",
+        OutputFormat::Iterm2 => "iterm2",
+        OutputFormat::Png(_) => "png",
+        OutputFormat::Asciicast => "cast",
+        OutputFormat::AnsiDump => "ans",
     };
-    let (width, height) = img.dimensions();
 
-    let rl = opts.rule;
+    let bar = opts
+        .progress
+        .then(|| ProgressBar::new(inputs.len() as u64).with_style(progress_style()));
+    let converted: Vec<(&PathBuf, Result<(), String>, Timings)> = inputs
+        .par_iter()
+        .map(|input| {
+            let stem = input.file_stem().unwrap_or_else(|| input.as_os_str());
+            let output = out_dir.join(stem).with_extension(extension);
+            let mut timings = Timings::default();
 
-    let mat: Vec<Vec<bool>> = (0..height)
-        .map(|y| {
-            (0..width)
-                .into_par_iter()
-                .map(|x| rl.is_on(&img, x, y))
-                .collect()
+            let start = Instant::now();
+            let animated = Animated::record_or(render_opts, Some(&output), Animated::FirstFrame);
+            let result = open_input(opts, input, render_opts, animated)
+                .and_then(|img| {
+                    timings.decode = start.elapsed();
+                    match img {
+                        Some(img) => emit(
+                            opts,
+                            &img,
+                            render_opts,
+                            Some(&output),
+                            &mut |stage, elapsed| timings.record(stage, elapsed),
+                        ),
+                        // recorded already
+                        None => Ok(()),
+                    }
+                })
+                .map_err(|e| e.to_string());
+            if let Some(bar) = &bar {
+                bar.inc(1);
+            }
+
+            (input, result, timings)
         })
         .collect();
+    if let Some(bar) = &bar {
+        bar.finish_and_clear();
+    }
 
-    (0..=height / 4).for_each(|y| {
-        (0..=width / 2).for_each(|x| {
-            let v = region_braille(x, y, |(y, x)| {
-                if !img.in_bounds(x, y) {
-                    return None;
-                }
+    let timings = converted
+        .iter()
+        .fold(Timings::default(), |sum, &(_, _, timings)| sum.add(timings));
+    let failures: Vec<(&PathBuf, &String)> = converted
+        .iter()
+        .filter_map(|(input, result, _)| Some((*input, result.as_ref().err()?)))
+        .collect();
 
-                Some(mat[y as usize][x as usize])
-            });
+    for (input, e) in &failures {
+        eprintln!("{}: {}", input.display(), e);
+    }
+    eprintln!(
+        "converted {} of {} images",
+        inputs.len() - failures.len(),
+        inputs.len()
+    );
 
-            let chr = std::char::from_u32(v).unwrap();
+    if opts.timing {
+        timings.report();
+    }
 
-            print!("{}", chr);
-        });
-        println!()
-    });
+    if !failures.is_empty() {
+        return Err(format!("{} images failed", failures.len()).into());
+    }
 
     Ok(())
 }
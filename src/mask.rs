@@ -0,0 +1,95 @@
+use crate::bitmap::Bitmap;
+use crate::Preprocess;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, GrayImage, ImageError};
+use std::path::Path;
+
+/// The level from which the pixels of a mask let the pixels under them on.
+const OPEN: u8 = 128;
+
+/// A stencil over the input: only the pixels under its opaque, or else
+/// bright, pixels can be on. It's stretched over the input when of another
+/// size, and rotated, cropped and resized along with it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mask {
+    levels: GrayImage,
+}
+
+impl Mask {
+    /// The mask of the alpha of `img`, or of its luma when it has none.
+    pub fn new(img: &DynamicImage) -> Self {
+        let levels = match img.color().has_alpha() {
+            true => {
+                let rgba = img.to_rgba8();
+                GrayImage::from_fn(img.width(), img.height(), |x, y| {
+                    image::Luma([rgba.get_pixel(x, y)[3]])
+                })
+            }
+            false => img.to_luma8(),
+        };
+
+        Mask { levels }
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        Ok(Mask::new(&image::open(path)?))
+    }
+
+    /// The pixels the mask lets on in an image of `input` size, once
+    /// `preprocess` transformed and resized to `size`.
+    pub(crate) fn bitmap(
+        &self,
+        input: (u32, u32),
+        preprocess: &Preprocess,
+        size: (u32, u32),
+    ) -> Bitmap {
+        let mut levels = DynamicImage::ImageLuma8(self.levels.clone());
+        if levels.dimensions() != input {
+            levels = levels.resize_exact(input.0, input.1, FilterType::Triangle);
+        }
+        let levels = preprocess
+            .transform(&levels)
+            .resize_exact(size.0, size.1, FilterType::Triangle)
+            .to_luma8();
+
+        Bitmap::from_fn(size.0, size.1, |x, y| levels.get_pixel(x, y)[0] >= OPEN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rotation;
+    use image::{Luma, LumaA};
+
+    #[test]
+    fn reads_the_alpha_or_else_the_luma() {
+        let luma = DynamicImage::ImageLuma8(GrayImage::from_fn(2, 1, |x, _| Luma([x as u8 * 255])));
+        let bitmap = Mask::new(&luma).bitmap((2, 1), &Preprocess::default(), (2, 1));
+        assert_eq!((bitmap.get(0, 0), bitmap.get(1, 0)), (false, true));
+
+        // the luma is left aside for the alpha
+        let alpha = DynamicImage::ImageLumaA8(image::ImageBuffer::from_fn(2, 1, |x, _| {
+            LumaA([255, 255 - x as u8 * 255])
+        }));
+        let bitmap = Mask::new(&alpha).bitmap((2, 1), &Preprocess::default(), (2, 1));
+        assert_eq!((bitmap.get(0, 0), bitmap.get(1, 0)), (true, false));
+    }
+
+    #[test]
+    fn follows_the_input() {
+        // the left half of a mask, over an input turned a quarter
+        let mask = DynamicImage::ImageLuma8(GrayImage::from_fn(4, 2, |x, _| {
+            Luma([if x < 2 { 255 } else { 0 }])
+        }));
+        let preprocess = Preprocess {
+            rotate: Some(Rotation::Quarter),
+            ..Preprocess::default()
+        };
+        let bitmap = Mask::new(&mask).bitmap((4, 2), &preprocess, (2, 4));
+
+        // the left of the input ends up at the top
+        let on: Vec<bool> = (0..4).map(|y| bitmap.get(0, y)).collect();
+        assert_eq!(on, [true, true, false, false]);
+    }
+}
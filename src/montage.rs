@@ -0,0 +1,420 @@
+use crate::animation::Frame;
+use crate::cell::Cell;
+use crate::{text, with_ascii, OnOffRule, RenderOptions, RuleExpr};
+use image::DynamicImage;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Blank columns between the tiles of a row.
+const GAP: usize = 2;
+
+/// How several images are laid out in one output: in a grid of `columns`
+/// tiles, with the caption of every tile beneath it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Montage {
+    pub columns: u32,
+    pub captions: bool,
+}
+
+#[derive(Error, Debug)]
+pub enum MontageParseError {
+    #[error("invalid montage `{0}`, expected cols=N, optionally followed by ,captions")]
+    InvalidMontage(String),
+}
+
+impl FromStr for Montage {
+    type Err = MontageParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || MontageParseError::InvalidMontage(s.into());
+        let mut montage = Montage {
+            columns: 0,
+            captions: false,
+        };
+
+        for part in s.split(',') {
+            match part.split_once('=') {
+                Some(("cols", columns)) => {
+                    montage.columns = u32::from_str(columns).map_err(|_| invalid())?
+                }
+                None if part == "captions" => montage.captions = true,
+                _ => return Err(invalid()),
+            }
+        }
+
+        match montage.columns {
+            0 => Err(invalid()),
+            _ => Ok(montage),
+        }
+    }
+}
+
+/// Renders the `images` into one grid of text, each under its caption if
+/// asked, in the color mode of `opts`. The size of `opts` is shared by the
+/// whole grid: a width is the width of a row of tiles.
+pub fn render_montage(
+    images: &[(&str, &DynamicImage)],
+    montage: &Montage,
+    opts: &RenderOptions,
+) -> String {
+    let tiles: Vec<_> = images
+        .iter()
+        .map(|&(caption, img)| (caption, img, &opts.rule))
+        .collect();
+    render_tiles(&tiles, montage, opts)
+}
+
+/// [`render_montage`] of images each evaluated with their own rule.
+fn render_tiles(
+    images: &[(&str, &DynamicImage, &RuleExpr)],
+    montage: &Montage,
+    opts: &RenderOptions,
+) -> String {
+    let columns = montage.columns.max(1) as usize;
+    let rows = images.len().div_ceil(columns);
+    let (cell_width, cell_height) = opts.charset.cell_size();
+    let caption_rows = montage.captions as u32;
+
+    let tile_opts = RenderOptions {
+        size: opts.size.tile(
+            (columns as u32, rows as u32),
+            (GAP as u32 * cell_width, (1 + caption_rows) * cell_height),
+            (cell_width, cell_height),
+        ),
+        ..opts.clone()
+    };
+    let tiles: Vec<Vec<Vec<Cell>>> = images
+        .iter()
+        .map(|&(_, img, rule)| {
+            let tile_opts = RenderOptions {
+                rule: rule.clone(),
+                ..tile_opts.clone()
+            };
+            with_ascii(img, &tile_opts, &mut |_, _| {}, |ascii| {
+                ascii.cells(&opts.charset)
+            })
+        })
+        .collect();
+
+    // every column as wide as its widest tile
+    let widths: Vec<usize> = (0..columns)
+        .map(|c| {
+            tiles
+                .iter()
+                .skip(c)
+                .step_by(columns)
+                .map(|tile| tile.first().map_or(0, Vec::len))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut lines: Vec<Vec<Cell>> = Vec::new();
+    for (r, row) in tiles.chunks(columns).enumerate() {
+        if r > 0 {
+            lines.push(Vec::new());
+        }
+
+        let height = row.iter().map(Vec::len).max().unwrap_or(0);
+        let mut join = |cell: &dyn Fn(usize, usize) -> Vec<Cell>| {
+            let mut line = Vec::new();
+            for (c, &width) in widths.iter().enumerate().take(row.len()) {
+                if c > 0 {
+                    line.extend(vec![Cell::new(' '); GAP]);
+                }
+                let mut cells = cell(c, width);
+                // the last tile of the line isn't padded
+                if c + 1 < row.len() {
+                    cells.resize(width, Cell::new(' '));
+                }
+                line.extend(cells);
+            }
+            lines.push(line);
+        };
+
+        for y in 0..height {
+            join(&|c, _| row[c].get(y).cloned().unwrap_or_default());
+        }
+        if montage.captions {
+            join(&|c, width| {
+                images[r * columns + c]
+                    .0
+                    .chars()
+                    .take(width)
+                    .map(Cell::new)
+                    .collect()
+            });
+        }
+    }
+
+    let mut out = String::new();
+    text::write(&lines, opts.color, &mut out);
+    out
+}
+
+/// Luma thresholds from `start` to `end`, both included, `step` apart, to
+/// render an image at each side by side: `50..250:50`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Sweep {
+    pub start: u8,
+    pub end: u8,
+    pub step: u8,
+}
+
+#[derive(Error, Debug)]
+pub enum SweepParseError {
+    #[error("invalid sweep `{0}`, expected START..END:STEP with thresholds in 0..=255")]
+    InvalidSweep(String),
+    #[error("the sweep must go up from START to END, by a STEP of at least 1")]
+    EmptySweep,
+}
+
+impl FromStr for Sweep {
+    type Err = SweepParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SweepParseError::InvalidSweep(s.into());
+        let (range, step) = s.split_once(':').ok_or_else(invalid)?;
+        let (start, end) = range.split_once("..").ok_or_else(invalid)?;
+        let sweep = Sweep {
+            start: u8::from_str(start).map_err(|_| invalid())?,
+            end: u8::from_str(end).map_err(|_| invalid())?,
+            step: u8::from_str(step).map_err(|_| invalid())?,
+        };
+
+        match sweep.step == 0 || sweep.start > sweep.end {
+            true => Err(SweepParseError::EmptySweep),
+            false => Ok(sweep),
+        }
+    }
+}
+
+impl Sweep {
+    pub fn thresholds(&self) -> impl Iterator<Item = u8> {
+        (self.start..=self.end).step_by(self.step.max(1) as usize)
+    }
+}
+
+/// Renders `img` once for every threshold of `sweep`, in a row with the
+/// `Luma` rule of each beneath it; the size of `opts` is shared by the row
+/// like with [`render_montage`].
+pub fn render_sweep(img: &DynamicImage, sweep: &Sweep, opts: &RenderOptions) -> String {
+    let rules: Vec<(String, RuleExpr)> = sweep
+        .thresholds()
+        .map(|t| {
+            let rule = RuleExpr::from(OnOffRule::LumaThreshold(t as i32));
+            (rule.to_string(), rule)
+        })
+        .collect();
+    let tiles: Vec<_> = rules
+        .iter()
+        .map(|(caption, rule)| (caption.as_str(), img, rule))
+        .collect();
+    let montage = Montage {
+        columns: tiles.len() as u32,
+        captions: true,
+    };
+
+    render_tiles(&tiles, &montage, opts)
+}
+
+/// A preview of an animation: a grid of `rows` by `columns` frames sampled
+/// evenly across it, each under the time it is shown at.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ContactSheet {
+    pub rows: u32,
+    pub columns: u32,
+}
+
+#[derive(Error, Debug)]
+pub enum ContactSheetParseError {
+    #[error("invalid contact sheet `{0}`, expected ROWSxCOLS")]
+    InvalidContactSheet(String),
+}
+
+impl FromStr for ContactSheet {
+    type Err = ContactSheetParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ContactSheetParseError::InvalidContactSheet(s.into());
+        let (rows, columns) = s.split_once(['x', '×']).ok_or_else(invalid)?;
+        let rows = u32::from_str(rows).map_err(|_| invalid())?;
+        let columns = u32::from_str(columns).map_err(|_| invalid())?;
+
+        match (rows, columns) {
+            (0, _) | (_, 0) => Err(invalid()),
+            _ => Ok(ContactSheet { rows, columns }),
+        }
+    }
+}
+
+impl ContactSheet {
+    /// The indices of the frames in the sheet out of `count` frames, spread
+    /// evenly from the first one; all of them when there are fewer.
+    pub fn sample(&self, count: usize) -> Vec<usize> {
+        let tiles = (self.rows as usize * self.columns as usize).min(count);
+        (0..tiles).map(|i| i * count / tiles).collect()
+    }
+}
+
+/// Renders the frames of an animation `count` frames long into a contact
+/// sheet, decoding them only up to the last sampled one; `count` may be an
+/// estimate, a sheet having fewer tiles when it is too high.
+pub fn render_contact_sheet<I, E>(
+    frames: I,
+    count: usize,
+    sheet: &ContactSheet,
+    opts: &RenderOptions,
+) -> Result<String, E>
+where
+    I: Iterator<Item = Result<Frame, E>>,
+{
+    let mut samples = sheet.sample(count).into_iter().peekable();
+    let mut tiles = Vec::new();
+    let mut time = Duration::ZERO;
+    for (index, frame) in frames.enumerate() {
+        let Some(&sample) = samples.peek() else {
+            break;
+        };
+        let frame = frame?;
+        if index == sample {
+            samples.next();
+            tiles.push((timestamp(time), frame.image));
+        }
+        time += frame.delay;
+    }
+
+    let images: Vec<_> = tiles
+        .iter()
+        .map(|(caption, img)| (caption.as_str(), img))
+        .collect();
+    let montage = Montage {
+        columns: sheet.columns,
+        captions: true,
+    };
+    Ok(render_montage(&images, &montage, opts))
+}
+
+/// `time` as minutes, seconds and tenths, e.g. `1:02.5`.
+fn timestamp(time: Duration) -> String {
+    let tenths = time.as_millis() / 100;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageSize;
+    use image::{GrayImage, Luma};
+
+    fn lit(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_pixel(width, height, Luma([255])))
+    }
+
+    #[test]
+    fn parses_montage() {
+        assert_eq!(
+            "cols=3,captions".parse::<Montage>().unwrap(),
+            Montage {
+                columns: 3,
+                captions: true
+            }
+        );
+        assert!("cols=0".parse::<Montage>().is_err());
+        assert!("rows=2".parse::<Montage>().is_err());
+    }
+
+    #[test]
+    fn lays_out_a_grid() {
+        let (a, b, c) = (lit(4, 4), lit(2, 8), lit(2, 4));
+        let montage = Montage {
+            columns: 2,
+            captions: true,
+        };
+        let out = render_montage(
+            &[("a", &a), ("bee", &b), ("c", &c)],
+            &montage,
+            &RenderOptions::default(),
+        );
+
+        assert_eq!(out, "⣿⣿  ⣿\n    ⣿\na   b\n\n⣿\nc\n");
+    }
+
+    #[test]
+    fn parses_sweeps() {
+        let sweep: Sweep = "50..250:50".parse().unwrap();
+        assert_eq!(
+            sweep.thresholds().collect::<Vec<_>>(),
+            [50, 100, 150, 200, 250]
+        );
+        assert_eq!(
+            "0..255:100"
+                .parse::<Sweep>()
+                .unwrap()
+                .thresholds()
+                .collect::<Vec<_>>(),
+            [0, 100, 200]
+        );
+        assert!("250..50:50".parse::<Sweep>().is_err());
+        assert!("50..250:0".parse::<Sweep>().is_err());
+        assert!("50..300:50".parse::<Sweep>().is_err());
+        assert!("50..250".parse::<Sweep>().is_err());
+    }
+
+    #[test]
+    fn sweeps_the_threshold() {
+        let img = DynamicImage::ImageLuma8(GrayImage::from_pixel(20, 4, Luma([100])));
+        let sweep: Sweep = "50..150:100".parse().unwrap();
+        let out = render_sweep(&img, &sweep, &RenderOptions::default());
+
+        assert_eq!(out, "⣿⣿⣿⣿⣿⣿⣿⣿⣿⣿  ⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀\nLuma(50)    Luma(150)\n");
+    }
+
+    #[test]
+    fn samples_contact_sheets() {
+        let sheet: ContactSheet = "2x3".parse().unwrap();
+        assert_eq!(
+            sheet,
+            ContactSheet {
+                rows: 2,
+                columns: 3
+            }
+        );
+        assert_eq!("2×3".parse::<ContactSheet>().unwrap(), sheet);
+        assert!("2x0".parse::<ContactSheet>().is_err());
+        assert_eq!(sheet.sample(60), [0, 10, 20, 30, 40, 50]);
+        assert_eq!(sheet.sample(4), [0, 1, 2, 3]);
+
+        let frames = (0..4).map(|_| {
+            Ok::<_, ()>(Frame {
+                image: lit(12, 4),
+                delay: Duration::from_millis(750),
+            })
+        });
+        let sheet = ContactSheet {
+            rows: 1,
+            columns: 2,
+        };
+        let out = render_contact_sheet(frames, 4, &sheet, &RenderOptions::default());
+        assert_eq!(out, Ok("⣿⣿⣿⣿⣿⣿  ⣿⣿⣿⣿⣿⣿\n0:00.0  0:01.5\n".to_string()));
+        assert_eq!(timestamp(Duration::from_millis(62_540)), "1:02.5");
+    }
+
+    #[test]
+    fn shares_the_size() {
+        let img = lit(40, 40);
+        let montage = Montage {
+            columns: 2,
+            captions: false,
+        };
+        let opts = RenderOptions {
+            size: ImageSize::Width(44),
+            ..RenderOptions::default()
+        };
+        let out = render_montage(&[("", &img), ("", &img)], &montage, &opts);
+
+        // two tiles of 20 pixels, 10 cells, and a gap of 2 cells
+        assert_eq!(out.lines().next().unwrap().chars().count(), 22);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::bitmap::Bitmap;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// An operation of mathematical morphology on the pixels on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MorphOp {
+    /// Keeps on the pixels whose neighborhood is all on, thinning strokes.
+    Erode,
+    /// Turns on the pixels with any neighbor on, thickening strokes.
+    Dilate,
+    /// An erosion, then a dilation: removes the specks smaller than the
+    /// neighborhood and keeps the rest as it was.
+    Open,
+    /// A dilation, then an erosion: fills the holes and gaps smaller than
+    /// the neighborhood.
+    Close,
+}
+
+/// A morphological operation over the square neighborhood of every pixel
+/// reaching `radius` pixels away, `open:1`; the pixels past the edges are
+/// left out of the neighborhoods.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Morph {
+    pub op: MorphOp,
+    pub radius: u32,
+}
+
+#[derive(Error, Debug)]
+pub enum MorphParseError {
+    #[error("unknown morphological operation `{0}`, expected erode, dilate, open or close")]
+    UnknownOp(String),
+    #[error("invalid radius `{0}`, expected at least 1")]
+    InvalidRadius(String),
+}
+
+impl FromStr for MorphOp {
+    type Err = MorphParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "erode" => Ok(MorphOp::Erode),
+            "dilate" => Ok(MorphOp::Dilate),
+            "open" => Ok(MorphOp::Open),
+            "close" => Ok(MorphOp::Close),
+            _ => Err(MorphParseError::UnknownOp(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for MorphOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MorphOp::Erode => write!(f, "erode"),
+            MorphOp::Dilate => write!(f, "dilate"),
+            MorphOp::Open => write!(f, "open"),
+            MorphOp::Close => write!(f, "close"),
+        }
+    }
+}
+
+/// `OP:RADIUS`, the radius being 1 when left out.
+impl FromStr for Morph {
+    type Err = MorphParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (op, radius) = s.split_once(':').unwrap_or((s, "1"));
+        let radius = match u32::from_str(radius) {
+            Ok(radius) if radius > 0 => radius,
+            _ => return Err(MorphParseError::InvalidRadius(radius.into())),
+        };
+
+        Ok(Morph {
+            op: op.parse()?,
+            radius,
+        })
+    }
+}
+
+impl fmt::Display for Morph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.op, self.radius)
+    }
+}
+
+impl Morph {
+    pub fn apply(&self, bitmap: &Bitmap) -> Bitmap {
+        let r = self.radius;
+        match self.op {
+            MorphOp::Erode => erode(bitmap, r),
+            MorphOp::Dilate => dilate(bitmap, r),
+            MorphOp::Open => dilate(&erode(bitmap, r), r),
+            MorphOp::Close => erode(&dilate(bitmap, r), r),
+        }
+    }
+}
+
+fn erode(bitmap: &Bitmap, radius: u32) -> Bitmap {
+    sweep(bitmap, radius, true)
+}
+
+fn dilate(bitmap: &Bitmap, radius: u32) -> Bitmap {
+    sweep(bitmap, radius, false)
+}
+
+/// The square neighborhoods, taken as a row then a column: every pixel on
+/// when `all` of its neighbors are, or else when any is.
+fn sweep(bitmap: &Bitmap, radius: u32, all: bool) -> Bitmap {
+    let (width, height) = (bitmap.width(), bitmap.height());
+    let span = |at: u32, len: u32| at.saturating_sub(radius)..(at + radius + 1).min(len);
+    let fold = |mut neighbors: Range<u32>, on: &dyn Fn(u32) -> bool| match all {
+        true => neighbors.all(on),
+        false => neighbors.any(on),
+    };
+
+    let rows = Bitmap::from_fn(width, height, |x, y| {
+        fold(span(x, width), &|nx| bitmap.get(nx, y))
+    });
+    Bitmap::from_fn(width, height, |x, y| {
+        fold(span(y, height), &|ny| rows.get(x, ny))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bitmap(rows: &[&str]) -> Bitmap {
+        Bitmap::from_fn(rows[0].len() as u32, rows.len() as u32, |x, y| {
+            rows[y as usize].as_bytes()[x as usize] == b'#'
+        })
+    }
+
+    fn rows(bitmap: &Bitmap) -> Vec<String> {
+        (0..bitmap.height())
+            .map(|y| {
+                (0..bitmap.width())
+                    .map(|x| if bitmap.get(x, y) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_operations() {
+        let morph: Morph = "open:2".parse().unwrap();
+        assert_eq!(
+            morph,
+            Morph {
+                op: MorphOp::Open,
+                radius: 2
+            }
+        );
+        assert_eq!(morph.to_string(), "open:2");
+        assert_eq!("dilate".parse::<Morph>().unwrap().radius, 1);
+
+        assert!("open:0".parse::<Morph>().is_err());
+        assert!("blur:1".parse::<Morph>().is_err());
+    }
+
+    #[test]
+    fn opening_removes_specks() {
+        let img = bitmap(&["#.....", "...###", "...###", "...###"]);
+        let opened = "open:1".parse::<Morph>().unwrap().apply(&img);
+
+        assert_eq!(rows(&opened), ["......", "...###", "...###", "...###"]);
+    }
+
+    #[test]
+    fn closing_fills_holes() {
+        let img = bitmap(&["#####", "##.##", "#####"]);
+        let closed = "close:1".parse::<Morph>().unwrap().apply(&img);
+
+        assert_eq!(rows(&closed), ["#####", "#####", "#####"]);
+    }
+
+    #[test]
+    fn dilates_and_erodes() {
+        let img = bitmap(&[".....", ".....", "..#..", ".....", "....."]);
+        let dilated = "dilate:1".parse::<Morph>().unwrap().apply(&img);
+        assert_eq!(
+            rows(&dilated),
+            [".....", ".###.", ".###.", ".###.", "....."]
+        );
+
+        let eroded = "erode:1".parse::<Morph>().unwrap().apply(&dilated);
+        assert_eq!(rows(&eroded), rows(&img));
+
+        // the pixels past the edges don't count
+        let eroded = "erode:1"
+            .parse::<Morph>()
+            .unwrap()
+            .apply(&bitmap(&["##.", "##."]));
+        assert_eq!(rows(&eroded), ["#..", "#.."]);
+    }
+}
@@ -0,0 +1,63 @@
+use std::io::{self, Read};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Limits on the downloads of remote images.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FetchOptions {
+    /// Time allowed for the whole request, body included.
+    pub timeout: Duration,
+    /// Largest body accepted, in bytes.
+    pub max_size: u64,
+    /// Redirects followed before giving up.
+    pub max_redirects: u32,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        FetchOptions {
+            timeout: Duration::from_secs(30),
+            max_size: 50 * 1024 * 1024,
+            max_redirects: 5,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum FetchError {
+    #[error("request failed: {0}")]
+    Request(#[from] Box<ureq::Error>),
+    #[error("couldn't read the response: {0}")]
+    Io(#[from] io::Error),
+    #[error("the response is larger than {0} bytes")]
+    TooLarge(u64),
+}
+
+/// Downloads the body at `url`.
+pub fn fetch(url: &str, opts: &FetchOptions) -> Result<Vec<u8>, FetchError> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(opts.timeout)
+        .redirects(opts.max_redirects)
+        .build();
+    let response = agent.get(url).call().map_err(Box::new)?;
+
+    let announced = response
+        .header("Content-Length")
+        .and_then(|len| len.parse::<u64>().ok());
+    if announced.is_some_and(|len| len > opts.max_size) {
+        return Err(FetchError::TooLarge(opts.max_size));
+    }
+
+    // the announced length can't be trusted, stop reading right after the
+    // limit
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .take(opts.max_size + 1)
+        .read_to_end(&mut body)?;
+    if body.len() as u64 > opts.max_size {
+        return Err(FetchError::TooLarge(opts.max_size));
+    }
+
+    Ok(body)
+}
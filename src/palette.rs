@@ -0,0 +1,396 @@
+use crate::cell::Cell;
+use crate::color::{parse_hex_color, HexColorParseError};
+use image::{Pixel, Rgb};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+/// How the distance between two colors is measured when quantizing.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ColorMetric {
+    #[default]
+    Rgb,
+    Lab,
+}
+
+#[derive(Error, Debug)]
+pub enum ColorMetricParseError {
+    #[error("unknown color metric `{0}`")]
+    UnknownColorMetric(String),
+}
+
+impl FromStr for ColorMetric {
+    type Err = ColorMetricParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rgb" => Ok(ColorMetric::Rgb),
+            "lab" | "cielab" => Ok(ColorMetric::Lab),
+            _ => Err(ColorMetricParseError::UnknownColorMetric(s.into())),
+        }
+    }
+}
+
+/// The default xterm colors for the 16 basic ANSI colors.
+const XTERM_16: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// A fixed set of colors, the output colors can be quantized to with
+/// [`crate::RenderOptions::palette`] to match the theme of a terminal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    rgb: Vec<Rgb<u8>>,
+    lab: Vec<[f32; 3]>,
+}
+
+#[derive(Error, Debug)]
+pub enum PaletteError {
+    #[error("couldn't read the palette")]
+    Io(#[from] std::io::Error),
+    #[error("invalid palette color on line {0}")]
+    InvalidColor(usize, #[source] HexColorParseError),
+    #[error("the palette has no colors")]
+    Empty,
+}
+
+/// The palettes built in, by name.
+pub const BUILTIN_PALETTES: [&str; 3] = ["gruvbox", "nord", "solarized"];
+
+/// The palette built in under `name`, see [`BUILTIN_PALETTES`].
+pub fn builtin_palette(name: &str) -> Option<Palette> {
+    let colors: &[[u8; 3]] = match name {
+        "gruvbox" => &GRUVBOX,
+        "nord" => &NORD,
+        "solarized" => &SOLARIZED,
+        _ => return None,
+    };
+
+    Some(Palette::new(colors.iter().map(|&c| Rgb(c)).collect()))
+}
+
+const GRUVBOX: [[u8; 3]; 16] = [
+    [0x28, 0x28, 0x28],
+    [0xcc, 0x24, 0x1d],
+    [0x98, 0x97, 0x1a],
+    [0xd7, 0x99, 0x21],
+    [0x45, 0x85, 0x88],
+    [0xb1, 0x62, 0x86],
+    [0x68, 0x9d, 0x6a],
+    [0xa8, 0x99, 0x84],
+    [0x92, 0x83, 0x74],
+    [0xfb, 0x49, 0x34],
+    [0xb8, 0xbb, 0x26],
+    [0xfa, 0xbd, 0x2f],
+    [0x83, 0xa5, 0x98],
+    [0xd3, 0x86, 0x9b],
+    [0x8e, 0xc0, 0x7c],
+    [0xeb, 0xdb, 0xb2],
+];
+
+const NORD: [[u8; 3]; 16] = [
+    [0x2e, 0x34, 0x40],
+    [0x3b, 0x42, 0x52],
+    [0x43, 0x4c, 0x5e],
+    [0x4c, 0x56, 0x6a],
+    [0xd8, 0xde, 0xe9],
+    [0xe5, 0xe9, 0xf0],
+    [0xec, 0xef, 0xf4],
+    [0x8f, 0xbc, 0xbb],
+    [0x88, 0xc0, 0xd0],
+    [0x81, 0xa1, 0xc1],
+    [0x5e, 0x81, 0xac],
+    [0xbf, 0x61, 0x6a],
+    [0xd0, 0x87, 0x70],
+    [0xeb, 0xcb, 0x8b],
+    [0xa3, 0xbe, 0x8c],
+    [0xb4, 0x8e, 0xad],
+];
+
+const SOLARIZED: [[u8; 3]; 16] = [
+    [0x00, 0x2b, 0x36],
+    [0x07, 0x36, 0x42],
+    [0x58, 0x6e, 0x75],
+    [0x65, 0x7b, 0x83],
+    [0x83, 0x94, 0x96],
+    [0x93, 0xa1, 0xa1],
+    [0xee, 0xe8, 0xd5],
+    [0xfd, 0xf6, 0xe3],
+    [0xb5, 0x89, 0x00],
+    [0xcb, 0x4b, 0x16],
+    [0xdc, 0x32, 0x2f],
+    [0xd3, 0x36, 0x82],
+    [0x6c, 0x71, 0xc4],
+    [0x26, 0x8b, 0xd2],
+    [0x2a, 0xa1, 0x98],
+    [0x85, 0x99, 0x00],
+];
+
+/// Parses a palette file: a color per line as `#rrggbb` or `#rgb`, what
+/// follows it on the line (its name) being ignored, and the blank lines and
+/// the lines starting with `;` skipped.
+impl FromStr for Palette {
+    type Err = PaletteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let colors = s
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                let color = line.split_whitespace().next()?;
+                (!color.starts_with(';')).then(|| {
+                    parse_hex_color(color).map_err(|e| PaletteError::InvalidColor(i + 1, e))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match colors.is_empty() {
+            true => Err(PaletteError::Empty),
+            false => Ok(Palette::new(colors)),
+        }
+    }
+}
+
+impl Palette {
+    fn new(rgb: Vec<Rgb<u8>>) -> Self {
+        let lab = rgb.iter().map(|&c| to_lab(c)).collect();
+        Palette { rgb, lab }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, PaletteError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    pub fn colors(&self) -> &[Rgb<u8>] {
+        &self.rgb
+    }
+
+    /// Replaces the foreground and background colors of the `cells` by the
+    /// nearest colors of the palette; when `dither`ing, the error of every
+    /// color is spread over the colors of the next cells (Floyd-Steinberg),
+    /// the cells without a color neither taking nor spreading any.
+    pub(crate) fn quantize(&self, cells: &mut [Vec<Cell>], metric: ColorMetric, dither: bool) {
+        let fields: [ColorField; 2] = [|c| &mut c.fg, |c| &mut c.bg];
+        for color in fields {
+            let mut error = vec![vec![[0f32; 3]; cells.first().map_or(0, Vec::len)]; 2];
+
+            for row in cells.iter_mut() {
+                error.rotate_left(1);
+                error[1].iter_mut().for_each(|e| *e = [0.0; 3]);
+
+                for x in 0..row.len() {
+                    let Some(c) = *color(&mut row[x]) else {
+                        continue;
+                    };
+                    let wanted = [0, 1, 2].map(|ch| c[ch] as f32 + error[0][x][ch]);
+                    let nearest = self.rgb[self.nearest(
+                        Rgb(wanted.map(|v| v.round().clamp(0.0, 255.0) as u8)),
+                        metric,
+                    )];
+                    *color(&mut row[x]) = Some(nearest);
+                    if !dither {
+                        continue;
+                    }
+
+                    let err = [0, 1, 2].map(|ch| wanted[ch] - nearest[ch] as f32);
+                    let mut spread = |dx: isize, dy: usize, factor: f32| {
+                        if let Some(e) = x
+                            .checked_add_signed(dx)
+                            .and_then(|nx| error[dy].get_mut(nx))
+                        {
+                            (0..3).for_each(|ch| e[ch] += err[ch] * factor);
+                        }
+                    };
+                    spread(1, 0, 7.0 / 16.0);
+                    spread(-1, 1, 3.0 / 16.0);
+                    spread(0, 1, 5.0 / 16.0);
+                    spread(1, 1, 1.0 / 16.0);
+                }
+            }
+        }
+    }
+
+    fn nearest(&self, c: Rgb<u8>, metric: ColorMetric) -> usize {
+        let dist = |i: usize| match metric {
+            ColorMetric::Rgb => {
+                let p = self.rgb[i];
+                (0..3)
+                    .map(|ch| {
+                        let d = c[ch] as f32 - p[ch] as f32;
+                        d * d
+                    })
+                    .sum::<f32>()
+            }
+            ColorMetric::Lab => {
+                let l = to_lab(c);
+                let p = self.lab[i];
+                (0..3)
+                    .map(|ch| (l[ch] - p[ch]) * (l[ch] - p[ch]))
+                    .sum::<f32>()
+            }
+        };
+
+        (0..self.rgb.len())
+            .min_by(|&a, &b| dist(a).partial_cmp(&dist(b)).unwrap())
+            .unwrap()
+    }
+}
+
+/// The foreground or background color of a cell.
+type ColorField = fn(&mut Cell) -> &mut Option<Rgb<u8>>;
+
+fn xterm_16() -> &'static Palette {
+    static PALETTE: OnceLock<Palette> = OnceLock::new();
+    PALETTE.get_or_init(|| Palette::new(XTERM_16.iter().map(|&c| Rgb(c)).collect()))
+}
+
+fn xterm_256() -> &'static Palette {
+    static PALETTE: OnceLock<Palette> = OnceLock::new();
+    PALETTE.get_or_init(|| {
+        let base = XTERM_16.iter().map(|&c| Rgb(c));
+        let cube = (0..216).map(|i| {
+            Rgb([
+                CUBE_LEVELS[i / 36],
+                CUBE_LEVELS[(i / 6) % 6],
+                CUBE_LEVELS[i % 6],
+            ])
+        });
+        let grays = (0..24).map(|i| {
+            let v = 8 + i * 10;
+            Rgb([v, v, v])
+        });
+
+        Palette::new(base.chain(cube).chain(grays).collect())
+    })
+}
+
+/// The colors of the xterm 256-color palette.
+pub(crate) fn xterm_256_colors() -> &'static [Rgb<u8>] {
+    &xterm_256().rgb
+}
+
+/// The colors of the basic 16-color palette.
+pub(crate) fn xterm_16_colors() -> &'static [Rgb<u8>] {
+    &xterm_16().rgb
+}
+
+/// Index of the xterm 256-color palette entry closest to `c`.
+pub(crate) fn nearest_256(c: Rgb<u8>, metric: ColorMetric) -> u8 {
+    xterm_256().nearest(c, metric) as u8
+}
+
+/// The 24 steps of the grayscale ramp closing the xterm 256-color palette.
+pub(crate) fn xterm_grays() -> &'static [Rgb<u8>] {
+    &xterm_256().rgb[232..]
+}
+
+/// Index in [`xterm_grays`] of the step closest to the brightness of `c`.
+pub(crate) fn nearest_gray(c: Rgb<u8>) -> u8 {
+    // the steps go from 8 to 238 by 10
+    ((c.to_luma()[0] as f32 - 8.0) / 10.0)
+        .round()
+        .clamp(0.0, 23.0) as u8
+}
+
+/// Index of the basic 16-color palette entry closest to `c`.
+pub(crate) fn nearest_16(c: Rgb<u8>, metric: ColorMetric) -> u8 {
+    xterm_16().nearest(c, metric) as u8
+}
+
+/// Converts an sRGB color to CIELAB, under the D65 white point.
+fn to_lab(c: Rgb<u8>) -> [f32; 3] {
+    let lin = |v: u8| {
+        let v = v as f32 / 255.0;
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (lin(c[0]), lin(c[1]), lin(c[2]));
+
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f32| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_palettes() {
+        let palette: Palette = "; black and white\n#000 black\n\nffffff\n".parse().unwrap();
+
+        assert_eq!(palette.colors(), &[Rgb([0, 0, 0]), Rgb([255, 255, 255])]);
+        assert!(matches!(
+            "#000\nblack\n".parse::<Palette>(),
+            Err(PaletteError::InvalidColor(2, _))
+        ));
+        assert!(matches!(
+            "; none\n".parse::<Palette>(),
+            Err(PaletteError::Empty)
+        ));
+        assert_eq!(builtin_palette("nord").unwrap().colors().len(), 16);
+    }
+
+    #[test]
+    fn picks_grays() {
+        assert_eq!(nearest_gray(Rgb([0, 0, 0])), 0);
+        assert_eq!(nearest_gray(Rgb([128, 128, 128])), 12);
+        assert_eq!(xterm_grays()[12], Rgb([128, 128, 128]));
+        assert_eq!(nearest_gray(Rgb([255, 255, 255])), 23);
+    }
+
+    #[test]
+    fn quantizes_cells() {
+        let palette: Palette = "#000\n#fff\n".parse().unwrap();
+        let gray = Cell::new(' ').fg(Some(Rgb([128, 128, 128])));
+        let quantize = |dither| {
+            let mut cells = vec![vec![gray; 4], vec![Cell::new(' '); 4]];
+            palette.quantize(&mut cells, ColorMetric::Rgb, dither);
+            cells
+        };
+        let fg = |cells: &[Vec<Cell>]| {
+            cells[0]
+                .iter()
+                .map(|c| c.fg.unwrap()[0])
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(fg(&quantize(false)), [255; 4]);
+        // the error spread, half of the cells turn black
+        assert_eq!(fg(&quantize(true)), [255, 0, 255, 0]);
+        // the cells without colors stay so
+        assert_eq!(quantize(true)[1], vec![Cell::new(' '); 4]);
+    }
+}
@@ -0,0 +1,39 @@
+//! Work spread over the rows of an image: in parallel with the `parallel`
+//! feature, one row after the other without it.
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// `f` of every row from 0 to `rows`, in order.
+pub(crate) fn map_rows<T, F>(rows: u32, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(u32) -> T + Sync,
+{
+    #[cfg(feature = "parallel")]
+    return (0..rows).into_par_iter().map(&f).collect();
+
+    #[cfg(not(feature = "parallel"))]
+    (0..rows).map(f).collect()
+}
+
+/// Calls `f` with every row of `buf`, `len` long, and its index.
+///
+/// # Panics
+///
+/// When `len` is 0.
+pub(crate) fn for_each_row_mut<T, F>(buf: &mut [T], len: usize, f: F)
+where
+    T: Send,
+    F: Fn(u32, &mut [T]) + Sync,
+{
+    #[cfg(feature = "parallel")]
+    buf.par_chunks_mut(len)
+        .enumerate()
+        .for_each(|(y, row)| f(y as u32, row));
+
+    #[cfg(not(feature = "parallel"))]
+    buf.chunks_mut(len)
+        .enumerate()
+        .for_each(|(y, row)| f(y as u32, row));
+}
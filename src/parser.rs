@@ -0,0 +1,349 @@
+use crate::expr::RuleExpr;
+use crate::registry::lookup;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Errors point at the position, counted in characters from 1, where the
+/// expression went wrong.
+#[derive(Error, Debug)]
+pub enum RuleExprParseError {
+    #[error("unexpected character `{0}` at position {1}")]
+    UnexpectedChar(char, usize),
+    #[error("expected {0} at position {1}, found {2}")]
+    Expected(&'static str, usize, String),
+    #[error("integer too large at position {0}")]
+    IntegerTooLarge(usize),
+    #[error("unknown rule `{0}` at position {1}")]
+    UnknownRule(String, usize),
+    #[error("too many arguments for `{0}` at position {1}, it takes {2}")]
+    TooManyArguments(String, usize, usize),
+    #[error("`{0}` has no parameter `{1}` (position {2})")]
+    UnknownParameter(String, String, usize),
+    #[error("`{1}` of `{0}` is given twice (position {2})")]
+    DuplicateArgument(String, String, usize),
+    #[error("`{0}` is missing `{1}` at position {2}")]
+    MissingArgument(String, String, usize),
+    #[error("invalid arguments for `{0}` at position {1}: {2}")]
+    InvalidArguments(String, usize, String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    LParen,
+    RParen,
+    Comma,
+    Equals,
+    Not,
+    And,
+    Or,
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "`{}`", name),
+            Token::Int(n) => write!(f, "`{}`", n),
+            Token::LParen => write!(f, "`(`"),
+            Token::RParen => write!(f, "`)`"),
+            Token::Comma => write!(f, "`,`"),
+            Token::Equals => write!(f, "`=`"),
+            Token::Not => write!(f, "`!`"),
+            Token::And => write!(f, "`&`"),
+            Token::Or => write!(f, "`|`"),
+            Token::End => write!(f, "the end"),
+        }
+    }
+}
+
+/// Splits `s` into tokens, each with the position it starts at.
+fn tokenize(s: &str) -> Result<Vec<(Token, usize)>, RuleExprParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().enumerate().peekable();
+
+    while let Some((pos, c)) = chars.next() {
+        let pos = pos + 1;
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '=' => Token::Equals,
+            '!' => Token::Not,
+            // doubled operators, as in C, are accepted too
+            '&' | '|' => {
+                chars.next_if(|&(_, next)| next == c);
+                if c == '&' {
+                    Token::And
+                } else {
+                    Token::Or
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some((_, c)) =
+                    chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_')
+                {
+                    name.push(c);
+                }
+                Token::Ident(name)
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut digits = c.to_string();
+                while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_ascii_digit()) {
+                    digits.push(c);
+                }
+                if digits == "-" {
+                    return Err(RuleExprParseError::UnexpectedChar('-', pos));
+                }
+                let n =
+                    i64::from_str(&digits).map_err(|_| RuleExprParseError::IntegerTooLarge(pos))?;
+                Token::Int(n)
+            }
+            c => return Err(RuleExprParseError::UnexpectedChar(c, pos)),
+        };
+        tokens.push((token, pos));
+    }
+
+    tokens.push((Token::End, s.chars().count() + 1));
+    Ok(tokens)
+}
+
+/// `|` binds the loosest, then `&`, then `!`. Arguments are integers,
+/// given in order or by name, as in `Border(threshold = 30, distance = 2)`.
+impl FromStr for RuleExpr {
+    type Err = RuleExprParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            next: 0,
+        };
+        let expr = parser.or()?;
+        parser.expect(Token::End, "`&`, `|` or the end")?;
+
+        Ok(expr)
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &(Token, usize) {
+        &self.tokens[self.next]
+    }
+
+    fn bump(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.next].clone();
+        // the end stays there
+        if token.0 != Token::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, token: Token) -> bool {
+        if self.peek().0 == token {
+            self.bump();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, token: Token, expected: &'static str) -> Result<(), RuleExprParseError> {
+        let (found, pos) = self.bump();
+        if found != token {
+            return Err(RuleExprParseError::Expected(
+                expected,
+                pos,
+                found.to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn or(&mut self) -> Result<RuleExpr, RuleExprParseError> {
+        let mut expr = self.and()?;
+        while self.eat(Token::Or) {
+            expr = RuleExpr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<RuleExpr, RuleExprParseError> {
+        let mut expr = self.not()?;
+        while self.eat(Token::And) {
+            expr = RuleExpr::And(Box::new(expr), Box::new(self.not()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<RuleExpr, RuleExprParseError> {
+        if self.eat(Token::Not) {
+            return Ok(RuleExpr::Not(Box::new(self.not()?)));
+        }
+
+        self.operand()
+    }
+
+    fn operand(&mut self) -> Result<RuleExpr, RuleExprParseError> {
+        match self.bump() {
+            (Token::LParen, _) => {
+                let expr = self.or()?;
+                self.expect(Token::RParen, "`)`")?;
+                Ok(expr)
+            }
+            (Token::Ident(name), pos) => self.rule(name, pos),
+            (found, pos) => Err(RuleExprParseError::Expected(
+                "a rule",
+                pos,
+                found.to_string(),
+            )),
+        }
+    }
+
+    /// A rule, its name already read, maybe followed by its arguments.
+    fn rule(&mut self, name: String, pos: usize) -> Result<RuleExpr, RuleExprParseError> {
+        let entry =
+            lookup(&name).ok_or_else(|| RuleExprParseError::UnknownRule(name.clone(), pos))?;
+        let mut args: Vec<Option<i32>> = vec![None; entry.params.len()];
+
+        if self.eat(Token::LParen) && !self.eat(Token::RParen) {
+            let mut given = 0;
+            loop {
+                let (param, arg_pos) = match self.peek().clone() {
+                    (Token::Ident(param), arg_pos) => {
+                        self.bump();
+                        self.expect(Token::Equals, "`=`")?;
+                        let index =
+                            entry
+                                .params
+                                .iter()
+                                .position(|&p| p == param)
+                                .ok_or_else(|| {
+                                    RuleExprParseError::UnknownParameter(
+                                        name.clone(),
+                                        param.clone(),
+                                        arg_pos,
+                                    )
+                                })?;
+                        (index, arg_pos)
+                    }
+                    (_, arg_pos) => (given, arg_pos),
+                };
+                let value = self.int()?;
+                given += 1;
+
+                match args.get_mut(param) {
+                    Some(Some(_)) => {
+                        return Err(RuleExprParseError::DuplicateArgument(
+                            name,
+                            entry.params[param].into(),
+                            arg_pos,
+                        ))
+                    }
+                    Some(arg) => *arg = Some(value),
+                    None => {
+                        return Err(RuleExprParseError::TooManyArguments(
+                            name,
+                            arg_pos,
+                            entry.params.len(),
+                        ))
+                    }
+                }
+
+                if !self.eat(Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(Token::RParen, "`,` or `)`")?;
+        }
+
+        let end = self.peek().1;
+        let args = args
+            .iter()
+            .zip(entry.params)
+            .map(|(arg, param)| {
+                arg.ok_or_else(|| {
+                    RuleExprParseError::MissingArgument(name.clone(), param.to_string(), end)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let rule = (entry.constructor)(&args)
+            .map_err(|e| RuleExprParseError::InvalidArguments(name, pos, e))?;
+        Ok(RuleExpr::Rule(rule))
+    }
+
+    fn int(&mut self) -> Result<i32, RuleExprParseError> {
+        match self.bump() {
+            (Token::Int(n), pos) => {
+                i32::try_from(n).map_err(|_| RuleExprParseError::IntegerTooLarge(pos))
+            }
+            (found, pos) => Err(RuleExprParseError::Expected(
+                "integer",
+                pos,
+                found.to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(s: &str) -> RuleExprParseError {
+        s.parse::<RuleExpr>().unwrap_err()
+    }
+
+    #[test]
+    fn points_at_the_unexpected_token() {
+        // an unclosed `(` runs into the end
+        assert!(matches!(
+            error("Luma(128"),
+            RuleExprParseError::Expected("`,` or `)`", 9, found) if found == "the end"
+        ));
+        assert!(matches!(
+            error("Luma(128) &"),
+            RuleExprParseError::Expected("a rule", 12, found) if found == "the end"
+        ));
+        assert!(matches!(
+            error("Luma(128) $ Otsu"),
+            RuleExprParseError::UnexpectedChar('$', 11)
+        ));
+        assert_eq!(
+            error("Luma(128) $ Otsu").to_string(),
+            "unexpected character `$` at position 11"
+        );
+    }
+
+    #[test]
+    fn points_at_the_rules_and_their_arguments() {
+        assert!(matches!(
+            error("Otsu | Lume(128)"),
+            RuleExprParseError::UnknownRule(name, 8) if name == "Lume"
+        ));
+        assert!(matches!(
+            error("Border(30, 2, 1)"),
+            RuleExprParseError::TooManyArguments(_, 15, 2)
+        ));
+        assert!(matches!(
+            error("Border(threshold = 30, size = 2)"),
+            RuleExprParseError::UnknownParameter(_, param, 24) if param == "size"
+        ));
+        assert!(matches!(
+            error("Border(30)"),
+            RuleExprParseError::MissingArgument(_, param, 11) if param == "distance"
+        ));
+    }
+}
@@ -0,0 +1,82 @@
+use image::{DynamicImage, ImageError, ImageFormat};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use thiserror::Error;
+
+/// Whether `bytes` are a PDF document, going by its header.
+pub fn is_pdf(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"%PDF-")
+}
+
+/// The page of a PDF document rasterized, and at what resolution.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PdfOptions {
+    /// The page, counted from 1.
+    pub page: u32,
+    /// Dots per inch, 72 rasterizing the page at its size in points.
+    pub dpi: f32,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        PdfOptions {
+            page: 1,
+            dpi: 150.0,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PdfError {
+    #[error("couldn't run pdftoppm: {0}")]
+    Io(#[from] io::Error),
+    #[error("couldn't rasterize page {0}: {1}")]
+    Rasterize(u32, String),
+    #[error("couldn't decode the rasterized page: {0}")]
+    Image(#[from] ImageError),
+}
+
+/// Rasterizes a page of the PDF document in `bytes` with poppler's
+/// `pdftoppm`.
+pub fn rasterize_pdf(bytes: &[u8], opts: &PdfOptions) -> Result<DynamicImage, PdfError> {
+    let page = opts.page.to_string();
+    let mut child = Command::new("pdftoppm")
+        .args(["-f", &page, "-l", &page])
+        .args(["-r", &opts.dpi.to_string()])
+        .args(["-png", "-singlefile", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // the document is written while the page is read, pdftoppm may start
+    // writing before it's done reading
+    let mut stdin = child.stdin.take().unwrap();
+    let output = thread::scope(|s| {
+        s.spawn(move || stdin.write_all(bytes));
+        child.wait_with_output()
+    })?;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(PdfError::Rasterize(opts.page, message));
+    }
+
+    Ok(image::load_from_memory_with_format(
+        &output.stdout,
+        ImageFormat::Png,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_pdfs_by_their_header() {
+        assert!(is_pdf(b"%PDF-1.7\n%\xe2\xe3\xcf\xd3"));
+        assert!(!is_pdf(b"\x89PNG\r\n\x1a\n"));
+        assert!(!is_pdf(b""));
+    }
+}
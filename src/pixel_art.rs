@@ -0,0 +1,168 @@
+use crate::{CellAspect, ImageSize};
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The size of the pixels of pixel art, in pixels of the image: sprites are
+/// often saved scaled up, every pixel of the art a square of image pixels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PixelGrid {
+    /// The largest grid the runs of same colored pixels fall on.
+    Detect,
+    Size(u32),
+}
+
+#[derive(Error, Debug)]
+pub enum PixelGridParseError {
+    #[error("invalid pixel grid `{0}`, expected auto or a positive size")]
+    InvalidPixelGrid(String),
+}
+
+impl FromStr for PixelGrid {
+    type Err = PixelGridParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(PixelGrid::Detect),
+            _ => u32::from_str(s)
+                .ok()
+                .filter(|size| *size > 0)
+                .map(PixelGrid::Size)
+                .ok_or_else(|| PixelGridParseError::InvalidPixelGrid(s.into())),
+        }
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b),
+    }
+}
+
+/// The pixel grid of `img`: the greatest common divisor of the lengths of
+/// the runs of same colored pixels along the rows and columns. The runs
+/// touching the edges are left out, the art may be cropped.
+pub fn detect_grid(img: &DynamicImage) -> u32 {
+    let img = img.to_rgba8();
+    let (width, height) = img.dimensions();
+    let mut grid = 0;
+
+    // the runs along a line of `len` pixels, `pixel` giving the one at `i`
+    let mut runs = |len: u32, pixel: &dyn Fn(u32) -> image::Rgba<u8>| {
+        let mut start = 0;
+        for i in 1..=len {
+            if i == len || pixel(i) != pixel(i - 1) {
+                if start > 0 && i < len {
+                    grid = gcd(grid, i - start);
+                }
+                start = i;
+            }
+        }
+    };
+
+    for y in 0..height {
+        runs(width, &|x| *img.get_pixel(x, y));
+    }
+    for x in 0..width {
+        runs(height, &|y| *img.get_pixel(x, y));
+    }
+
+    grid.max(1)
+}
+
+/// Scales the pixel art `img` so that every pixel of its `grid` becomes the
+/// same whole number of pixels of the output, with no blending; as large
+/// as fits `size`, and at least one pixel per pixel of the art. The cells
+/// of the given aspect stretch the art by whole factors too.
+pub fn scale(
+    img: &DynamicImage,
+    grid: PixelGrid,
+    size: ImageSize,
+    cell: (u32, u32),
+    aspect: CellAspect,
+) -> DynamicImage {
+    let grid = match grid {
+        PixelGrid::Detect => detect_grid(img),
+        PixelGrid::Size(size) => size,
+    };
+    let (width, height) = img.dimensions();
+    let art = (width.div_ceil(grid), height.div_ceil(grid));
+
+    // the pixels of the output per pixel of the art, at the smallest
+    let ratio = aspect.pixel_ratio(cell);
+    let (unit_x, unit_y) = if ratio >= 1.0 {
+        (1, ratio.round() as u32)
+    } else {
+        ((1.0 / ratio).round() as u32, 1)
+    };
+    let (max_width, max_height) = size.dimensions(art, cell, aspect);
+    let factor = (max_width / (art.0 * unit_x).max(1))
+        .min(max_height / (art.1 * unit_y).max(1))
+        .max(1);
+    let (step_x, step_y) = (unit_x * factor, unit_y * factor);
+
+    let img = img.to_rgba8();
+    // every pixel of the art is sampled in the middle of its square
+    let sample = |art: u32, len: u32| (art * grid + grid / 2).min(len - 1);
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(
+        art.0 * step_x,
+        art.1 * step_y,
+        |x, y| *img.get_pixel(sample(x / step_x, width), sample(y / step_y, height)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    /// A 4x3 sprite, every pixel of it a `grid`x`grid` square.
+    fn sprite(grid: u32) -> DynamicImage {
+        let art = [[0, 255, 255, 0], [255, 0, 0, 255], [0, 0, 255, 255]];
+        DynamicImage::ImageLuma8(GrayImage::from_fn(4 * grid, 3 * grid, |x, y| {
+            Luma([art[(y / grid) as usize][(x / grid) as usize]])
+        }))
+    }
+
+    #[test]
+    fn detects_the_grid() {
+        assert_eq!(detect_grid(&sprite(1)), 1);
+        assert_eq!(detect_grid(&sprite(3)), 3);
+        assert_eq!(detect_grid(&sprite(4)), 4);
+        // no runs inside the image
+        assert_eq!(detect_grid(&sprite(4).crop_imm(0, 0, 4, 4)), 1);
+    }
+
+    #[test]
+    fn scales_by_whole_factors() {
+        let braille = ((2, 4), CellAspect::default());
+        let scaled = |size| scale(&sprite(3), PixelGrid::Detect, size, braille.0, braille.1);
+
+        // a pixel of the art per dot
+        let one = scaled(ImageSize::Default);
+        assert_eq!(one.dimensions(), (4, 3));
+        assert_eq!(one.to_luma8().into_raw(), sprite(1).to_luma8().into_raw());
+
+        // as large as fits, and never smaller than the art
+        assert_eq!(scaled(ImageSize::Width(11)).dimensions(), (8, 6));
+        assert_eq!(scaled(ImageSize::Width(2)).dimensions(), (4, 3));
+        assert_eq!(
+            scaled(ImageSize::Width(12)).to_luma8(),
+            sprite(3).to_luma8()
+        );
+    }
+
+    #[test]
+    fn stretches_for_the_cell_aspect() {
+        // quadrant dots are twice as tall as wide
+        let scaled = scale(
+            &sprite(1),
+            PixelGrid::Size(1),
+            ImageSize::Default,
+            (2, 2),
+            CellAspect::default(),
+        );
+        assert_eq!(scaled.dimensions(), (8, 3));
+    }
+}
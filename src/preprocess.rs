@@ -0,0 +1,258 @@
+use image::{DynamicImage, GenericImageView, Rgba};
+use std::borrow::Cow;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Unsharp mask used by [`Preprocess::sharpen`].
+const SHARPEN_SIGMA: f32 = 1.0;
+const SHARPEN_THRESHOLD: i32 = 2;
+
+/// Adjustments applied to the image before the on/off rule: geometric
+/// transforms before it is resized, filters and tone adjustments after.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Preprocess {
+    /// Clockwise rotation, done first.
+    pub rotate: Option<Rotation>,
+    /// Mirroring, done after the rotation.
+    pub flip: Option<Flip>,
+    /// Cropping, in the coordinates of the rotated and flipped image.
+    pub crop: Option<Crop>,
+    /// Added to every channel, in `-255..=255`.
+    pub brightness: i32,
+    /// Contrast change in percent, positive values increasing it.
+    pub contrast: f32,
+    /// Gamma correction, values above 1 brightening the mid tones.
+    pub gamma: f32,
+    /// Standard deviation of a gaussian blur, in pixels of the resized
+    /// image.
+    pub blur: Option<f32>,
+    pub sharpen: bool,
+}
+
+impl Default for Preprocess {
+    fn default() -> Self {
+        Preprocess {
+            rotate: None,
+            flip: None,
+            crop: None,
+            brightness: 0,
+            contrast: 0.0,
+            gamma: 1.0,
+            blur: None,
+            sharpen: false,
+        }
+    }
+}
+
+impl Preprocess {
+    /// Applies the geometric transforms, done on the original image.
+    pub fn transform<'a>(&self, img: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        let img = orient(Cow::Borrowed(img), self.rotate, self.flip);
+        match self.crop {
+            Some(crop) => Cow::Owned(crop.apply(&img)),
+            None => img,
+        }
+    }
+
+    /// Applies the blur and sharpening, done on the resized image.
+    pub fn filter<'a>(&self, img: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        let mut img = Cow::Borrowed(img);
+        if let Some(sigma) = self.blur {
+            img = Cow::Owned(img.blur(sigma));
+        }
+        if self.sharpen {
+            img = Cow::Owned(img.unsharpen(SHARPEN_SIGMA, SHARPEN_THRESHOLD));
+        }
+
+        img
+    }
+
+    /// Applies the tone adjustments, done on the resized image.
+    pub fn adjust_tones<'a>(&self, img: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        if self.brightness == 0 && self.contrast == 0.0 && self.gamma == 1.0 {
+            return Cow::Borrowed(img);
+        }
+
+        let lut = self.lut();
+        let mut rgba = img.to_rgba8();
+        rgba.pixels_mut().for_each(|Rgba(p)| {
+            p[..3].iter_mut().for_each(|v| *v = lut[*v as usize]);
+        });
+
+        Cow::Owned(DynamicImage::ImageRgba8(rgba))
+    }
+
+    /// The adjusted value of every channel value: brightness first, then
+    /// contrast (as [`DynamicImage::adjust_contrast`]), then gamma.
+    fn lut(&self) -> [u8; 256] {
+        let percent = ((100.0 + self.contrast) / 100.0).powi(2);
+        let mut lut = [0; 256];
+
+        lut.iter_mut().enumerate().for_each(|(v, out)| {
+            let v = (v as i32 + self.brightness).clamp(0, 255) as f32 / 255.0;
+            let v = ((v - 0.5) * percent + 0.5).clamp(0.0, 1.0);
+            let v = v.powf(1.0 / self.gamma);
+
+            *out = (v * 255.0).round() as u8;
+        });
+
+        lut
+    }
+}
+
+/// A region of the image to keep.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Crop {
+    Region {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    /// A region of the given size in the middle of the image.
+    Center { width: u32, height: u32 },
+}
+
+impl Crop {
+    /// Crops the image, the region being clipped to the image but never
+    /// smaller than one pixel.
+    pub fn apply(&self, img: &DynamicImage) -> DynamicImage {
+        let (iw, ih) = img.dimensions();
+        let (x, y, width, height) = match *self {
+            Crop::Region {
+                x,
+                y,
+                width,
+                height,
+            } => (x, y, width, height),
+            Crop::Center { width, height } => (
+                iw.saturating_sub(width) / 2,
+                ih.saturating_sub(height) / 2,
+                width,
+                height,
+            ),
+        };
+
+        let x = x.min(iw.saturating_sub(1));
+        let y = y.min(ih.saturating_sub(1));
+        img.crop_imm(x, y, width.min(iw - x).max(1), height.min(ih - y).max(1))
+    }
+
+    /// Parses a centered crop, `WxH`.
+    pub fn parse_center(s: &str) -> Result<Self, CropParseError> {
+        let (width, height) = parse_size(s)?;
+
+        Ok(Crop::Center { width, height })
+    }
+}
+
+fn parse_size(s: &str) -> Result<(u32, u32), CropParseError> {
+    let (w, h) = s
+        .split_once('x')
+        .ok_or_else(|| CropParseError::UnknownFormat(s.into()))?;
+
+    Ok((u32::from_str(w)?, u32::from_str(h)?))
+}
+
+#[derive(Error, Debug)]
+pub enum CropParseError {
+    #[error("couldn't parse an int in the crop region")]
+    ParseIntError(#[from] ParseIntError),
+    #[error("unknown crop format `{0}`, expected X,Y,WxH")]
+    UnknownFormat(String),
+}
+
+/// Parses a region, `X,Y,WxH`.
+impl FromStr for Crop {
+    type Err = CropParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, ',');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(x), Some(y), Some(size)) => {
+                let (width, height) = parse_size(size)?;
+
+                Ok(Crop::Region {
+                    x: u32::from_str(x)?,
+                    y: u32::from_str(y)?,
+                    width,
+                    height,
+                })
+            }
+            _ => Err(CropParseError::UnknownFormat(s.into())),
+        }
+    }
+}
+
+/// Rotates, then flips the image.
+pub fn orient<'a>(
+    img: Cow<'a, DynamicImage>,
+    rotate: Option<Rotation>,
+    flip: Option<Flip>,
+) -> Cow<'a, DynamicImage> {
+    let img = match rotate {
+        Some(Rotation::Quarter) => Cow::Owned(img.rotate90()),
+        Some(Rotation::Half) => Cow::Owned(img.rotate180()),
+        Some(Rotation::ThreeQuarters) => Cow::Owned(img.rotate270()),
+        None => img,
+    };
+    match flip {
+        Some(Flip::Horizontal) => Cow::Owned(img.fliph()),
+        Some(Flip::Vertical) => Cow::Owned(img.flipv()),
+        None => img,
+    }
+}
+
+/// A clockwise rotation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+#[derive(Error, Debug)]
+pub enum RotationParseError {
+    #[error("unknown rotation `{0}`, expected 90, 180 or 270")]
+    UnknownRotation(String),
+}
+
+impl FromStr for Rotation {
+    type Err = RotationParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "90" => Ok(Rotation::Quarter),
+            "180" => Ok(Rotation::Half),
+            "270" => Ok(Rotation::ThreeQuarters),
+            _ => Err(RotationParseError::UnknownRotation(s.into())),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Flip {
+    /// Mirrors left and right.
+    Horizontal,
+    /// Mirrors top and bottom.
+    Vertical,
+}
+
+#[derive(Error, Debug)]
+pub enum FlipParseError {
+    #[error("unknown flip `{0}`, expected h or v")]
+    UnknownFlip(String),
+}
+
+impl FromStr for Flip {
+    type Err = FlipParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "h" | "horizontal" => Ok(Flip::Horizontal),
+            "v" | "vertical" => Ok(Flip::Vertical),
+            _ => Err(FlipParseError::UnknownFlip(s.into())),
+        }
+    }
+}
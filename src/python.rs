@@ -0,0 +1,68 @@
+//! The `ascii_image` Python module; build it with `maturin build`, which
+//! reads the features from `pyproject.toml`.
+
+use crate::{ImageSize, RenderOptions};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Renders the image at the path `image`, or encoded in the bytes `image`,
+/// as text.
+///
+/// `width` is the width in pixels the image is resized to, the height
+/// following; the other options are strings parsed like the flags of the
+/// command line.
+#[pyfunction]
+#[pyo3(signature = (image, width=None, rule=None, charset=None, dither=None, color=None, invert=false))]
+fn render(
+    image: &Bound<'_, PyAny>,
+    width: Option<u32>,
+    rule: Option<&str>,
+    charset: Option<&str>,
+    dither: Option<&str>,
+    color: Option<&str>,
+    invert: bool,
+) -> PyResult<String> {
+    let img = if let Ok(bytes) = image.downcast::<PyBytes>() {
+        image::load_from_memory(bytes.as_bytes()).map_err(value_error)?
+    } else {
+        let path: PathBuf = image.extract()?;
+        image::open(&path).map_err(|e| PyIOError::new_err(format!("{}: {}", path.display(), e)))?
+    };
+
+    let defaults = RenderOptions::default();
+    let opts = RenderOptions {
+        size: width.map_or(defaults.size, ImageSize::Width),
+        rule: parse("rule", rule)?.unwrap_or(defaults.rule),
+        charset: parse("charset", charset)?.unwrap_or(defaults.charset),
+        dither: parse("dither", dither)?.unwrap_or(defaults.dither),
+        color: parse("color", color)?.unwrap_or(defaults.color),
+        invert,
+        ..defaults
+    };
+
+    Ok(crate::render(&img, &opts))
+}
+
+fn parse<T: FromStr>(key: &str, value: Option<&str>) -> PyResult<Option<T>>
+where
+    T::Err: Display,
+{
+    value
+        .map(|value| {
+            T::from_str(value).map_err(|e| value_error(format!("invalid `{}`: {}", key, e)))
+        })
+        .transpose()
+}
+
+fn value_error(e: impl Display) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+#[pymodule]
+fn ascii_image(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(render, m)?)
+}
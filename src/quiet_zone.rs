@@ -0,0 +1,45 @@
+use image::{imageops, DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+/// The margins (left, top, right, bottom) in pixels of a quiet zone of
+/// `cells` blank cells around an image of `size`, the right and bottom ones
+/// also filling the partial cells at the edges.
+pub(crate) fn margins(
+    (width, height): (u32, u32),
+    (cell_width, cell_height): (u32, u32),
+    cells: u32,
+) -> (u32, u32, u32, u32) {
+    let (x, y) = (cells * cell_width, cells * cell_height);
+    let rest = |len: u32, cell: u32| (cell - len % cell) % cell;
+
+    (
+        x,
+        y,
+        x + rest(width, cell_width),
+        y + rest(height, cell_height),
+    )
+}
+
+/// Surrounds `img` with white by the margins (left, top, right, bottom).
+pub(crate) fn pad(
+    img: &DynamicImage,
+    (left, top, right, bottom): (u32, u32, u32, u32),
+) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let mut padded =
+        RgbaImage::from_pixel(left + width + right, top + height + bottom, Rgba([255; 4]));
+    imageops::replace(&mut padded, &img.to_rgba8(), left, top);
+
+    DynamicImage::ImageRgba8(padded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_the_partial_cells() {
+        assert_eq!(margins((8, 8), (2, 4), 2), (4, 8, 4, 8));
+        assert_eq!(margins((7, 5), (2, 4), 1), (2, 4, 3, 7));
+        assert_eq!(margins((7, 5), (2, 4), 0), (0, 0, 1, 3));
+    }
+}
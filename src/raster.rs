@@ -0,0 +1,91 @@
+use crate::blocks;
+use crate::braille;
+use crate::cell::Cell;
+use font8x8::{UnicodeFonts, BASIC_FONTS, BLOCK_FONTS, BOX_FONTS, GREEK_FONTS, LATIN_FONTS};
+use image::{Rgb, RgbImage};
+
+/// Layout and styling of the PNG output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PngOptions {
+    /// Size (width, height) of every character cell, in pixels.
+    pub cell_size: (u32, u32),
+    /// Color of the characters that aren't colored by the color mode.
+    pub fg: Rgb<u8>,
+    /// Color behind the characters that have no background of their own.
+    pub bg: Rgb<u8>,
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        PngOptions {
+            cell_size: (8, 16),
+            fg: Rgb([0, 0, 0]),
+            bg: Rgb([255, 255, 255]),
+        }
+    }
+}
+
+/// The 8x8 bitmap of `ch` in the embedded font, one byte per row with the
+/// leftmost pixel at bit 0.
+fn glyph(ch: char) -> Option<[u8; 8]> {
+    BASIC_FONTS
+        .get(ch)
+        .or_else(|| BLOCK_FONTS.get(ch))
+        .or_else(|| BOX_FONTS.get(ch))
+        .or_else(|| LATIN_FONTS.get(ch))
+        .or_else(|| GREEK_FONTS.get(ch))
+}
+
+/// Whether the pixel at (`x`, `y`) of a `w`x`h` cell showing `ch` is part of
+/// the character.
+fn covers(ch: char, x: u32, y: u32, (w, h): (u32, u32)) -> bool {
+    // braille and sextants are drawn from their geometry, they aren't part of
+    // the embedded font
+    if let Some(bits) = braille::dots(ch) {
+        let (dw, dh) = (w as f32 / 2.0, h as f32 / 4.0);
+        let radius = dw.min(dh) * 0.4;
+        let (col, row) = ((x as f32 / dw) as u32, (y as f32 / dh) as u32);
+        let bit = braille::DOTS.iter().position(|&dot| dot == (col, row));
+
+        return bit.is_some_and(|bit| {
+            let cx = (col as f32 + 0.5) * dw;
+            let cy = (row as f32 + 0.5) * dh;
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+
+            bits & (1 << bit) != 0 && dx * dx + dy * dy <= radius * radius
+        });
+    }
+    if let Some(mask) = blocks::sextant_mask(ch) {
+        let sixth = (y * 3 / h) * 2 + x * 2 / w;
+        return mask & (1 << sixth) != 0;
+    }
+
+    glyph(ch).is_some_and(|rows| rows[(y * 8 / h) as usize] & (1 << (x * 8 / w)) != 0)
+}
+
+/// Draws the cells onto an image, one `cell_size` block per cell.
+pub(crate) fn write(cells: &[Vec<Cell>], opts: &PngOptions) -> RgbImage {
+    let (cw, ch) = opts.cell_size;
+    let cols = cells.iter().map(Vec::len).max().unwrap_or(0) as u32;
+    let mut out = RgbImage::from_pixel(cols * cw, cells.len() as u32 * ch, opts.bg);
+
+    for (y, row) in cells.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            let fg = cell.fg.unwrap_or(opts.fg);
+            let bg = cell.bg.unwrap_or(opts.bg);
+
+            for dy in 0..ch {
+                for dx in 0..cw {
+                    let color = if covers(cell.ch, dx, dy, (cw, ch)) {
+                        fg
+                    } else {
+                        bg
+                    };
+                    out.put_pixel(x as u32 * cw + dx, y as u32 * ch + dy, color);
+                }
+            }
+        }
+    }
+
+    out
+}
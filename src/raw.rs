@@ -0,0 +1,167 @@
+use image::{DynamicImage, GrayImage, RgbImage, RgbaImage};
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The layout of the pixels of a raw buffer, 8 bits per channel, the rows
+/// one after the other from the top, with no padding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PixelLayout {
+    Rgb8,
+    Rgba8,
+    Gray8,
+}
+
+impl PixelLayout {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelLayout::Rgb8 => 3,
+            PixelLayout::Rgba8 => 4,
+            PixelLayout::Gray8 => 1,
+        }
+    }
+}
+
+/// The shape of a raw buffer of already decoded pixels, `WxH:layout`, e.g.
+/// `1920x1080:rgba8`, for framebuffers piped in without being encoded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RawImage {
+    pub width: u32,
+    pub height: u32,
+    pub layout: PixelLayout,
+}
+
+#[derive(Error, Debug)]
+pub enum RawError {
+    #[error("expected {expected} bytes of raw pixels, got {got}")]
+    WrongLength { expected: usize, got: usize },
+}
+
+impl RawImage {
+    /// The image of the pixels in `bytes`, which must hold exactly
+    /// `width * height` of them.
+    pub fn decode(&self, bytes: Vec<u8>) -> Result<DynamicImage, RawError> {
+        let expected = self.width as usize * self.height as usize * self.layout.bytes_per_pixel();
+        if bytes.len() != expected {
+            return Err(RawError::WrongLength {
+                expected,
+                got: bytes.len(),
+            });
+        }
+
+        let (w, h) = (self.width, self.height);
+        // the length is checked, the buffers always fit
+        Ok(match self.layout {
+            PixelLayout::Rgb8 => DynamicImage::ImageRgb8(RgbImage::from_raw(w, h, bytes).unwrap()),
+            PixelLayout::Rgba8 => {
+                DynamicImage::ImageRgba8(RgbaImage::from_raw(w, h, bytes).unwrap())
+            }
+            PixelLayout::Gray8 => {
+                DynamicImage::ImageLuma8(GrayImage::from_raw(w, h, bytes).unwrap())
+            }
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RawImageParseError {
+    #[error("couldn't parse an int in the raw image size")]
+    ParseIntError(#[from] ParseIntError),
+    #[error("unknown raw image format `{0}`, expected WxH:rgb8, WxH:rgba8 or WxH:gray8")]
+    UnknownFormat(String),
+    #[error("unknown pixel layout `{0}`, expected rgb8, rgba8 or gray8")]
+    UnknownLayout(String),
+    #[error("the raw image must be at least 1x1")]
+    Empty,
+}
+
+impl fmt::Display for PixelLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PixelLayout::Rgb8 => write!(f, "rgb8"),
+            PixelLayout::Rgba8 => write!(f, "rgba8"),
+            PixelLayout::Gray8 => write!(f, "gray8"),
+        }
+    }
+}
+
+impl FromStr for PixelLayout {
+    type Err = RawImageParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rgb8" => Ok(PixelLayout::Rgb8),
+            "rgba8" => Ok(PixelLayout::Rgba8),
+            "gray8" => Ok(PixelLayout::Gray8),
+            _ => Err(RawImageParseError::UnknownLayout(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for RawImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}:{}", self.width, self.height, self.layout)
+    }
+}
+
+impl FromStr for RawImage {
+    type Err = RawImageParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = || RawImageParseError::UnknownFormat(s.into());
+        let (size, layout) = s.split_once(':').ok_or_else(unknown)?;
+        let (w, h) = size.split_once('x').ok_or_else(unknown)?;
+
+        let raw = RawImage {
+            width: u32::from_str(w)?,
+            height: u32::from_str(h)?,
+            layout: layout.parse()?,
+        };
+        if raw.width == 0 || raw.height == 0 {
+            return Err(RawImageParseError::Empty);
+        }
+
+        Ok(raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+
+    #[test]
+    fn parses_the_size_and_the_layout() {
+        let raw: RawImage = "640x480:rgba8".parse().unwrap();
+        assert_eq!(
+            raw,
+            RawImage {
+                width: 640,
+                height: 480,
+                layout: PixelLayout::Rgba8,
+            }
+        );
+        assert_eq!(raw.to_string(), "640x480:rgba8");
+
+        assert!("640x480".parse::<RawImage>().is_err());
+        assert!("640x480:bgr8".parse::<RawImage>().is_err());
+        assert!("0x480:gray8".parse::<RawImage>().is_err());
+    }
+
+    #[test]
+    fn decodes_buffers_of_the_right_length() {
+        let raw: RawImage = "2x1:rgb8".parse().unwrap();
+        let img = raw.decode(vec![255, 0, 0, 0, 0, 255]).unwrap();
+        assert_eq!(img.dimensions(), (2, 1));
+        assert_eq!(img.to_rgb8().get_pixel(1, 0).0, [0, 0, 255]);
+
+        assert!(matches!(
+            raw.decode(vec![0; 5]),
+            Err(RawError::WrongLength {
+                expected: 6,
+                got: 5
+            })
+        ));
+    }
+}
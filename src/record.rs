@@ -0,0 +1,149 @@
+use crate::animation::Frame;
+use crate::format::OutputFormat;
+use crate::{json, render, RenderOptions};
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Records the frames of an animation, rendered as text, in the recording
+/// format of the options: an asciicast (asciinema v2) file, or an ANSI dump
+/// drawing every frame over the previous one, to be replayed with `cat`.
+pub struct Recorder {
+    opts: RenderOptions,
+    asciicast: bool,
+    /// The events of the asciicast, or the frames of the dump.
+    out: String,
+    elapsed: Duration,
+    /// The size in cells of the largest frame.
+    columns: usize,
+    rows: usize,
+}
+
+impl Recorder {
+    /// A recorder in the format of `opts`, asciicast unless it is
+    /// [`OutputFormat::AnsiDump`].
+    pub fn new(opts: &RenderOptions) -> Self {
+        Recorder {
+            opts: RenderOptions {
+                format: OutputFormat::Text,
+                ..opts.clone()
+            },
+            asciicast: opts.format != OutputFormat::AnsiDump,
+            out: String::new(),
+            elapsed: Duration::ZERO,
+            columns: 0,
+            rows: 0,
+        }
+    }
+
+    /// Renders `frame` and records it, for as long as its delay.
+    pub fn push(&mut self, frame: &Frame) {
+        let text = render(&frame.image, &self.opts);
+        self.push_text(&text, frame.delay);
+    }
+
+    /// Records the rendered `text` of a frame, shown for `delay`.
+    pub(crate) fn push_text(&mut self, text: &str, delay: Duration) {
+        let rows = text.lines().count();
+        self.columns = self
+            .columns
+            .max(text.lines().map(visible_width).max().unwrap_or(0));
+
+        if self.asciicast {
+            // a terminal moves back to the start of the line on `\r` only
+            let screen = format!("\x1b[H\x1b[2J{}", text.replace('\n', "\r\n"));
+            self.push_event(&screen);
+        } else {
+            // the delay in an application program command, which terminals
+            // ignore, then the frame over the previous one
+            write!(self.out, "\x1b_delay={}\x1b\\", delay.as_millis()).unwrap();
+            if self.rows > 0 {
+                write!(self.out, "\x1b[{}A", self.rows).unwrap();
+            }
+            self.out.push_str(text);
+        }
+
+        self.rows = match self.asciicast {
+            true => self.rows.max(rows),
+            false => rows,
+        };
+        self.elapsed += delay;
+    }
+
+    /// The output event of an asciicast writing `data` at the current time.
+    fn push_event(&mut self, data: &str) {
+        write!(self.out, "[{:.6}, \"o\", ", self.elapsed.as_secs_f64()).unwrap();
+        json::push_string(&mut self.out, data);
+        self.out.push_str("]\n");
+    }
+
+    /// The recording; an asciicast ends with an empty event for the last
+    /// frame to last its delay.
+    pub fn finish(mut self) -> String {
+        if !self.asciicast {
+            return self.out;
+        }
+
+        self.push_event("");
+        format!(
+            "{{\"version\": 2, \"width\": {}, \"height\": {}}}\n{}",
+            self.columns, self.rows, self.out
+        )
+    }
+}
+
+/// How many cells `line` takes, leaving out its SGR escapes.
+fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\x1b' => {
+                chars.by_ref().find(|&c| c == 'm');
+            }
+            _ => width += 1,
+        }
+    }
+
+    width
+}
+
+/// Renders and records every frame, see [`Recorder`].
+pub fn record(frames: &[Frame], opts: &RenderOptions) -> String {
+    let mut recorder = Recorder::new(opts);
+    frames.iter().for_each(|frame| recorder.push(frame));
+    recorder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorder(format: OutputFormat) -> Recorder {
+        let mut recorder = Recorder::new(&RenderOptions {
+            format,
+            ..RenderOptions::default()
+        });
+        recorder.push_text("⣿⣿\n⣿\n", Duration::from_millis(100));
+        recorder.push_text("\x1b[31m⣿\x1b[0m\n", Duration::from_millis(50));
+        recorder
+    }
+
+    #[test]
+    fn records_asciicasts() {
+        assert_eq!(
+            recorder(OutputFormat::Asciicast).finish(),
+            "{\"version\": 2, \"width\": 2, \"height\": 2}\n\
+             [0.000000, \"o\", \"\\u001b[H\\u001b[2J⣿⣿\\u000d\\u000a⣿\\u000d\\u000a\"]\n\
+             [0.100000, \"o\", \"\\u001b[H\\u001b[2J\\u001b[31m⣿\\u001b[0m\\u000d\\u000a\"]\n\
+             [0.150000, \"o\", \"\"]\n"
+        );
+    }
+
+    #[test]
+    fn dumps_frames_over_each_other() {
+        assert_eq!(
+            recorder(OutputFormat::AnsiDump).finish(),
+            "\x1b_delay=100\x1b\\⣿⣿\n⣿\n\x1b_delay=50\x1b\\\x1b[2A\x1b[31m⣿\x1b[0m\n"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::bitmap::Bitmap;
+use crate::{Rule, RuleCtx, RuleExpr, RuleExprParseError};
+use image::DynamicImage;
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A rectangle of the image turned on by a rule of its own, `X,Y,WxH:RULE`,
+/// e.g. `0,0,100x100:Sobel(60)`; it is in pixels of the input, once rotated,
+/// flipped and cropped, and follows the image as it is resized.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub rule: RuleExpr,
+}
+
+#[derive(Error, Debug)]
+pub enum RegionParseError {
+    #[error("couldn't parse an int in the region")]
+    ParseIntError(#[from] ParseIntError),
+    #[error("invalid region `{0}`, expected X,Y,WxH:RULE")]
+    InvalidRegion(String),
+    #[error("invalid rule of the region: {0}")]
+    Rule(#[from] RuleExprParseError),
+}
+
+impl FromStr for Region {
+    type Err = RegionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || RegionParseError::InvalidRegion(s.into());
+        let (rect, rule) = s.split_once(':').ok_or_else(invalid)?;
+        let mut parts = rect.splitn(3, ',');
+        let (x, y, size) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(x), Some(y), Some(size)) => (x, y, size),
+            _ => return Err(invalid()),
+        };
+        let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+
+        Ok(Region {
+            x: u32::from_str(x.trim())?,
+            y: u32::from_str(y.trim())?,
+            width: u32::from_str(width.trim())?,
+            height: u32::from_str(height.trim())?,
+            rule: rule.parse()?,
+        })
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{}x{}:{}",
+            self.x, self.y, self.width, self.height, self.rule
+        )
+    }
+}
+
+impl Region {
+    /// The pixels `(x, y, width, height)` the region covers in an image of
+    /// `size`, resized from an image of `source` size.
+    fn scaled(&self, source: (u32, u32), size: (u32, u32)) -> (u32, u32, u32, u32) {
+        let scale = |v: u32, from: u32, to: u32, round_up: bool| {
+            let v = v as u64 * to as u64;
+            let from = from.max(1) as u64;
+            let v = match round_up {
+                true => v.div_ceil(from),
+                false => v / from,
+            };
+            v.min(to as u64) as u32
+        };
+
+        let (x, y) = (
+            scale(self.x, source.0, size.0, false),
+            scale(self.y, source.1, size.1, false),
+        );
+        let (right, bottom) = (
+            scale(self.x.saturating_add(self.width), source.0, size.0, true),
+            scale(self.y.saturating_add(self.height), source.1, size.1, true),
+        );
+
+        (x, y, right.saturating_sub(x), bottom.saturating_sub(y))
+    }
+}
+
+/// The pixels of `img` on by `rule`, and in every region by the rule of the
+/// region instead, the last one listed winning where they overlap. `img` is
+/// an image of `source` size resized to `size`, then moved by `offset`.
+pub(crate) fn evaluate_regions(
+    img: &DynamicImage,
+    rule: &RuleExpr,
+    regions: &[Region],
+    source: (u32, u32),
+    size: (u32, u32),
+    offset: (u32, u32),
+) -> Bitmap {
+    let ctx = RuleCtx::new(img);
+    let mut bitmap = rule.evaluate(&ctx);
+
+    for region in regions {
+        let (x, y, width, height) = region.scaled(source, size);
+        if width == 0 || height == 0 {
+            continue;
+        }
+
+        // the rule sees the whole image, for its neighborhoods and
+        // thresholds
+        let region_bitmap = region.rule.evaluate(&ctx);
+        for y in offset.1 + y..offset.1 + y + height {
+            for x in offset.0 + x..offset.0 + x + width {
+                bitmap.set(x, y, region_bitmap.get(x, y));
+            }
+        }
+    }
+
+    bitmap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GrayImage;
+
+    #[test]
+    fn parses_regions() {
+        let region: Region = "0,10,100x50:Luma(50)".parse().unwrap();
+        assert_eq!(
+            region,
+            Region {
+                x: 0,
+                y: 10,
+                width: 100,
+                height: 50,
+                rule: "Luma(50)".parse().unwrap(),
+            }
+        );
+        assert_eq!(region.to_string(), "0,10,100x50:Luma(50)");
+
+        assert!("0,10:Luma(50)".parse::<Region>().is_err());
+        assert!("0,10,100x50".parse::<Region>().is_err());
+        assert!("0,10,100x50:Nope".parse::<Region>().is_err());
+    }
+
+    #[test]
+    fn scales_with_the_image() {
+        let region: Region = "10,10,15x20:Luma(50)".parse().unwrap();
+        assert_eq!(region.scaled((100, 100), (100, 100)), (10, 10, 15, 20));
+        // the edges are rounded outwards
+        assert_eq!(region.scaled((100, 100), (10, 10)), (1, 1, 2, 2));
+        assert_eq!(region.scaled((100, 100), (20, 20)), (2, 2, 3, 4));
+    }
+
+    #[test]
+    fn overrides_the_rule_in_the_regions() {
+        let img = DynamicImage::ImageLuma8(GrayImage::from_pixel(4, 2, image::Luma([100])));
+        let rule: RuleExpr = "Luma(200)".parse().unwrap();
+        let regions = ["2,0,2x2:Luma(50)".parse().unwrap()];
+
+        // the image resized to half its height, then moved down a row
+        let bitmap = evaluate_regions(&img, &rule, &regions, (4, 2), (4, 1), (0, 1));
+        let on: Vec<bool> = (0..2)
+            .flat_map(|y| (0..4).map(move |x| (x, y)))
+            .map(|(x, y)| bitmap.get(x, y))
+            .collect();
+        assert_eq!(on, [false, false, false, false, false, false, true, true]);
+    }
+}
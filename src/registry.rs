@@ -0,0 +1,221 @@
+use crate::rule::{AdaptiveKernel, OnOffRule, Rule};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Builds a rule from its arguments, in the order of its parameters, or
+/// explains what is wrong with them.
+pub type RuleConstructor = fn(&[i32]) -> Result<Arc<dyn Rule>, String>;
+
+/// A rule known to the parser.
+#[derive(Copy, Clone)]
+pub(crate) struct RuleEntry {
+    /// Names of the parameters, for the arguments given by name.
+    pub params: &'static [&'static str],
+    pub constructor: RuleConstructor,
+    /// What the rule turns on, with an example, for `--help`; empty for the
+    /// rules registered with [`register_rule`].
+    pub about: &'static str,
+}
+
+const THRESHOLD: &[&str] = &["threshold"];
+
+fn registry() -> &'static RwLock<HashMap<String, RuleEntry>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, RuleEntry>>> = OnceLock::new();
+
+    REGISTRY.get_or_init(|| {
+        let builtins: [(&str, &'static [&'static str], RuleConstructor, &str); 10] = [
+            (
+                "Threshold",
+                THRESHOLD,
+                |args| {
+                    let [t] = unsigned(args)?;
+                    Ok(Arc::new(OnOffRule::PxThreshold(t)))
+                },
+                "On when the sum of the red, green and blue of the pixel is at least \
+                 `threshold`, in 0..=765, e.g. `Threshold(384)`.",
+            ),
+            (
+                "InvertedThreshold",
+                THRESHOLD,
+                |args| {
+                    let [t] = unsigned(args)?;
+                    Ok(Arc::new(OnOffRule::InvertedPxThreshold(t)))
+                },
+                "On when the sum of the red, green and blue of the pixel is at most \
+                 `threshold`, e.g. `InvertedThreshold(200)`.",
+            ),
+            (
+                "Border",
+                &["threshold", "distance"],
+                |args| {
+                    let [t, distance] = unsigned(args)?;
+                    Ok(Arc::new(OnOffRule::Border(t, distance)))
+                },
+                "On when a channel differs by more than `threshold` from a pixel up to \
+                 `distance` pixels away, tracing outlines, e.g. `Border(40, 1)`.",
+            ),
+            (
+                "Luma",
+                THRESHOLD,
+                |args| {
+                    let [t] = unsigned(args)?;
+                    Ok(Arc::new(OnOffRule::LumaThreshold(t)))
+                },
+                "On when the luma of the pixel is at least `threshold`, in 0..=255, \
+                 e.g. `Luma(128)`.",
+            ),
+            (
+                "Otsu",
+                &[],
+                |_| Ok(Arc::new(OnOffRule::Otsu)),
+                "A luma threshold picked for the image with Otsu's method, for photos, \
+                 e.g. `Otsu`.",
+            ),
+            (
+                "Adaptive",
+                &["window", "c"],
+                |args| adaptive(args, AdaptiveKernel::Mean),
+                "On when the luma is above the average of the `window`x`window` pixels \
+                 around, minus `c`, for uneven lighting, e.g. `Adaptive(15, 5)`.",
+            ),
+            (
+                "AdaptiveGaussian",
+                &["window", "c"],
+                |args| adaptive(args, AdaptiveKernel::Gaussian),
+                "Adaptive, with the pixels around weighted by their distance, \
+                 e.g. `AdaptiveGaussian(15, 5)`.",
+            ),
+            (
+                "Sobel",
+                THRESHOLD,
+                |args| {
+                    let [t] = unsigned(args)?;
+                    Ok(Arc::new(OnOffRule::Sobel(t)))
+                },
+                "On when the gradient of the luma is at least `threshold`, for edges, \
+                 e.g. `Sobel(100)`.",
+            ),
+            (
+                "Canny",
+                &["low", "high"],
+                |args| {
+                    let [low, high] = unsigned(args)?;
+                    Ok(Arc::new(OnOffRule::Canny(low, high)))
+                },
+                "On for the edges found by the Canny detector between the `low` and \
+                 `high` thresholds, e.g. `Canny(50, 150)`.",
+            ),
+            (
+                "Bands",
+                &["n"],
+                |args| match *args {
+                    [n] if (2..=16).contains(&n) => Ok(Arc::new(OnOffRule::Bands(n as u8))),
+                    _ => Err("expected from 2 to 16 bands".into()),
+                },
+                "The luma split into `n` even bands, from 2 to 16, on above the lowest \
+                 one, for posters, e.g. `Bands(4)`.",
+            ),
+        ];
+
+        RwLock::new(
+            builtins
+                .iter()
+                .map(|&(name, params, constructor, about)| {
+                    (
+                        name.to_string(),
+                        RuleEntry {
+                            params,
+                            constructor,
+                            about,
+                        },
+                    )
+                })
+                .collect(),
+        )
+    })
+}
+
+/// Makes `name(params...)` available in the rule syntax, replacing any rule
+/// of the same name; the constructor is only called with as many arguments
+/// as there are parameters.
+pub fn register_rule(name: &str, params: &'static [&'static str], constructor: RuleConstructor) {
+    registry().write().unwrap().insert(
+        name.to_string(),
+        RuleEntry {
+            params,
+            constructor,
+            about: "",
+        },
+    );
+}
+
+pub(crate) fn lookup(name: &str) -> Option<RuleEntry> {
+    registry().read().unwrap().get(name).copied()
+}
+
+/// The rules of the syntax, one by name with its parameters and what it
+/// does, then the operators combining them, for `--help`.
+pub fn rule_help() -> String {
+    let registry = registry().read().unwrap();
+    let mut names: Vec<&String> = registry.keys().collect();
+    names.sort();
+
+    let mut help = String::from("RULES:\n");
+    for name in names {
+        let entry = &registry[name];
+        let signature = match entry.params {
+            [] => name.clone(),
+            params => format!("{}({})", name, params.join(", ")),
+        };
+        help += &format!("    {}\n", signature);
+        if !entry.about.is_empty() {
+            help += &format!("            {}\n", entry.about);
+        }
+    }
+    help += "\nRules are combined with `!` (not), `&` (and) and `|` (or), and the \
+             arguments given by name, e.g. `Otsu & !Border(distance = 2, threshold = 30)`.\n";
+
+    help
+}
+
+/// The `N` arguments, none negative.
+fn unsigned<const N: usize>(args: &[i32]) -> Result<[i32; N], String> {
+    let args: [i32; N] = args
+        .try_into()
+        .map_err(|_| format!("expected {} arguments", N))?;
+    if args.iter().any(|&arg| arg < 0) {
+        return Err("expected arguments of at least 0".into());
+    }
+
+    Ok(args)
+}
+
+fn adaptive(args: &[i32], kernel: AdaptiveKernel) -> Result<Arc<dyn Rule>, String> {
+    match *args {
+        [window, c] if window > 0 => Ok(Arc::new(OnOffRule::Adaptive {
+            window: window as u32,
+            c,
+            kernel,
+        })),
+        [_, _] => Err("expected a positive window".into()),
+        _ => Err("expected 2 arguments".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn help_lists_the_registered_rules() {
+        register_rule("Everything", &[], |_| {
+            Ok(Arc::new(OnOffRule::LumaThreshold(0)))
+        });
+
+        let help = rule_help();
+        assert!(help.contains("    Border(threshold, distance)\n"));
+        assert!(help.contains("    Otsu\n"));
+        assert!(help.contains("    Everything\n"));
+    }
+}
@@ -0,0 +1,420 @@
+use crate::bitmap::Bitmap;
+use crate::canny::canny;
+use crate::levels::Levels;
+use crate::threshold::threshold_luma;
+use image::{DynamicImage, GenericImageView, GrayImage, Pixel, RgbaImage};
+use itertools::Itertools;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+/// What rules get to look at when deciding whether a pixel is on.
+///
+/// The pixels are converted once, on first use, into buffers shared by all
+/// the rules evaluated against the context, instead of on every read.
+pub struct RuleCtx<'a> {
+    pub img: &'a DynamicImage,
+    luma: OnceLock<GrayImage>,
+    rgba: OnceLock<RgbaImage>,
+}
+
+impl<'a> RuleCtx<'a> {
+    pub fn new(img: &'a DynamicImage) -> Self {
+        RuleCtx {
+            img,
+            luma: OnceLock::new(),
+            rgba: OnceLock::new(),
+        }
+    }
+
+    /// The luma of every pixel.
+    pub fn luma(&self) -> &GrayImage {
+        self.luma.get_or_init(|| self.img.to_luma8())
+    }
+
+    /// The colors of every pixel.
+    pub fn rgba(&self) -> &RgbaImage {
+        self.rgba.get_or_init(|| self.img.to_rgba8())
+    }
+}
+
+/// Decides which pixels of an image are on; new rules are made available to
+/// the rule syntax with [`register_rule`](crate::register_rule).
+///
+/// `Display` must format the rule the way it is parsed.
+pub trait Rule: fmt::Display + fmt::Debug + Send + Sync {
+    /// Whether the pixel at (`x`, `y`) is on.
+    fn is_on(&self, ctx: &RuleCtx, x: u32, y: u32) -> bool;
+
+    /// The on/off state of every pixel; rules that depend on the whole image
+    /// should compute it once here.
+    fn evaluate(&self, ctx: &RuleCtx) -> Bitmap {
+        evaluate_pixels(self, ctx)
+    }
+
+    /// The rule that can be evaluated pixel by pixel, for rules that depend
+    /// on the whole image.
+    fn resolve(&self, _img: &DynamicImage) -> Option<Arc<dyn Rule>> {
+        None
+    }
+
+    /// The rule with its threshold moved by `delta`, if it has one.
+    fn adjusted(&self, _delta: i32) -> Option<Arc<dyn Rule>> {
+        None
+    }
+
+    /// The rule looking `delta` further around every pixel, if it looks
+    /// around.
+    fn adjusted_reach(&self, _delta: i32) -> Option<Arc<dyn Rule>> {
+        None
+    }
+
+    /// How many pixels away from a pixel the rule looks to decide on it,
+    /// `None` for rules that depend on the whole image.
+    fn reach(&self) -> Option<u32> {
+        None
+    }
+
+    /// The band of every pixel, for the rules splitting the image into
+    /// more than on and off; the pixels of the lowest band are the ones
+    /// off.
+    fn levels(&self, _ctx: &RuleCtx) -> Option<Levels> {
+        None
+    }
+}
+
+/// Evaluates `rule` pixel by pixel, the rows in parallel.
+fn evaluate_pixels<R: Rule + ?Sized>(rule: &R, ctx: &RuleCtx) -> Bitmap {
+    let (width, height) = ctx.img.dimensions();
+
+    Bitmap::from_fn(width, height, |x, y| rule.is_on(ctx, x, y))
+}
+
+/// The built-in rules.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OnOffRule {
+    PxThreshold(i32),
+    InvertedPxThreshold(i32),
+    Border(i32, i32),
+    /// On when the Rec. 709 luma of the pixel (ignoring alpha) is at least
+    /// the given threshold, in `0..=255`.
+    LumaThreshold(i32),
+    /// A luma threshold picked automatically with Otsu's method; this is
+    /// the recommended rule for photos.
+    Otsu,
+    /// On when the luma of the pixel is above the (mean or gaussian
+    /// weighted) average luma of the `window`x`window` neighborhood around
+    /// it, minus `c`.
+    Adaptive {
+        window: u32,
+        c: i32,
+        kernel: AdaptiveKernel,
+    },
+    /// On when the Sobel gradient magnitude of the luma at the pixel is at
+    /// least the given threshold.
+    Sobel(i32),
+    /// On for the edges found by the Canny edge detector, with the given
+    /// low and high hysteresis thresholds.
+    Canny(i32, i32),
+    /// The luma split into the given number of even bands, on above the
+    /// lowest one, for posters.
+    Bands(u8),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AdaptiveKernel {
+    Mean,
+    Gaussian,
+}
+
+impl OnOffRule {
+    /// Resolves the rules that depend on the whole image (like `Otsu`)
+    /// into rules that can be evaluated pixel by pixel.
+    pub fn resolve(&self, img: &DynamicImage) -> OnOffRule {
+        match self {
+            OnOffRule::Otsu => OnOffRule::LumaThreshold(otsu_threshold(img) as i32),
+            rule => *rule,
+        }
+    }
+
+    /// The rule with its threshold moved by `delta`; for `Adaptive` it's the
+    /// offset `c` that moves, for `Canny` both thresholds.
+    pub fn adjusted(&self, delta: i32) -> OnOffRule {
+        match *self {
+            OnOffRule::PxThreshold(t) => OnOffRule::PxThreshold((t + delta).clamp(0, 765)),
+            OnOffRule::InvertedPxThreshold(t) => {
+                OnOffRule::InvertedPxThreshold((t + delta).clamp(0, 765))
+            }
+            OnOffRule::Border(t, distance) => OnOffRule::Border((t + delta).max(0), distance),
+            OnOffRule::LumaThreshold(t) => OnOffRule::LumaThreshold((t + delta).clamp(0, 255)),
+            OnOffRule::Otsu => OnOffRule::Otsu,
+            OnOffRule::Adaptive { window, c, kernel } => OnOffRule::Adaptive {
+                window,
+                c: c + delta,
+                kernel,
+            },
+            OnOffRule::Sobel(t) => OnOffRule::Sobel((t + delta).max(0)),
+            OnOffRule::Canny(low, high) => {
+                OnOffRule::Canny((low + delta).max(0), (high + delta).max(0))
+            }
+            OnOffRule::Bands(n) => OnOffRule::Bands(n),
+        }
+    }
+
+    /// The rule looking `delta` further around every pixel: the distance of
+    /// `Border`, or the window of `Adaptive` (in steps of two, to keep it
+    /// odd); other rules are left as they are.
+    pub fn adjusted_reach(&self, delta: i32) -> OnOffRule {
+        match *self {
+            OnOffRule::Border(t, distance) => OnOffRule::Border(t, (distance + delta).max(1)),
+            OnOffRule::Adaptive { window, c, kernel } => OnOffRule::Adaptive {
+                window: (window as i32 + 2 * delta).max(3) as u32,
+                c,
+                kernel,
+            },
+            rule => rule,
+        }
+    }
+}
+
+impl Rule for OnOffRule {
+    fn evaluate(&self, ctx: &RuleCtx) -> Bitmap {
+        match self {
+            OnOffRule::LumaThreshold(threshold) => threshold_luma(ctx.luma(), *threshold),
+            OnOffRule::Otsu => threshold_luma(ctx.luma(), otsu_luma(ctx.luma()) as i32),
+            OnOffRule::Canny(low, high) => canny(ctx.luma(), *low as f32, *high as f32),
+            rule => evaluate_pixels(rule, ctx),
+        }
+    }
+
+    /// Rules that depend on the whole image are recomputed on every call,
+    /// prefer [`Rule::evaluate`] to evaluate an entire image.
+    fn is_on(&self, ctx: &RuleCtx, x: u32, y: u32) -> bool {
+        if !ctx.img.in_bounds(x, y) {
+            return false;
+        }
+        match self {
+            OnOffRule::PxThreshold(threshold) => {
+                *threshold
+                    <= ctx
+                        .rgba()
+                        .get_pixel(x, y)
+                        .to_rgb()
+                        .0
+                        .iter()
+                        .map(|&v| v as i32)
+                        .sum::<i32>()
+            }
+            OnOffRule::InvertedPxThreshold(threshold) => {
+                *threshold
+                    >= ctx
+                        .rgba()
+                        .get_pixel(x, y)
+                        .to_rgb()
+                        .0
+                        .iter()
+                        .map(|&v| v as i32)
+                        .sum::<i32>()
+            }
+            OnOffRule::Border(threshold, distance) => {
+                let img = ctx.rgba();
+                let px = img.get_pixel(x, y);
+
+                [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                    .iter()
+                    .cartesian_product(1..=*distance)
+                    .map(|(&(dx, dy), d)| (dx * d, dy * d))
+                    .any(|(dx, dy)| {
+                        let nx = u32::try_from(x as i32 + dx).unwrap_or(0);
+                        let ny = u32::try_from(y as i32 + dy).unwrap_or(0);
+                        if !img.in_bounds(nx, ny) {
+                            return false;
+                        }
+
+                        let df = img
+                            .get_pixel(nx, ny)
+                            .0
+                            .iter()
+                            .zip(px.0.iter())
+                            .map(|(&a, &b)| a.abs_diff(b) as i32)
+                            .max()
+                            .unwrap_or(0);
+
+                        df >= *threshold
+                    })
+            }
+            OnOffRule::LumaThreshold(threshold) => {
+                *threshold <= ctx.luma().get_pixel(x, y)[0] as i32
+            }
+            OnOffRule::Otsu => OnOffRule::resolve(self, ctx.img).is_on(ctx, x, y),
+            OnOffRule::Adaptive { window, c, kernel } => {
+                let img = ctx.luma();
+                let radius = (*window / 2) as i32;
+                // same default as OpenCV's getGaussianKernel
+                let sigma = 0.3 * ((*window as f32 - 1.0) * 0.5 - 1.0) + 0.8;
+
+                let mut sum = 0.0;
+                let mut weights = 0.0;
+                for dy in -radius..=radius {
+                    for dx in -radius..=radius {
+                        let nx = x as i32 + dx;
+                        let ny = y as i32 + dy;
+                        if nx < 0 || ny < 0 || !img.in_bounds(nx as u32, ny as u32) {
+                            continue;
+                        }
+
+                        let weight = match kernel {
+                            AdaptiveKernel::Mean => 1.0,
+                            AdaptiveKernel::Gaussian => {
+                                (-((dx * dx + dy * dy) as f32) / (2.0 * sigma * sigma)).exp()
+                            }
+                        };
+                        sum += weight * img.get_pixel(nx as u32, ny as u32)[0] as f32;
+                        weights += weight;
+                    }
+                }
+
+                let luma = img.get_pixel(x, y)[0] as f32;
+                luma > sum / weights - *c as f32
+            }
+            OnOffRule::Sobel(threshold) => {
+                let (gx, gy) = sobel(ctx.luma(), x, y);
+                let magnitude = ((gx * gx + gy * gy) as f32).sqrt();
+
+                magnitude >= *threshold as f32
+            }
+            OnOffRule::Canny(..) => self.evaluate(ctx).get(x, y),
+            OnOffRule::Bands(n) => band(ctx.luma().get_pixel(x, y)[0], *n) > 0,
+        }
+    }
+
+    fn resolve(&self, img: &DynamicImage) -> Option<Arc<dyn Rule>> {
+        Some(Arc::new(OnOffRule::resolve(self, img)))
+    }
+
+    fn adjusted(&self, delta: i32) -> Option<Arc<dyn Rule>> {
+        Some(Arc::new(OnOffRule::adjusted(self, delta)))
+    }
+
+    fn adjusted_reach(&self, delta: i32) -> Option<Arc<dyn Rule>> {
+        Some(Arc::new(OnOffRule::adjusted_reach(self, delta)))
+    }
+
+    fn reach(&self) -> Option<u32> {
+        match *self {
+            OnOffRule::PxThreshold(_)
+            | OnOffRule::InvertedPxThreshold(_)
+            | OnOffRule::LumaThreshold(_) => Some(0),
+            OnOffRule::Border(_, distance) => Some(distance.max(0) as u32),
+            OnOffRule::Adaptive { window, .. } => Some(window / 2),
+            OnOffRule::Sobel(_) => Some(1),
+            // the colors of the bands are those of the whole image
+            OnOffRule::Otsu | OnOffRule::Canny(..) | OnOffRule::Bands(_) => None,
+        }
+    }
+
+    fn levels(&self, ctx: &RuleCtx) -> Option<Levels> {
+        let OnOffRule::Bands(n) = *self else {
+            return None;
+        };
+        let luma = ctx.luma();
+
+        Some(Levels::from_fn(luma.width(), luma.height(), n, |x, y| {
+            band(luma.get_pixel(x, y)[0], n)
+        }))
+    }
+}
+
+/// Which of `n` even bands `luma` falls in.
+fn band(luma: u8, n: u8) -> u8 {
+    (luma as u32 * n as u32 / 256) as u8
+}
+
+/// Luma of the pixel at (`x`, `y`), with coordinates outside the image
+/// clamped to its edges.
+fn clamped_luma(luma: &GrayImage, x: i32, y: i32) -> i32 {
+    let x = x.clamp(0, luma.width() as i32 - 1) as u32;
+    let y = y.clamp(0, luma.height() as i32 - 1) as u32;
+
+    luma.get_pixel(x, y)[0] as i32
+}
+
+/// Horizontal and vertical Sobel gradients of the luma at (`x`, `y`).
+fn sobel(luma: &GrayImage, x: u32, y: u32) -> (i32, i32) {
+    let (x, y) = (x as i32, y as i32);
+    let l = |dx: i32, dy: i32| clamped_luma(luma, x + dx, y + dy);
+
+    let gx = (l(1, -1) + 2 * l(1, 0) + l(1, 1)) - (l(-1, -1) + 2 * l(-1, 0) + l(-1, 1));
+    let gy = (l(-1, 1) + 2 * l(0, 1) + l(1, 1)) - (l(-1, -1) + 2 * l(0, -1) + l(1, -1));
+
+    (gx, gy)
+}
+
+/// The luma threshold that best separates the image into two classes, as
+/// given by Otsu's method.
+pub fn otsu_threshold(img: &DynamicImage) -> u8 {
+    otsu_luma(&img.to_luma8())
+}
+
+/// [`otsu_threshold`] of the luma of an image.
+fn otsu_luma(luma: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    luma.pixels().for_each(|p| histogram[p[0] as usize] += 1);
+
+    let total: u64 = histogram.iter().sum();
+    let sum_all: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(v, &n)| v as f64 * n as f64)
+        .sum();
+
+    let mut sum_bg = 0.0;
+    let mut weight_bg = 0u64;
+    let mut best = (0.0, 0u8);
+
+    for (t, &n) in histogram.iter().enumerate() {
+        weight_bg += n;
+        if weight_bg == 0 {
+            continue;
+        }
+        let weight_fg = total - weight_bg;
+        if weight_fg == 0 {
+            break;
+        }
+
+        sum_bg += t as f64 * n as f64;
+        let mean_bg = sum_bg / weight_bg as f64;
+        let mean_fg = (sum_all - sum_bg) / weight_fg as f64;
+        let variance = weight_bg as f64 * weight_fg as f64 * (mean_bg - mean_fg).powi(2);
+
+        if variance > best.0 {
+            best = (variance, t as u8);
+        }
+    }
+
+    // pixels above the class boundary are on
+    best.1.saturating_add(1)
+}
+
+/// Formats the rule the way it is parsed.
+impl fmt::Display for OnOffRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OnOffRule::PxThreshold(t) => write!(f, "Threshold({})", t),
+            OnOffRule::InvertedPxThreshold(t) => write!(f, "InvertedThreshold({})", t),
+            OnOffRule::Border(t, distance) => write!(f, "Border({},{})", t, distance),
+            OnOffRule::LumaThreshold(t) => write!(f, "Luma({})", t),
+            OnOffRule::Otsu => write!(f, "Otsu"),
+            OnOffRule::Adaptive { window, c, kernel } => {
+                let name = match kernel {
+                    AdaptiveKernel::Mean => "Adaptive",
+                    AdaptiveKernel::Gaussian => "AdaptiveGaussian",
+                };
+                write!(f, "{}({},{})", name, window, c)
+            }
+            OnOffRule::Sobel(t) => write!(f, "Sobel({})", t),
+            OnOffRule::Canny(low, high) => write!(f, "Canny({},{})", low, high),
+            OnOffRule::Bands(n) => write!(f, "Bands({})", n),
+        }
+    }
+}
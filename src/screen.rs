@@ -0,0 +1,130 @@
+use crate::cell::Cell;
+use crate::color::ColorMode;
+use crate::delta;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Starts a synchronized update: the terminal holds off showing what is
+/// drawn until it ends (DEC private mode 2026).
+const BEGIN_SYNC: &str = "\x1b[?2026h";
+const END_SYNC: &str = "\x1b[?2026l";
+
+/// Where a [`Screen`] draws its frames.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Anchor {
+    /// From the line of the cursor, going back up over the previous frame.
+    Inline,
+    /// From the top left corner of the screen.
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    Home,
+}
+
+/// The back buffer of the frames drawn on the terminal: the cells it
+/// shows, for the next frame to rewrite only the ones that change, and the
+/// escapes drawing a frame, written out at once.
+pub(crate) struct Screen {
+    cells: Vec<Vec<Cell>>,
+    color: ColorMode,
+    anchor: Anchor,
+    synchronized: bool,
+    buf: String,
+}
+
+impl Screen {
+    pub(crate) fn new(color: ColorMode, anchor: Anchor, synchronized: bool) -> Self {
+        Screen {
+            cells: Vec::new(),
+            color,
+            anchor,
+            synchronized,
+            buf: String::new(),
+        }
+    }
+
+    /// Forgets what the terminal shows, for the next frame to be drawn
+    /// whole, e.g. after the terminal is resized.
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    pub(crate) fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    /// Draws `cells` over the previous frame, see [`delta::write`], in one
+    /// synchronized update if asked for the terminal not to show half drawn
+    /// frames; the cursor is left at the start of the line below.
+    pub(crate) fn draw<W: Write>(&mut self, out: &mut W, cells: Vec<Vec<Cell>>) -> io::Result<()> {
+        self.draw_with_footer(out, cells, "")
+    }
+
+    /// [`Screen::draw`], then `footer` over the line below the frame, in the
+    /// same update; the cursor is left after it.
+    pub(crate) fn draw_with_footer<W: Write>(
+        &mut self,
+        out: &mut W,
+        cells: Vec<Vec<Cell>>,
+        footer: &str,
+    ) -> io::Result<()> {
+        self.buf.clear();
+        if self.synchronized {
+            self.buf.push_str(BEGIN_SYNC);
+        }
+        match self.anchor {
+            Anchor::Inline if !self.cells.is_empty() => {
+                write!(self.buf, "\x1b[{}A", self.cells.len()).unwrap()
+            }
+            Anchor::Inline => {}
+            Anchor::Home => self.buf.push_str("\x1b[H"),
+        }
+        delta::write(&self.cells, &cells, self.color, &mut self.buf);
+        if !footer.is_empty() {
+            write!(self.buf, "\x1b[2K{}\r", footer).unwrap();
+        }
+        if self.synchronized {
+            self.buf.push_str(END_SYNC);
+        }
+
+        out.write_all(self.buf.as_bytes())?;
+        out.flush()?;
+        self.cells = cells;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(s: &str) -> Vec<Cell> {
+        s.chars().map(Cell::new).collect()
+    }
+
+    #[test]
+    fn draws_over_the_previous_frame() {
+        let draw = |screen: &mut Screen, cells: Vec<Vec<Cell>>| {
+            let mut out = Vec::new();
+            screen.draw(&mut out, cells).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let mut screen = Screen::new(ColorMode::None, Anchor::Inline, true);
+        assert_eq!(
+            draw(&mut screen, vec![line("ab"), line("cd")]),
+            "\x1b[?2026h\x1b[Jab\r\ncd\r\n\x1b[?2026l"
+        );
+        assert_eq!(
+            draw(&mut screen, vec![line("ab"), line("cX")]),
+            "\x1b[?2026h\x1b[2A\x1b[1B\x1b[2GX\x1b[1B\r\x1b[?2026l"
+        );
+
+        let mut screen = Screen::new(ColorMode::None, Anchor::Home, false);
+        draw(&mut screen, vec![line("ab")]);
+        assert_eq!(draw(&mut screen, vec![line("ab")]), "\x1b[H\x1b[1B\r");
+        screen.clear();
+        assert_eq!(draw(&mut screen, vec![line("ab")]), "\x1b[H\x1b[Jab\r\n");
+
+        let mut out = Vec::new();
+        screen
+            .draw_with_footer(&mut out, vec![line("ab")], "status")
+            .unwrap();
+        assert_eq!(out, b"\x1b[H\x1b[1B\r\x1b[2Kstatus\r");
+    }
+}
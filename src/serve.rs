@@ -0,0 +1,495 @@
+use crate::{exif_orientation, orient, render, OutputFormat, RenderOptions, RuleExpr};
+use image::io::Reader;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::str::FromStr;
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+/// Largest request body accepted, in bytes.
+const MAX_BODY: usize = 50 * 1024 * 1024;
+
+/// Longest request line or header accepted, in bytes.
+const MAX_LINE: u64 = 8 * 1024;
+
+/// Most headers accepted in a request.
+const MAX_HEADERS: usize = 64;
+
+/// Most pixels of the image the rule is evaluated on, once resized.
+const MAX_PIXELS: u64 = 4096 * 4096;
+
+/// Furthest a rule may look around every pixel.
+const MAX_REACH: u32 = 32;
+
+/// Time allowed for reading a request, between two reads.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How the server takes its requests.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ServeOptions {
+    /// Connections handled at once, the others waiting to be accepted.
+    pub workers: usize,
+    /// Fetch the images of `url=` queries, letting the clients make the
+    /// server reach any address it can, its own and its network's too.
+    pub allow_urls: bool,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        ServeOptions {
+            workers: 8,
+            allow_urls: false,
+        }
+    }
+}
+
+/// Renders images over HTTP on a fixed pool of threads, until accepting a
+/// connection fails. `POST /render` renders the image of the body,
+/// `GET /render?url=...` the one at the URL with the `net` feature and
+/// `allow_urls`; the query sets `rule`, `size`, `charset`, `color`,
+/// `dither`, `contrast`, `edges`, `invert` and `format` (text, html, svg
+/// or json) over `defaults`, as the flags of the same names do:
+///
+/// ```sh
+/// curl --data-binary @cat.png 'localhost:8080/render?size=60x_&format=html'
+/// ```
+pub fn serve(
+    listener: TcpListener,
+    defaults: &RenderOptions,
+    serve_opts: &ServeOptions,
+) -> io::Result<()> {
+    // accepting waits for a worker to be free, the connections beyond
+    // those left in the backlog of the listener
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(0);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..serve_opts.workers.max(1) {
+        let receiver = Arc::clone(&receiver);
+        let defaults = defaults.clone();
+        let serve_opts = *serve_opts;
+        thread::spawn(move || loop {
+            // the lock is only held while waiting for a connection, the
+            // receiver is whole even if it was poisoned
+            let receiver = receiver.lock().unwrap_or_else(PoisonError::into_inner);
+            let stream = match receiver.recv() {
+                Ok(stream) => stream,
+                Err(_) => break,
+            };
+            drop(receiver);
+            // a client gone away is no concern of the server, and a request
+            // that panics loses its connection alone, the panic being
+            // reported on stderr
+            let _ =
+                panic::catch_unwind(AssertUnwindSafe(|| handle(stream, &defaults, &serve_opts)));
+        });
+    }
+
+    for stream in listener.incoming() {
+        if let Err(mpsc::SendError(stream)) = sender.send(stream?) {
+            eprintln!(
+                "no worker left to handle the connection of {:?}, dropping it",
+                stream.peer_addr()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn handle(
+    mut stream: TcpStream,
+    defaults: &RenderOptions,
+    serve_opts: &ServeOptions,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let response = match Request::read(&mut reader) {
+        Ok(request) => respond(&request, defaults, serve_opts),
+        Err(response) => response,
+    };
+
+    response.write(&mut stream)
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    body: Vec<u8>,
+}
+
+impl Request {
+    /// Reads a request, the response being the error for the ones it can't
+    /// take.
+    fn read<R: BufRead>(reader: &mut R) -> Result<Self, Response> {
+        let bad = |message: &str| Response::error(400, "Bad Request", message);
+        let too_large = || {
+            Response::error(
+                431,
+                "Request Header Fields Too Large",
+                &format!(
+                    "lines are at most {} bytes, and headers at most {}",
+                    MAX_LINE, MAX_HEADERS
+                ),
+            )
+        };
+        let mut line = String::new();
+        let mut read_line = |line: &mut String| {
+            line.clear();
+            match reader.by_ref().take(MAX_LINE).read_line(line) {
+                Ok(0) | Err(_) => Err(bad("incomplete request")),
+                Ok(_) if !line.ends_with('\n') => Err(too_large()),
+                Ok(_) => Ok(line.trim_end().to_string()),
+            }
+        };
+
+        let request_line = read_line(&mut line)?;
+        let mut parts = request_line.split(' ');
+        let (method, target) = match (parts.next(), parts.next()) {
+            (Some(method), Some(target)) => (method.to_string(), target),
+            _ => return Err(bad("invalid request line")),
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let (path, query) = (path.to_string(), query.to_string());
+
+        let mut length = 0;
+        for headers in 0.. {
+            let header = read_line(&mut line)?;
+            if header.is_empty() {
+                break;
+            }
+            if headers == MAX_HEADERS {
+                return Err(too_large());
+            }
+            let (name, value) = header
+                .split_once(':')
+                .ok_or_else(|| bad("invalid header"))?;
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.parse().map_err(|_| bad("invalid Content-Length"))?;
+            } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                return Err(Response::error(
+                    411,
+                    "Length Required",
+                    "chunked bodies aren't supported, send a Content-Length",
+                ));
+            }
+        }
+        if length > MAX_BODY {
+            return Err(Response::error(
+                413,
+                "Payload Too Large",
+                &format!("bodies are at most {} bytes", MAX_BODY),
+            ));
+        }
+
+        let mut body = vec![0; length];
+        reader
+            .read_exact(&mut body)
+            .map_err(|_| bad("incomplete body"))?;
+
+        Ok(Request {
+            method,
+            path,
+            query,
+            body,
+        })
+    }
+}
+
+struct Response {
+    status: u16,
+    reason: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn error(status: u16, reason: &'static str, message: &str) -> Self {
+        Response {
+            status,
+            reason,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{}\n", message),
+        }
+    }
+
+    fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(
+            out,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            self.reason,
+            self.content_type,
+            self.body.len(),
+            self.body
+        )?;
+        out.flush()
+    }
+}
+
+fn respond(request: &Request, defaults: &RenderOptions, serve_opts: &ServeOptions) -> Response {
+    let bad = |message: &str| Response::error(400, "Bad Request", message);
+    if request.path != "/render" {
+        return Response::error(404, "Not Found", "the endpoint is /render");
+    }
+
+    let query = form_urlencoded::parse(request.query.as_bytes()).collect::<Vec<_>>();
+    let url = query
+        .iter()
+        .find(|(key, _)| key == "url")
+        .map(|(_, url)| url);
+    let bytes = match (request.method.as_str(), url) {
+        ("POST", None) if !request.body.is_empty() => Cow::Borrowed(&request.body),
+        ("GET", Some(_)) | ("POST", Some(_)) if !serve_opts.allow_urls => {
+            return Response::error(
+                403,
+                "Forbidden",
+                "fetching URLs is off, start the server with --allow-urls",
+            );
+        }
+        ("GET", Some(url)) | ("POST", Some(url)) => match fetch(url) {
+            Ok(bytes) => Cow::Owned(bytes),
+            Err(response) => return response,
+        },
+        ("GET", None) | ("POST", None) => {
+            return bad("send the image as the body of a POST, or its `url`");
+        }
+        _ => return Response::error(405, "Method Not Allowed", "use GET or POST"),
+    };
+
+    let opts = match options(&query, defaults) {
+        Ok(opts) => opts,
+        Err(message) => return bad(&message),
+    };
+    let content_type = match opts.format {
+        OutputFormat::Text => "text/plain; charset=utf-8",
+        OutputFormat::Html => "text/html; charset=utf-8",
+        OutputFormat::Svg(_) => "image/svg+xml",
+        OutputFormat::Json => "application/json",
+        _ => return bad("the formats are text, html, svg and json"),
+    };
+    // the header tells the size before the pixels are decoded at all
+    let too_large = format!("the images are at most {} pixels", MAX_PIXELS);
+    let img = match dimensions(&bytes) {
+        Ok((width, height)) if width as u64 * height as u64 > MAX_PIXELS => {
+            return bad(&too_large);
+        }
+        Ok(_) => decode(&bytes),
+        Err(err) => Err(err),
+    };
+    let img = match img {
+        Ok(img) => img,
+        Err(err) => return bad(&format!("couldn't decode the image: {}", err)),
+    };
+    let cell = opts.charset.cell_size();
+    let (width, height) = opts
+        .size
+        .dimensions(img.dimensions(), cell, opts.cell_aspect);
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return bad(&format!("{}, ask for a smaller `size`", too_large));
+    }
+
+    Response {
+        status: 200,
+        reason: "OK",
+        content_type,
+        body: render(&img, &opts),
+    }
+}
+
+/// `defaults` with the options of the query.
+fn options(
+    query: &[(Cow<str>, Cow<str>)],
+    defaults: &RenderOptions,
+) -> Result<RenderOptions, String> {
+    let mut opts = defaults.clone();
+    for (key, value) in query {
+        match key.as_ref() {
+            "url" => {}
+            "rule" => {
+                opts.rule = parse(key, value)?;
+                if reach(&opts.rule) > MAX_REACH {
+                    return Err(format!(
+                        "the rules look at most {} pixels around",
+                        MAX_REACH
+                    ));
+                }
+            }
+            "size" => opts.size = parse(key, value)?,
+            "charset" => opts.charset = parse(key, value)?,
+            "color" => opts.color = parse(key, value)?,
+            "dither" => opts.dither = parse(key, value)?,
+            "contrast" => opts.preprocess.contrast = parse(key, value)?,
+            "edges" => opts.edges = parse(key, value)?,
+            // `invert` alone inverts
+            "invert" if value.is_empty() => opts.invert = true,
+            "invert" => opts.invert = parse(key, value)?,
+            "format" => opts.format = parse(key, value)?,
+            _ => return Err(format!("unknown parameter `{}`", key)),
+        }
+    }
+
+    Ok(opts)
+}
+
+/// The furthest any rule of `expr` looks around every pixel.
+fn reach(expr: &RuleExpr) -> u32 {
+    match expr {
+        RuleExpr::Rule(rule) => rule.reach().unwrap_or(0),
+        RuleExpr::Not(e) => reach(e),
+        RuleExpr::And(a, b) | RuleExpr::Or(a, b) => reach(a).max(reach(b)),
+    }
+}
+
+fn parse<T>(key: &str, value: &str) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|err| format!("invalid `{}`: {}", key, err))
+}
+
+/// The size of the image of `bytes`, read from its header alone.
+fn dimensions(bytes: &[u8]) -> image::ImageResult<(u32, u32)> {
+    Reader::new(Cursor::new(bytes))
+        .with_guessed_format()?
+        .into_dimensions()
+}
+
+/// Decodes an image, turning JPEGs upright.
+fn decode(bytes: &[u8]) -> image::ImageResult<DynamicImage> {
+    let img = image::load_from_memory(bytes)?;
+    if image::guess_format(bytes)? != ImageFormat::Jpeg {
+        return Ok(img);
+    }
+
+    let (rotate, flip) = exif_orientation(bytes);
+    Ok(orient(Cow::Owned(img), rotate, flip).into_owned())
+}
+
+#[cfg(feature = "net")]
+fn fetch(url: &str) -> Result<Vec<u8>, Response> {
+    crate::fetch(url, &Default::default()).map_err(|err| {
+        Response::error(
+            502,
+            "Bad Gateway",
+            &format!("couldn't fetch the image: {}", err),
+        )
+    })
+}
+
+#[cfg(not(feature = "net"))]
+fn fetch(_: &str) -> Result<Vec<u8>, Response> {
+    Err(Response::error(
+        501,
+        "Not Implemented",
+        "fetching URLs needs the `net` feature",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = DynamicImage::ImageLuma8(GrayImage::from_pixel(width, height, Luma([255])));
+        let mut bytes = Vec::new();
+        img.write_to(&mut bytes, ImageFormat::Png).unwrap();
+        bytes
+    }
+
+    /// The response to the request of `head` and `body`, as sent.
+    fn exchange(head: &str, body: &[u8]) -> String {
+        let mut request = head.replace('\n', "\r\n").into_bytes();
+        request.extend_from_slice(body);
+        let response = match Request::read(&mut request.as_slice()) {
+            Ok(request) => respond(
+                &request,
+                &RenderOptions::default(),
+                &ServeOptions::default(),
+            ),
+            Err(response) => response,
+        };
+
+        let mut out = Vec::new();
+        response.write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn renders_posted_images() {
+        let body = png(4, 4);
+        let head = format!(
+            "POST /render?size=4x_&invert=false HTTP/1.1\nContent-Length: {}\n\n",
+            body.len()
+        );
+        assert_eq!(
+            exchange(&head, &body),
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\n\
+             Content-Length: 7\r\nConnection: close\r\n\r\n⣿⣿\n"
+        );
+
+        let head = format!(
+            "POST /render?format=json HTTP/1.1\nContent-Length: {}\n\n",
+            body.len()
+        );
+        assert!(exchange(&head, &body).contains("Content-Type: application/json"));
+    }
+
+    #[test]
+    fn rejects_bad_requests() {
+        let status = |head: &str, body: &[u8]| exchange(head, body)[9..12].to_string();
+        let body = png(4, 4);
+        let head = |target: &str| {
+            format!(
+                "POST {} HTTP/1.1\nContent-Length: {}\n\n",
+                target,
+                body.len()
+            )
+        };
+
+        assert_eq!(status(&head("/"), &body), "404");
+        // turned down before its pixels are decoded
+        let huge = png(4097, 4096);
+        let huge_head = format!(
+            "POST /render?size=1x1 HTTP/1.1\nContent-Length: {}\n\n",
+            huge.len()
+        );
+        assert_eq!(status(&huge_head, &huge), "400");
+        assert_eq!(status(&head("/render?size=huge"), &body), "400");
+        assert_eq!(status(&head("/render?size=100000x100000"), &body), "400");
+        assert_eq!(status(&head("/render?rule=Adaptive(999,5)"), &body), "400");
+        assert_eq!(
+            status("GET /render?url=http://127.0.0.1/ HTTP/1.1\n\n", b""),
+            "403"
+        );
+        assert_eq!(status(&head("/render?colour=red"), &body), "400");
+        assert_eq!(status(&head("/render?format=png"), &body), "400");
+        assert_eq!(status("GET /render HTTP/1.1\n\n", b""), "400");
+        assert_eq!(status("DELETE /render HTTP/1.1\n\n", b""), "405");
+        assert_eq!(
+            status("POST /render HTTP/1.1\nContent-Length: 3\n\n", b"abc"),
+            "400"
+        );
+        assert_eq!(
+            status("POST /render HTTP/1.1\nTransfer-Encoding: chunked\n\n", b""),
+            "411"
+        );
+
+        let long = format!("GET /render?{} HTTP/1.1\n\n", "a".repeat(MAX_LINE as usize));
+        assert_eq!(status(&long, b""), "431");
+        let many = format!(
+            "GET /render HTTP/1.1\n{}\n",
+            "X-A: b\n".repeat(MAX_HEADERS + 1)
+        );
+        assert_eq!(status(&many, b""), "431");
+    }
+}
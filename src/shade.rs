@@ -0,0 +1,125 @@
+use crate::bitmap::Bitmap;
+use crate::dither::bayer_matrix;
+use image::GrayImage;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// How the on/off state of the pixels is decided.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Shade {
+    /// Pixel by pixel, by the rule.
+    #[default]
+    Threshold,
+    /// Cell by cell, as many pixels on as the brightness of the cell asks
+    /// for, spread in an ordered pattern: smoother gradients without colors.
+    Density,
+}
+
+impl Shade {
+    /// The pixels on in `gray` shaded cell by cell, for cells of
+    /// `cell_size`; the brightest cells are all on.
+    pub(crate) fn density(gray: &GrayImage, (cell_width, cell_height): (u32, u32)) -> Bitmap {
+        let (width, height) = gray.dimensions();
+        let order = cell_order((cell_width, cell_height));
+
+        let mut bitmap = Bitmap::new(width, height);
+        for cy in (0..height).step_by(cell_height.max(1) as usize) {
+            for cx in (0..width).step_by(cell_width.max(1) as usize) {
+                let pixels: Vec<(u32, u32)> = order
+                    .iter()
+                    .map(|&(dx, dy)| (cx + dx, cy + dy))
+                    .filter(|&(x, y)| x < width && y < height)
+                    .collect();
+                let sum: u32 = pixels
+                    .iter()
+                    .map(|&(x, y)| gray.get_pixel(x, y)[0] as u32)
+                    .sum();
+                let on = (sum as f32 / 255.0).round() as usize;
+
+                for &(x, y) in pixels.iter().take(on) {
+                    bitmap.set(x, y, true);
+                }
+            }
+        }
+
+        bitmap
+    }
+}
+
+/// The pixels of a cell in the order they are turned on, the order of an
+/// 8x8 Bayer matrix, so that every count of pixels is spread out evenly.
+fn cell_order((width, height): (u32, u32)) -> Vec<(u32, u32)> {
+    let matrix = bayer_matrix(8);
+    let mut order: Vec<(u32, u32)> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .collect();
+    order.sort_by_key(|&(x, y)| matrix[y as usize % 8][x as usize % 8]);
+
+    order
+}
+
+/// Formats the shading the way it is parsed.
+impl fmt::Display for Shade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Shade::Threshold => write!(f, "threshold"),
+            Shade::Density => write!(f, "density"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ShadeParseError {
+    #[error("unknown shading `{0}`")]
+    UnknownShade(String),
+}
+
+impl FromStr for Shade {
+    type Err = ShadeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "threshold" => Ok(Shade::Threshold),
+            "density" => Ok(Shade::Density),
+            _ => Err(ShadeParseError::UnknownShade(s.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::braille;
+    use image::Luma;
+
+    #[test]
+    fn orders_the_dots() {
+        assert_eq!(
+            cell_order((2, 4)),
+            [
+                (0, 0),
+                (0, 2),
+                (1, 1),
+                (1, 3),
+                (1, 0),
+                (1, 2),
+                (0, 1),
+                (0, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn shades_by_density() {
+        // cells of a quarter, half and full brightness
+        let gray = GrayImage::from_fn(6, 4, |x, _| Luma([[64, 128, 255][x as usize / 2]]));
+        let bitmap = Shade::density(&gray, (2, 4));
+        let dots = |x| braille::dots(braille::region_braille(&bitmap, x, 0)).unwrap();
+
+        assert_eq!(
+            (0..3).map(|x| dots(x).count_ones()).collect::<Vec<_>>(),
+            [2, 4, 8]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use image::Rgb;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// The sixel of 6 pixels, the top one in bit 0.
+fn sixel(bits: u8) -> char {
+    (b'?' + bits) as char
+}
+
+/// Appends the sixels of a band in one color, runs of the same sixel
+/// compressed; the empty sixels at the end are left out.
+fn push_runs(out: &mut String, bits: &[u8]) {
+    let end = bits.iter().rposition(|&b| b != 0).map_or(0, |x| x + 1);
+
+    let mut x = 0;
+    while x < end {
+        let run = bits[x..end].iter().take_while(|&&b| b == bits[x]).count();
+        if run > 3 {
+            write!(out, "!{}{}", run, sixel(bits[x])).unwrap();
+        } else {
+            (0..run).for_each(|_| out.push(sixel(bits[x])));
+        }
+        x += run;
+    }
+}
+
+/// Writes a `width`x`height` image as sixel graphics, `pixel` giving the
+/// index in `palette` of the color of the pixel at (`x`, `y`), or `None` to
+/// leave it transparent.
+pub(crate) fn write<F>(width: u32, height: u32, palette: &[Rgb<u8>], pixel: F) -> String
+where
+    F: Fn(u32, u32) -> Option<u8>,
+{
+    // the second parameter keeps the background under the unset pixels
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    let percent = |v: u8| (v as u32 * 100 + 127) / 255;
+    for (i, c) in palette.iter().enumerate() {
+        write!(
+            out,
+            "#{};2;{};{};{}",
+            i,
+            percent(c[0]),
+            percent(c[1]),
+            percent(c[2])
+        )
+        .unwrap();
+    }
+
+    for band in (0..height).step_by(6) {
+        if band > 0 {
+            out.push('-');
+        }
+
+        // the sixels of every color used in the band
+        let mut colors = BTreeMap::new();
+        for dy in 0..6.min(height - band) {
+            for x in 0..width {
+                if let Some(i) = pixel(x, band + dy) {
+                    colors.entry(i).or_insert_with(|| vec![0; width as usize])[x as usize] |=
+                        1 << dy;
+                }
+            }
+        }
+
+        for (n, (i, bits)) in colors.iter().enumerate() {
+            if n > 0 {
+                out.push('$');
+            }
+            write!(out, "#{}", i).unwrap();
+            push_runs(&mut out, bits);
+        }
+    }
+
+    out.push_str("\x1b\\\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_bands_and_runs() {
+        // a 5x7 image: a column of color 1 at x=0, the rest of the top row
+        // in color 0
+        let palette = [Rgb([255, 255, 255]), Rgb([255, 0, 0])];
+        let sixels = write(5, 7, &palette, |x, y| match (x, y) {
+            (0, _) => Some(1),
+            (_, 0) => Some(0),
+            _ => None,
+        });
+
+        assert_eq!(
+            sixels,
+            "\x1bP0;1;0q\"1;1;5;7#0;2;100;100;100#1;2;100;0;0\
+             #0?!4@$#1~-#1@\x1b\\\n"
+        );
+    }
+}
@@ -0,0 +1,410 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView};
+use std::borrow::Cow;
+use std::fmt;
+use std::num::{ParseFloatError, ParseIntError};
+use std::str::FromStr;
+#[cfg(feature = "tui")]
+use terminal_size::{terminal_size, Height, Width};
+use thiserror::Error;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum ImageSize {
+    #[default]
+    Default,
+
+    Sized {
+        width: u32,
+        height: u32,
+    },
+
+    /// Only the width is given, the height follows the aspect ratio.
+    Width(u32),
+
+    /// Only the height is given, the width follows the aspect ratio.
+    Height(u32),
+
+    /// Scale both dimensions by the same factor.
+    Scale(f32),
+
+    /// Fit the output within the rows and columns of the current terminal.
+    Fit,
+}
+
+impl ImageSize {
+    /// Resizes `img` with `filter`, for an output whose characters each
+    /// cover `cell` (width, height) pixels, drawn in terminal cells of the
+    /// given aspect; the height is scaled so that shapes keep their
+    /// proportions when the pixels aren't shown square.
+    pub fn apply<'a>(
+        &self,
+        img: &'a DynamicImage,
+        cell: (u32, u32),
+        aspect: CellAspect,
+        filter: ResizeFilter,
+    ) -> Cow<'a, DynamicImage> {
+        let (width, height) = self.dimensions(img.dimensions(), cell, aspect);
+
+        if width != img.width() || height != img.height() {
+            Cow::Owned(img.resize_exact(width, height, filter.into()))
+        } else {
+            Cow::Borrowed(img)
+        }
+    }
+
+    /// The size an image of `size` is resized to, see [`ImageSize::apply`].
+    pub fn dimensions(
+        &self,
+        (img_width, img_height): (u32, u32),
+        cell: (u32, u32),
+        aspect: CellAspect,
+    ) -> (u32, u32) {
+        // the height of the image in pixels as tall as wide
+        let height = match img_height {
+            0 => 0,
+            h => ((h as f64 * aspect.pixel_ratio(cell)).round() as u32).max(1),
+        };
+
+        match *self {
+            ImageSize::Default => (img_width, height),
+            ImageSize::Sized {
+                width: max_width,
+                height: max_height,
+            } => fit((img_width, height), (max_width, max_height)),
+            ImageSize::Width(width) => (width, proportional(height, width, img_width)),
+            ImageSize::Height(height_) => (proportional(img_width, height_, height), height_),
+            ImageSize::Scale(factor) => {
+                let width = (img_width as f32 * factor).round() as u32;
+                let height = (height as f32 * factor).round() as u32;
+                (width.max(1), height.max(1))
+            }
+            ImageSize::Fit => match terminal_pixels(cell) {
+                Some((width, height)) => ImageSize::Sized { width, height }.dimensions(
+                    (img_width, img_height),
+                    cell,
+                    aspect,
+                ),
+                None => ImageSize::Default.dimensions((img_width, img_height), cell, aspect),
+            },
+        }
+    }
+
+    /// The size of every tile of a grid of (`columns`, `rows`) tiles,
+    /// `gap` (horizontal, vertical) pixels apart, sharing this size; the
+    /// sizes not bounding the image are the same for every tile.
+    pub fn tile(
+        &self,
+        (columns, rows): (u32, u32),
+        (gap_x, gap_y): (u32, u32),
+        cell: (u32, u32),
+    ) -> ImageSize {
+        let share = |len: u32, n: u32, gap: u32| {
+            let n = n.max(1);
+            (len.saturating_sub(gap * (n - 1)) / n).max(1)
+        };
+
+        match *self {
+            ImageSize::Sized { width, height } => ImageSize::Sized {
+                width: share(width, columns, gap_x),
+                height: share(height, rows, gap_y),
+            },
+            ImageSize::Width(width) => ImageSize::Width(share(width, columns, gap_x)),
+            ImageSize::Height(height) => ImageSize::Height(share(height, rows, gap_y)),
+            ImageSize::Fit => match terminal_pixels(cell) {
+                Some((width, height)) => {
+                    ImageSize::Sized { width, height }.tile((columns, rows), (gap_x, gap_y), cell)
+                }
+                None => ImageSize::Default,
+            },
+            ImageSize::Default | ImageSize::Scale(_) => *self,
+        }
+    }
+
+    /// Parses a scale factor, either as a fraction (`0.5`) or as a
+    /// percentage (`50%`).
+    pub fn parse_scale(s: &str) -> Result<Self, ImageSizeParseError> {
+        let factor = match s.strip_suffix('%') {
+            Some(percent) => f32::from_str(percent)? / 100.0,
+            None => f32::from_str(s)?,
+        };
+
+        if !(factor > 0.0 && factor.is_finite()) {
+            return Err(ImageSizeParseError::InvalidScale(s.into()));
+        }
+
+        Ok(Self::Scale(factor))
+    }
+}
+
+/// `v * num / den`, rounded and never zero.
+fn proportional(v: u32, num: u32, den: u32) -> u32 {
+    ((v as u64 * num as u64 + den as u64 / 2) / den.max(1) as u64).max(1) as u32
+}
+
+/// The largest size within `max` with the proportions of `size`, rounded
+/// down like [`DynamicImage::resize`].
+fn fit((width, height): (u32, u32), (max_width, max_height): (u32, u32)) -> (u32, u32) {
+    let ratio = width as u64 * max_height as u64;
+    let max_ratio = max_width as u64 * height as u64;
+
+    if max_ratio <= ratio {
+        let height = height as u64 * max_width as u64 / (width as u64).max(1);
+        (max_width, height as u32)
+    } else {
+        let width = width as u64 * max_height as u64 / (height as u64).max(1);
+        (width as u32, max_height)
+    }
+}
+
+/// The largest image, in pixels, whose rendering fits in the terminal attached
+/// to stdout, keeping one row free for the prompt.
+#[cfg(feature = "tui")]
+fn terminal_pixels((cell_width, cell_height): (u32, u32)) -> Option<(u32, u32)> {
+    let (Width(cols), Height(rows)) = terminal_size()?;
+    let cols = cols as u32;
+    let rows = (rows as u32).saturating_sub(1);
+
+    Some((cols * cell_width, rows * cell_height))
+}
+
+/// Without the `tui` feature the terminal size is unknown.
+#[cfg(not(feature = "tui"))]
+fn terminal_pixels(_: (u32, u32)) -> Option<(u32, u32)> {
+    None
+}
+
+/// The filter an image is resized with.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ResizeFilter {
+    /// The closest pixel, keeping hard edges for pixel art and QR codes.
+    Nearest,
+    #[default]
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+impl From<ResizeFilter> for FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Gaussian => FilterType::Gaussian,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ResizeFilterParseError {
+    #[error("unknown resize filter `{0}`")]
+    UnknownResizeFilter(String),
+}
+
+impl FromStr for ResizeFilter {
+    type Err = ResizeFilterParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(ResizeFilter::Nearest),
+            "triangle" => Ok(ResizeFilter::Triangle),
+            "catmullrom" => Ok(ResizeFilter::CatmullRom),
+            "gaussian" => Ok(ResizeFilter::Gaussian),
+            "lanczos3" => Ok(ResizeFilter::Lanczos3),
+            _ => Err(ResizeFilterParseError::UnknownResizeFilter(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for ResizeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ResizeFilter::Nearest => "nearest",
+            ResizeFilter::Triangle => "triangle",
+            ResizeFilter::CatmullRom => "catmullrom",
+            ResizeFilter::Gaussian => "gaussian",
+            ResizeFilter::Lanczos3 => "lanczos3",
+        })
+    }
+}
+
+/// The shape of the terminal cells, as width:height; a cell is usually
+/// about twice as tall as wide.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CellAspect {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for CellAspect {
+    fn default() -> Self {
+        CellAspect {
+            width: 1.0,
+            height: 2.0,
+        }
+    }
+}
+
+impl CellAspect {
+    /// The width over the height of a pixel, for characters covering
+    /// `cell` (width, height) pixels.
+    pub fn pixel_ratio(&self, (cell_width, cell_height): (u32, u32)) -> f64 {
+        (self.width as f64 * cell_height as f64) / (self.height as f64 * cell_width as f64)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CellAspectParseError {
+    #[error("invalid cell aspect `{0}`, expected W:H with positive numbers")]
+    InvalidCellAspect(String),
+}
+
+impl FromStr for CellAspect {
+    type Err = CellAspectParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || CellAspectParseError::InvalidCellAspect(s.into());
+        let (width, height) = s.split_once(':').ok_or_else(invalid)?;
+        let side = |v: &str| {
+            f32::from_str(v)
+                .ok()
+                .filter(|v| *v > 0.0 && v.is_finite())
+                .ok_or_else(invalid)
+        };
+
+        Ok(CellAspect {
+            width: side(width)?,
+            height: side(height)?,
+        })
+    }
+}
+
+impl fmt::Display for CellAspect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.width, self.height)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ImageSizeParseError {
+    #[error("couldn't parse an int in the image size")]
+    ParseIntError(#[from] ParseIntError),
+    #[error("couldn't parse the scale factor")]
+    ParseFloatError(#[from] ParseFloatError),
+    #[error("the scale factor must be positive, got `{0}`")]
+    InvalidScale(String),
+    #[error("unknown size format `{0}`")]
+    UnknownSizeFormat(String),
+}
+
+/// Formats the size the way it is parsed, scales the way `--scale` takes
+/// them.
+impl fmt::Display for ImageSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageSize::Default => write!(f, "_"),
+            ImageSize::Sized { width, height } => write!(f, "{}x{}", width, height),
+            ImageSize::Width(width) => write!(f, "{}x_", width),
+            ImageSize::Height(height) => write!(f, "_x{}", height),
+            ImageSize::Scale(factor) => write!(f, "{}%", factor * 100.0),
+            ImageSize::Fit => write!(f, "fit"),
+        }
+    }
+}
+
+impl FromStr for ImageSize {
+    type Err = ImageSizeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "_" {
+            return Ok(Self::Default);
+        }
+        if s == "fit" {
+            return Ok(Self::Fit);
+        }
+        let mut it = s.split('x');
+        if let Some(w_str) = it.next() {
+            if let Some(h_str) = it.next() {
+                return Ok(match (w_str, h_str) {
+                    ("_", "_") => Self::Default,
+                    (w_str, "_") => Self::Width(u32::from_str(w_str)?),
+                    ("_", h_str) => Self::Height(u32::from_str(h_str)?),
+                    (w_str, h_str) => Self::Sized {
+                        width: u32::from_str(w_str)?,
+                        height: u32::from_str(h_str)?,
+                    },
+                });
+            }
+        }
+
+        Err(ImageSizeParseError::UnknownSizeFormat(s.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GrayImage;
+
+    fn sized(size: ImageSize, cell: (u32, u32), aspect: &str) -> (u32, u32) {
+        let img = DynamicImage::ImageLuma8(GrayImage::new(40, 40));
+        size.apply(&img, cell, aspect.parse().unwrap(), ResizeFilter::Nearest)
+            .dimensions()
+    }
+
+    #[test]
+    fn corrects_cell_aspect() {
+        // braille dots are square in cells twice as tall as wide
+        assert_eq!(sized(ImageSize::Default, (2, 4), "1:2"), (40, 40));
+        // quadrant dots are twice as tall as wide
+        assert_eq!(sized(ImageSize::Default, (2, 2), "1:2"), (40, 20));
+        assert_eq!(sized(ImageSize::Width(20), (2, 2), "1:2"), (20, 10));
+        assert_eq!(sized(ImageSize::Height(20), (2, 2), "1:2"), (40, 20));
+        assert_eq!(
+            sized(
+                ImageSize::Sized {
+                    width: 20,
+                    height: 20
+                },
+                (2, 4),
+                "1:3"
+            ),
+            (20, 13)
+        );
+    }
+
+    #[test]
+    fn nearest_keeps_hard_edges() {
+        // a checkerboard of 1x1 squares, doubled
+        let img = DynamicImage::ImageLuma8(GrayImage::from_fn(4, 4, |x, y| {
+            image::Luma([if (x + y) % 2 == 0 { 0 } else { 255 }])
+        }));
+        let resized = ImageSize::Scale(2.0).apply(
+            &img,
+            (2, 4),
+            CellAspect::default(),
+            "nearest".parse().unwrap(),
+        );
+
+        assert!(resized
+            .to_luma8()
+            .pixels()
+            .all(|p| p[0] == 0 || p[0] == 255));
+        assert!("bicubic".parse::<ResizeFilter>().is_err());
+    }
+
+    #[test]
+    fn parses_cell_aspect() {
+        assert_eq!(
+            "8:17".parse::<CellAspect>().unwrap(),
+            CellAspect {
+                width: 8.0,
+                height: 17.0
+            }
+        );
+        assert!("1".parse::<CellAspect>().is_err());
+        assert!("0:2".parse::<CellAspect>().is_err());
+    }
+}
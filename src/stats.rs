@@ -0,0 +1,222 @@
+use crate::grid::CellGrid;
+use crate::json;
+use crate::{with_ascii, write_output, RenderOptions, RuleCtx, RuleExpr, Stage, Stopwatch};
+use image::{DynamicImage, GenericImageView};
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Figures about the render of an image, see [`render_stats`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderStats {
+    /// Size (width, height) of the input image, in pixels.
+    pub input_size: (u32, u32),
+    /// Size of the image the rule is evaluated on, once resized.
+    pub image_size: (u32, u32),
+    /// Columns and rows of cells of the output.
+    pub cells: (u32, u32),
+    pub luma: LumaSummary,
+    /// The share of the pixels that are on, in `0..=1`.
+    pub fill: f64,
+    /// Every rule of the expression, in the order they appear.
+    pub rules: Vec<RuleStats>,
+    /// Time spent in every stage of the render.
+    pub stages: Vec<(Stage, Duration)>,
+}
+
+/// The luma histogram of the image the rule is evaluated on, summed up.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LumaSummary {
+    pub min: u8,
+    pub max: u8,
+    pub mean: f64,
+    pub median: u8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleStats {
+    pub rule: String,
+    /// The rule evaluated in its place, for rules depending on the whole
+    /// image: `Luma(117)` for `Otsu`, with the threshold it picked.
+    pub resolved: Option<String>,
+    /// Time taken to evaluate the rule on its own.
+    pub elapsed: Duration,
+}
+
+/// Renders the image like [`render`](crate::render), measuring the render
+/// and the image on the way.
+pub fn render_stats(img: &DynamicImage, opts: &RenderOptions) -> RenderStats {
+    let mut stages = Vec::new();
+    // the render stage is timed here, without the stats
+    let mut on_stage = |stage, elapsed| {
+        if stage != Stage::Render {
+            stages.push((stage, elapsed));
+        }
+    };
+    let (mut stats, render) = with_ascii(img, opts, &mut on_stage, |ascii| {
+        let start = Stopwatch::start();
+        write_output(ascii, opts, &mut String::new());
+        let render = start.elapsed();
+
+        let image_size = ascii.source.dimensions();
+        let grid = CellGrid::new(image_size, opts.charset.cell_size(), opts.edges);
+        let pixels = image_size.0 as u64 * image_size.1 as u64;
+
+        let stats = RenderStats {
+            input_size: img.dimensions(),
+            image_size,
+            cells: (grid.columns, grid.rows),
+            luma: luma_summary(ascii.source),
+            fill: match pixels {
+                0 => 0.0,
+                _ => ascii.bitmap.count_ones() as f64 / pixels as f64,
+            },
+            rules: rule_stats(&opts.rule, ascii.source),
+            stages: Vec::new(),
+        };
+        (stats, render)
+    });
+
+    stages.push((Stage::Render, render));
+    stats.stages = stages;
+    stats
+}
+
+fn luma_summary(img: &DynamicImage) -> LumaSummary {
+    let mut histogram = [0u64; 256];
+    img.to_luma8()
+        .pixels()
+        .for_each(|p| histogram[p[0] as usize] += 1);
+
+    let total: u64 = histogram.iter().sum();
+    let values = || (0..=255u8).filter(|&v| histogram[v as usize] > 0);
+    let mut seen = 0;
+    let median = (0..=255u8)
+        .find(|&v| {
+            seen += histogram[v as usize];
+            seen * 2 >= total
+        })
+        .unwrap_or(0);
+    let sum: u64 = (0..256).map(|v| v as u64 * histogram[v]).sum();
+
+    LumaSummary {
+        min: values().next().unwrap_or(0),
+        max: values().next_back().unwrap_or(0),
+        mean: match total {
+            0 => 0.0,
+            _ => sum as f64 / total as f64,
+        },
+        median,
+    }
+}
+
+/// Every rule of `expr`, each resolved and evaluated on its own.
+fn rule_stats(expr: &RuleExpr, img: &DynamicImage) -> Vec<RuleStats> {
+    match expr {
+        RuleExpr::Rule(rule) => {
+            let start = Stopwatch::start();
+            rule.evaluate(&RuleCtx::new(img));
+            let elapsed = start.elapsed();
+
+            let resolved = rule
+                .resolve(img)
+                .map(|r| r.to_string())
+                .filter(|r| *r != rule.to_string());
+            vec![RuleStats {
+                rule: rule.to_string(),
+                resolved,
+                elapsed,
+            }]
+        }
+        RuleExpr::Not(e) => rule_stats(e, img),
+        RuleExpr::And(a, b) | RuleExpr::Or(a, b) => {
+            let mut rules = rule_stats(a, img);
+            rules.extend(rule_stats(b, img));
+            rules
+        }
+    }
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+impl RenderStats {
+    /// The stats as a JSON object, with the times in milliseconds.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\n");
+        let size = |(width, height): (u32, u32)| {
+            format!("{{\"width\": {}, \"height\": {}}}", width, height)
+        };
+
+        writeln!(out, "  \"input\": {},", size(self.input_size)).unwrap();
+        writeln!(out, "  \"image\": {},", size(self.image_size)).unwrap();
+        writeln!(
+            out,
+            "  \"cells\": {{\"columns\": {}, \"rows\": {}}},",
+            self.cells.0, self.cells.1
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  \"luma\": {{\"min\": {}, \"max\": {}, \"mean\": {:.2}, \"median\": {}}},",
+            self.luma.min, self.luma.max, self.luma.mean, self.luma.median
+        )
+        .unwrap();
+        writeln!(out, "  \"fill\": {:.4},", self.fill).unwrap();
+
+        out.push_str("  \"rules\": [");
+        for (i, rule) in self.rules.iter().enumerate() {
+            out.push_str(if i == 0 { "\n    " } else { ",\n    " });
+            out.push_str("{\"rule\": ");
+            json::push_string(&mut out, &rule.rule);
+            out.push_str(", \"resolved\": ");
+            match &rule.resolved {
+                Some(resolved) => json::push_string(&mut out, resolved),
+                None => out.push_str("null"),
+            }
+            write!(out, ", \"ms\": {:.3}}}", millis(rule.elapsed)).unwrap();
+        }
+        out.push_str("\n  ],\n");
+
+        out.push_str("  \"stages\": {");
+        for (i, (stage, elapsed)) in self.stages.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            write!(out, "\"{}\": {:.3}", stage, millis(*elapsed)).unwrap();
+        }
+        out.push_str("}\n}\n");
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OnOffRule;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn measures_the_image() {
+        // the left quarter dark, the rest light
+        let img = DynamicImage::ImageLuma8(GrayImage::from_fn(8, 8, |x, _| {
+            Luma([if x < 2 { 10 } else { 200 }])
+        }));
+        let opts = RenderOptions {
+            rule: RuleExpr::Not(Box::new(OnOffRule::Otsu.into())),
+            ..RenderOptions::default()
+        };
+        let stats = render_stats(&img, &opts);
+
+        assert_eq!(stats.cells, (4, 2));
+        assert_eq!(
+            (stats.luma.min, stats.luma.max, stats.luma.median),
+            (10, 200, 200)
+        );
+        assert_eq!(stats.luma.mean, 152.5);
+        assert_eq!(stats.fill, 0.25);
+        assert_eq!(stats.rules.len(), 1);
+        assert_eq!(stats.rules[0].resolved.as_deref(), Some("Luma(11)"));
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{
+    AsciiImage, Decorations, Dither, ImageSize, OutputFormat, RenderOptions, Rule, RuleCtx, Shade,
+};
+use image::{ColorType, DynamicImage, GenericImageView, ImageBuffer, ImageDecoder, ImageError};
+use std::io::{self, Read, Write};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum StreamError {
+    #[error("{0} can't be streamed")]
+    Unsupported(&'static str),
+    #[error(transparent)]
+    Image(#[from] ImageError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Renders the image `decoder` decodes as text, writing it to `out` a row of
+/// cells at a time; only the rows of that band, and the rows the rule looks
+/// at around it, are in memory at once.
+///
+/// Memory stays bounded as far as the decoder reads its rows in order, like
+/// the decoder of non interlaced PNGs. The image is rendered at its own
+/// size, without filters or dithering, and with rules that only look at the
+/// pixels around each pixel; anything else is an error.
+pub fn render_streamed<'a, D, W>(
+    decoder: D,
+    opts: &RenderOptions,
+    out: &mut W,
+) -> Result<(), StreamError>
+where
+    D: ImageDecoder<'a>,
+    W: Write,
+{
+    let reach = reach(opts)?;
+    let (width, height) = decoder.dimensions();
+    let color = decoder.color_type();
+    if !matches!(
+        color,
+        ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8
+    ) {
+        return Err(StreamError::Unsupported(
+            "an image with more than 8 bits per channel",
+        ));
+    }
+
+    let row_bytes = width as usize * color.bytes_per_pixel() as usize;
+    let cell_height = opts.charset.cell_size().1;
+    let mut reader = decoder.into_reader()?;
+
+    // the rows from `top` to `bottom`
+    let mut window = Vec::new();
+    let (mut top, mut bottom) = (0, 0);
+    for band in (0..height).step_by(cell_height as usize) {
+        let band_height = cell_height.min(height - band);
+
+        let end = (band + band_height + reach).min(height);
+        let start = window.len();
+        window.resize(start + (end - bottom) as usize * row_bytes, 0);
+        reader.read_exact(&mut window[start..])?;
+        bottom = end;
+
+        let first = band.saturating_sub(reach);
+        window.drain(..(first - top) as usize * row_bytes);
+        top = first;
+
+        let img = image(color, width, bottom - top, window.clone());
+        let text = render_band(&img, opts, band - top, band_height);
+        out.write_all(text.as_bytes())?;
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+/// How many rows around a band the rule of `opts` needs, if the rest of
+/// `opts` can be streamed.
+fn reach(opts: &RenderOptions) -> Result<u32, StreamError> {
+    let unsupported = |what| Err(StreamError::Unsupported(what));
+    let pre = &opts.preprocess;
+
+    if opts.size != ImageSize::Default
+        || opts.pixel_art.is_some()
+        || opts.quiet_zone > 0
+        || opts.cell_aspect.pixel_ratio(opts.charset.cell_size()) != 1.0
+    {
+        return unsupported("resizing");
+    }
+    if pre.rotate.is_some() || pre.flip.is_some() || pre.crop.is_some() || opts.auto_crop {
+        return unsupported("rotating, flipping or cropping");
+    }
+    if pre.blur.is_some() || pre.sharpen {
+        return unsupported("blurring or sharpening");
+    }
+    if opts.trim {
+        return unsupported("trimming");
+    }
+    if opts.decorations != Decorations::default() {
+        return unsupported("captions, watermarks and frames");
+    }
+    if opts.shade != Shade::Threshold {
+        return unsupported("shading by density");
+    }
+    #[cfg(feature = "color")]
+    if opts.palette_dither {
+        return unsupported("dithering");
+    }
+    if opts.dither != Dither::None {
+        return unsupported("dithering");
+    }
+    if opts.format != OutputFormat::Text {
+        return unsupported("an output format other than text");
+    }
+
+    match opts.rule.reach() {
+        Some(reach) => Ok(reach),
+        None => unsupported("a rule depending on the whole image"),
+    }
+}
+
+fn image(color: ColorType, width: u32, height: u32, raw: Vec<u8>) -> DynamicImage {
+    let img = match color {
+        ColorType::L8 => ImageBuffer::from_raw(width, height, raw).map(DynamicImage::ImageLuma8),
+        ColorType::La8 => ImageBuffer::from_raw(width, height, raw).map(DynamicImage::ImageLumaA8),
+        ColorType::Rgb8 => ImageBuffer::from_raw(width, height, raw).map(DynamicImage::ImageRgb8),
+        ColorType::Rgba8 => ImageBuffer::from_raw(width, height, raw).map(DynamicImage::ImageRgba8),
+        _ => None,
+    };
+
+    img.expect("rows of 8-bit pixels")
+}
+
+/// The text of the `height` rows from row `y` of `window`, the rule
+/// evaluated over all of `window`.
+fn render_band(window: &DynamicImage, opts: &RenderOptions, y: u32, height: u32) -> String {
+    let img = opts.preprocess.adjust_tones(window);
+    let composited = opts.alpha.apply(&img);
+    let bitmap = opts
+        .rule
+        .evaluate(&RuleCtx::new(&composited))
+        .rows(y, height);
+
+    let width = window.width();
+    let band = img.crop_imm(0, y, width, height);
+    let band_composited = composited.crop_imm(0, y, width, height);
+
+    let mut ascii = AsciiImage::from_bitmap(&band_composited, bitmap);
+    if opts.invert {
+        ascii = ascii.inverted();
+    }
+    #[cfg(feature = "color")]
+    if let Some(palette) = &opts.palette {
+        ascii = ascii.quantized(palette, false);
+    }
+    ascii
+        .masked_by_alpha(&band, opts.alpha)
+        .colored(&band_composited, opts.color)
+        .sampled_by(opts.color_sample)
+        .with_background(opts.background)
+        .with_edges(opts.edges)
+        .to_text(&opts.charset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "color")]
+    use crate::ColorMode;
+    use crate::{render, OnOffRule, RuleExpr};
+    use image::png::PngEncoder;
+    use image::{Rgb, RgbImage};
+
+    fn png(img: &RgbImage) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        PngEncoder::new(&mut bytes)
+            .encode(img, img.width(), img.height(), ColorType::Rgb8)
+            .unwrap();
+        bytes
+    }
+
+    fn streamed(bytes: &[u8], opts: &RenderOptions) -> Result<String, StreamError> {
+        let decoder = image::png::PngDecoder::new(bytes).unwrap();
+        let mut out = Vec::new();
+        render_streamed(decoder, opts, &mut out)?;
+
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn matches_the_whole_image() {
+        // a height that leaves a partial band at the bottom
+        let img = RgbImage::from_fn(37, 23, |x, y| {
+            Rgb([(x * 7) as u8, (y * 11) as u8, ((x ^ y) * 5) as u8])
+        });
+        let bytes = png(&img);
+        let whole = DynamicImage::ImageRgb8(img);
+
+        let rules = [
+            OnOffRule::LumaThreshold(100),
+            OnOffRule::Border(40, 3),
+            OnOffRule::Sobel(80),
+        ];
+        for rule in rules.iter() {
+            #[allow(unused_mut)]
+            let mut opts = RenderOptions {
+                rule: RuleExpr::from(*rule),
+                ..RenderOptions::default()
+            };
+            #[cfg(feature = "color")]
+            {
+                opts.color = ColorMode::TrueColor;
+            }
+
+            assert_eq!(streamed(&bytes, &opts).unwrap(), render(&whole, &opts));
+        }
+    }
+
+    #[test]
+    fn rejects_whole_image_rules() {
+        let bytes = png(&RgbImage::new(4, 4));
+        let opts = RenderOptions {
+            rule: OnOffRule::Otsu.into(),
+            ..RenderOptions::default()
+        };
+
+        assert!(matches!(
+            streamed(&bytes, &opts),
+            Err(StreamError::Unsupported(_))
+        ));
+    }
+}
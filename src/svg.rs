@@ -0,0 +1,160 @@
+use crate::braille;
+use crate::cell::Cell;
+use image::Rgb;
+use std::fmt::Write;
+
+/// Layout and styling of the SVG output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgOptions {
+    pub font: String,
+    /// Size (width, height) of every character cell, in SVG units.
+    pub cell_size: (f32, f32),
+    /// Color of the characters that aren't colored by the color mode.
+    pub fg: Rgb<u8>,
+    /// Background of the whole document; transparent if `None`.
+    pub bg: Option<Rgb<u8>>,
+    /// Draw braille as one circle per dot instead of as text.
+    pub dots: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            font: "monospace".into(),
+            cell_size: (8.0, 16.0),
+            fg: Rgb([0, 0, 0]),
+            bg: None,
+            dots: false,
+        }
+    }
+}
+
+fn css_color(c: Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", c[0], c[1], c[2])
+}
+
+fn escape(ch: char) -> String {
+    match ch {
+        '&' => "&amp;".into(),
+        '<' => "&lt;".into(),
+        '>' => "&gt;".into(),
+        ch => ch.to_string(),
+    }
+}
+
+fn header(opts: &SvgOptions, cols: usize, rows: usize) -> String {
+    let (cw, ch) = opts.cell_size;
+    let (width, height) = (cols as f32 * cw, rows as f32 * ch);
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        width, height, width, height
+    );
+    if let Some(bg) = opts.bg {
+        writeln!(
+            out,
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+            css_color(bg)
+        )
+        .unwrap();
+    }
+
+    out
+}
+
+/// Writes the cells as SVG text, one `<text>` element per row.
+pub(crate) fn write_text(cells: &[Vec<Cell>], opts: &SvgOptions) -> String {
+    let (cw, ch) = opts.cell_size;
+    let cols = cells.iter().map(Vec::len).max().unwrap_or(0);
+    let mut out = header(opts, cols, cells.len());
+
+    writeln!(
+        out,
+        "<g font-family=\"{}\" font-size=\"{}\" fill=\"{}\" xml:space=\"preserve\">",
+        opts.font,
+        ch,
+        css_color(opts.fg)
+    )
+    .unwrap();
+
+    for (y, row) in cells.iter().enumerate() {
+        let baseline = (y as f32 + 0.8) * ch;
+        for (x, cell) in row.iter().enumerate() {
+            let left = x as f32 * cw;
+            if let Some(bg) = cell.bg {
+                writeln!(
+                    out,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    left,
+                    y as f32 * ch,
+                    cw,
+                    ch,
+                    css_color(bg)
+                )
+                .unwrap();
+            }
+            if cell.ch == ' ' || cell.ch == '\u{2800}' {
+                continue;
+            }
+
+            let fill = cell
+                .fg
+                .map(|fg| format!(" fill=\"{}\"", css_color(fg)))
+                .unwrap_or_default();
+            writeln!(
+                out,
+                "<text x=\"{}\" y=\"{}\"{}>{}</text>",
+                left,
+                baseline,
+                fill,
+                escape(cell.ch)
+            )
+            .unwrap();
+        }
+    }
+
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+/// Writes braille cells as one circle per raised dot.
+pub(crate) fn write_dots(cells: &[Vec<Cell>], opts: &SvgOptions) -> String {
+    let (cw, ch) = opts.cell_size;
+    let cols = cells.iter().map(Vec::len).max().unwrap_or(0);
+    let mut out = header(opts, cols, cells.len());
+    let radius = (cw / 2.0).min(ch / 4.0) * 0.4;
+
+    writeln!(out, "<g fill=\"{}\">", css_color(opts.fg)).unwrap();
+
+    for (y, row) in cells.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            let bits = match braille::dots(cell.ch) {
+                Some(bits) => bits,
+                None => continue,
+            };
+
+            let fill = cell
+                .fg
+                .map(|fg| format!(" fill=\"{}\"", css_color(fg)))
+                .unwrap_or_default();
+            for (bit, &(dx, dy)) in braille::DOTS.iter().enumerate() {
+                if bits & (1 << bit) == 0 {
+                    continue;
+                }
+
+                writeln!(
+                    out,
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\"{}/>",
+                    x as f32 * cw + (dx as f32 + 0.5) * cw / 2.0,
+                    y as f32 * ch + (dy as f32 + 0.5) * ch / 4.0,
+                    radius,
+                    fill
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    out.push_str("</g>\n</svg>\n");
+    out
+}
@@ -0,0 +1,291 @@
+use crate::{CellAspect, Charset, ColorMode, OutputFormat, DEFAULT_RAMP};
+use std::env;
+
+/// What the terminal can show, to pick the defaults of the output from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The most colors the terminal is known to show.
+    pub color: ColorMode,
+    /// Whether braille characters show as one cell each; when they don't,
+    /// the font or the console likely lacks them.
+    pub braille: bool,
+}
+
+impl Capabilities {
+    /// Braille if it shows, else ASCII.
+    pub fn charset(&self) -> Charset {
+        if self.braille {
+            Charset::Braille
+        } else {
+            Charset::Ascii {
+                ramp: DEFAULT_RAMP.chars().collect(),
+            }
+        }
+    }
+}
+
+/// The capabilities of the terminal, as told by `$TERM`, `$COLORTERM` and
+/// `$NO_COLOR`; with the `tui` feature, the terminal of stdin and stdout is
+/// also asked where a braille character moved the cursor.
+pub fn detect_capabilities() -> Capabilities {
+    let caps = capabilities_from_env(|name| env::var(name).ok());
+
+    #[cfg(feature = "tui")]
+    let caps = Capabilities {
+        braille: caps.braille && braille_width().is_none_or(|width| width == 1),
+        ..caps
+    };
+
+    caps
+}
+
+/// [`detect_capabilities`] without asking the terminal, the environment
+/// variables given by `var`.
+fn capabilities_from_env<F>(var: F) -> Capabilities
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let term = var("TERM").unwrap_or_default();
+    let colorterm = var("COLORTERM").unwrap_or_default();
+    let no_color = var("NO_COLOR").is_some_and(|v| !v.is_empty());
+
+    Capabilities {
+        color: color_from_env(&term, &colorterm, no_color),
+        // the Linux console and hardware terminals have no braille
+        braille: !matches!(
+            term.as_str(),
+            "linux" | "dumb" | "vt100" | "vt102" | "vt220"
+        ),
+    }
+}
+
+#[cfg(feature = "color")]
+fn color_from_env(term: &str, colorterm: &str, no_color: bool) -> ColorMode {
+    if no_color || term.is_empty() || term == "dumb" {
+        ColorMode::None
+    } else if colorterm == "truecolor" || colorterm == "24bit" {
+        ColorMode::TrueColor
+    } else if term.contains("256color") {
+        ColorMode::Ansi256(Default::default())
+    } else {
+        ColorMode::Ansi16(Default::default())
+    }
+}
+
+#[cfg(not(feature = "color"))]
+fn color_from_env(_term: &str, _colorterm: &str, _no_color: bool) -> ColorMode {
+    ColorMode::None
+}
+
+/// How many columns a braille character moves the cursor, `None` when
+/// stdin or stdout isn't a terminal, or the terminal doesn't answer.
+#[cfg(feature = "tui")]
+fn braille_width() -> Option<u16> {
+    use crossterm::{cursor, terminal};
+    use std::io::{self, IsTerminal, Write};
+
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return None;
+    }
+
+    terminal::enable_raw_mode().ok()?;
+    let mut stdout = io::stdout();
+    let width = write!(stdout, "\r\u{28ff}")
+        .and_then(|_| stdout.flush())
+        .ok()
+        .and_then(|_| cursor::position().ok())
+        .map(|(column, _)| column);
+    // erases the probe
+    let _ = write!(stdout, "\r\x1b[K").and_then(|_| stdout.flush());
+    let _ = terminal::disable_raw_mode();
+
+    width
+}
+
+/// The shape of the cells of the terminal of stdout, from its size in
+/// pixels; `None` without the `tui` feature, or when the terminal doesn't
+/// tell its size in pixels.
+pub fn detect_cell_aspect() -> Option<CellAspect> {
+    #[cfg(feature = "tui")]
+    {
+        let size = crossterm::terminal::window_size().ok()?;
+        cell_aspect((size.width, size.height), (size.columns, size.rows))
+    }
+
+    #[cfg(not(feature = "tui"))]
+    None
+}
+
+/// The aspect of a cell of a terminal of `pixels` (width, height) holding
+/// `cells` (columns, rows).
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+fn cell_aspect(pixels: (u16, u16), cells: (u16, u16)) -> Option<CellAspect> {
+    if pixels.0 == 0 || pixels.1 == 0 || cells.0 == 0 || cells.1 == 0 {
+        return None;
+    }
+
+    Some(CellAspect {
+        width: pixels.0 as f32 / cells.0 as f32,
+        height: pixels.1 as f32 / cells.1 as f32,
+    })
+}
+
+/// Whether the terminal holds off showing what is drawn between the escapes
+/// of a synchronized update (DEC private mode 2026), as told by its
+/// environment, for animations not to tear; the terminals that don't
+/// ignore the escapes anyway.
+pub fn detect_synchronized_output() -> bool {
+    synchronized_from_env(|name| env::var(name).ok())
+}
+
+/// [`detect_synchronized_output`], the environment variables given by `var`.
+fn synchronized_from_env<F>(var: F) -> bool
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let term = var("TERM").unwrap_or_default();
+    let program = var("TERM_PROGRAM").unwrap_or_default();
+
+    [
+        "xterm-kitty",
+        "foot",
+        "alacritty",
+        "xterm-ghostty",
+        "contour",
+    ]
+    .iter()
+    .any(|name| term.starts_with(name))
+        || matches!(
+            program.as_str(),
+            "WezTerm" | "iTerm.app" | "ghostty" | "contour"
+        )
+        || var("KITTY_WINDOW_ID").is_some()
+        || var("LC_TERMINAL").as_deref() == Some("iTerm2")
+}
+
+/// The best output format for the terminal, as told by its environment:
+/// one of its graphics protocols, or else text.
+pub fn detect_format() -> OutputFormat {
+    format_from_env(|name| env::var(name).ok())
+}
+
+/// [`detect_format`], the environment variables given by `var`.
+fn format_from_env<F>(var: F) -> OutputFormat
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let term = var("TERM").unwrap_or_default();
+    let program = var("TERM_PROGRAM").unwrap_or_default();
+
+    if term == "xterm-kitty" || var("KITTY_WINDOW_ID").is_some() || program == "ghostty" {
+        OutputFormat::Kitty
+    } else if program == "iTerm.app"
+        || program == "WezTerm"
+        || var("LC_TERMINAL").as_deref() == Some("iTerm2")
+    {
+        OutputFormat::Iterm2
+    } else if term.starts_with("foot") || term.starts_with("mlterm") || term.contains("sixel") {
+        OutputFormat::Sixel
+    } else {
+        OutputFormat::Text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The environment variables `vars`.
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    fn detect(vars: &[(&str, &str)]) -> OutputFormat {
+        format_from_env(env(vars))
+    }
+
+    fn capabilities(vars: &[(&str, &str)]) -> Capabilities {
+        capabilities_from_env(env(vars))
+    }
+
+    #[test]
+    fn detects_braille() {
+        assert!(capabilities(&[("TERM", "xterm-256color")]).braille);
+        assert!(!capabilities(&[("TERM", "linux")]).braille);
+        assert_eq!(
+            capabilities(&[("TERM", "linux")]).charset(),
+            "ascii".parse().unwrap()
+        );
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn detects_color_depth() {
+        let color = |vars: &[(&str, &str)]| capabilities(vars).color;
+
+        assert_eq!(
+            color(&[("TERM", "xterm-256color"), ("COLORTERM", "truecolor")]),
+            ColorMode::TrueColor
+        );
+        assert_eq!(
+            color(&[("TERM", "xterm-256color")]),
+            ColorMode::Ansi256(Default::default())
+        );
+        assert_eq!(
+            color(&[("TERM", "xterm")]),
+            ColorMode::Ansi16(Default::default())
+        );
+        assert_eq!(
+            color(&[("TERM", "xterm-256color"), ("NO_COLOR", "1")]),
+            ColorMode::None
+        );
+        assert_eq!(color(&[("TERM", "dumb")]), ColorMode::None);
+        assert_eq!(color(&[]), ColorMode::None);
+    }
+
+    #[test]
+    fn detects_synchronized_output() {
+        let synchronized = |vars: &[(&str, &str)]| synchronized_from_env(env(vars));
+
+        assert!(synchronized(&[("TERM", "xterm-kitty")]));
+        assert!(synchronized(&[("TERM", "foot-extra")]));
+        assert!(synchronized(&[("TERM_PROGRAM", "WezTerm")]));
+        assert!(!synchronized(&[("TERM", "xterm-256color")]));
+        assert!(!synchronized(&[]));
+    }
+
+    #[test]
+    fn measures_cell_aspect() {
+        assert_eq!(
+            cell_aspect((800, 600), (100, 30)),
+            Some(CellAspect {
+                width: 8.0,
+                height: 20.0
+            })
+        );
+        // terminals not telling their size in pixels answer zeros
+        assert_eq!(cell_aspect((0, 0), (100, 30)), None);
+    }
+
+    #[test]
+    fn detects_graphics_protocols() {
+        assert_eq!(detect(&[("TERM", "xterm-kitty")]), OutputFormat::Kitty);
+        assert_eq!(
+            detect(&[("TERM", "xterm-256color"), ("KITTY_WINDOW_ID", "1")]),
+            OutputFormat::Kitty
+        );
+        assert_eq!(
+            detect(&[("TERM", "xterm-256color"), ("TERM_PROGRAM", "iTerm.app")]),
+            OutputFormat::Iterm2
+        );
+        // iTerm2 over ssh
+        assert_eq!(detect(&[("LC_TERMINAL", "iTerm2")]), OutputFormat::Iterm2);
+        assert_eq!(detect(&[("TERM", "foot-extra")]), OutputFormat::Sixel);
+        assert_eq!(detect(&[("TERM", "xterm-256color")]), OutputFormat::Text);
+        assert_eq!(detect(&[]), OutputFormat::Text);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::cell::Cell;
+use crate::color::{self, ColorMode};
+
+/// Appends the cells to `out` as lines of text, with ANSI escapes for the
+/// colors of `color`, growing `out` at most once for the whole output.
+pub(crate) fn write(cells: &[Vec<Cell>], color: ColorMode, out: &mut String) {
+    let escape = color.max_escape_len();
+    let reset = if color == ColorMode::None {
+        ""
+    } else {
+        color::RESET
+    };
+
+    let capacity = cells
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| {
+                    // an escape for the colors, or for going back to the
+                    // default ones
+                    cell.ch.len_utf8() + 2 * escape
+                })
+                .sum::<usize>()
+                + reset.len()
+                + 1
+        })
+        .sum();
+    out.reserve(capacity);
+
+    for row in cells {
+        write_run(row, color, out);
+        out.push('\n');
+    }
+}
+
+/// Appends a run of the cells of a line to `out`, with ANSI escapes for the
+/// colors of `color`, going back to the default colors at the end.
+pub(crate) fn write_run(cells: &[Cell], color: ColorMode, out: &mut String) {
+    // a cell without a color after one with it goes back to the default of
+    // the terminal
+    let (mut fg_set, mut bg_set) = (false, false);
+    for cell in cells {
+        match cell.fg {
+            Some(fg) => color.write_fg_escape(out, fg),
+            None if fg_set => out.push_str(color::DEFAULT_FG),
+            None => {}
+        }
+        match cell.bg {
+            Some(bg) => color.write_bg_escape(out, bg),
+            None if bg_set => out.push_str(color::DEFAULT_BG),
+            None => {}
+        }
+        fg_set = cell.fg.is_some() && color != ColorMode::None;
+        bg_set = cell.bg.is_some() && color != ColorMode::None;
+
+        out.push(cell.ch);
+    }
+    if color != ColorMode::None {
+        out.push_str(color::RESET);
+    }
+}
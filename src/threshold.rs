@@ -0,0 +1,110 @@
+use crate::bitmap::Bitmap;
+use image::GrayImage;
+
+/// The pixels of `luma` of at least `threshold` on; vectorized with the
+/// `simd` feature.
+pub fn threshold_luma(luma: &GrayImage, threshold: i32) -> Bitmap {
+    #[cfg(feature = "simd")]
+    return threshold_luma_simd(luma, threshold);
+
+    #[cfg(not(feature = "simd"))]
+    threshold_luma_scalar(luma, threshold)
+}
+
+/// [`threshold_luma`], a pixel at a time.
+pub fn threshold_luma_scalar(luma: &GrayImage, threshold: i32) -> Bitmap {
+    threshold_rows(luma, threshold, |pixels, threshold, row| {
+        row_words(pixels, row, |chunk| scalar_word(chunk, threshold))
+    })
+}
+
+#[cfg(feature = "simd")]
+fn threshold_luma_simd(luma: &GrayImage, threshold: i32) -> Bitmap {
+    use std::convert::TryInto;
+    use wide::u8x32;
+
+    threshold_rows(luma, threshold, |pixels, threshold, row| {
+        let splat = u8x32::splat(threshold);
+        let mask = |half: &[u8]| {
+            let v = u8x32::new(half.try_into().unwrap());
+            v.simd_ge(splat).to_bitmask() as u64
+        };
+
+        row_words(pixels, row, |chunk| match chunk.len() {
+            64 => mask(&chunk[..32]) | mask(&chunk[32..]) << 32,
+            _ => scalar_word(chunk, threshold),
+        })
+    })
+}
+
+/// Thresholds every row with `row`, given the pixels of the row and the
+/// threshold as a byte; thresholds out of the byte range need no pixels.
+fn threshold_rows<F>(luma: &GrayImage, threshold: i32, row: F) -> Bitmap
+where
+    F: Fn(&[u8], u8, &mut [u64]) + Sync,
+{
+    let (width, height) = luma.dimensions();
+    let mut bitmap = Bitmap::new(width, height);
+    if threshold <= 0 {
+        bitmap.invert();
+        return bitmap;
+    }
+    if threshold > u8::MAX as i32 {
+        return bitmap;
+    }
+
+    let raw: &[u8] = luma;
+    Bitmap::from_rows(width, height, |y, words| {
+        let start = y as usize * width as usize;
+        row(&raw[start..start + width as usize], threshold as u8, words)
+    })
+}
+
+/// Fills every word of a row from its (up to) 64 pixels.
+fn row_words<F>(pixels: &[u8], row: &mut [u64], word: F)
+where
+    F: Fn(&[u8]) -> u64,
+{
+    row.iter_mut()
+        .zip(pixels.chunks(64))
+        .for_each(|(w, chunk)| *w = word(chunk));
+}
+
+fn scalar_word(chunk: &[u8], threshold: u8) -> u64 {
+    chunk
+        .iter()
+        .enumerate()
+        .fold(0, |word, (i, &v)| word | ((v >= threshold) as u64) << i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    fn noise(width: u32, height: u32) -> GrayImage {
+        let mut seed = 0x2545_f491_u32;
+        GrayImage::from_fn(width, height, |_, _| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            Luma([seed as u8])
+        })
+    }
+
+    #[test]
+    fn matches_pixel_by_pixel() {
+        // rows of whole words, and rows ending partway through a word
+        for &(width, height) in &[(128, 3), (131, 5), (7, 2)] {
+            let luma = noise(width, height);
+            for &threshold in &[-5, 0, 1, 97, 128, 255, 256] {
+                let expected = Bitmap::from_fn(width, height, |x, y| {
+                    luma.get_pixel(x, y)[0] as i32 >= threshold
+                });
+
+                assert_eq!(threshold_luma(&luma, threshold), expected);
+                assert_eq!(threshold_luma_scalar(&luma, threshold), expected);
+            }
+        }
+    }
+}
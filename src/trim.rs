@@ -0,0 +1,70 @@
+use crate::cell::Cell;
+
+/// Whether nothing shows in the cell: a space or an empty braille
+/// character, on the background of the terminal.
+pub(crate) fn is_blank(cell: &Cell) -> bool {
+    matches!(cell.ch, ' ' | '\u{2800}') && cell.bg.is_none()
+}
+
+/// `cells` without their blank rows at the top and bottom and blank
+/// columns at the left and right.
+pub(crate) fn trim(cells: Vec<Vec<Cell>>) -> Vec<Vec<Cell>> {
+    let filled = |row: &Vec<Cell>| !row.iter().all(is_blank);
+    let (Some(top), Some(bottom)) = (
+        cells.iter().position(filled),
+        cells.iter().rposition(filled),
+    ) else {
+        return Vec::new();
+    };
+
+    let rows = &cells[top..=bottom];
+    let left = rows
+        .iter()
+        .filter_map(|row| row.iter().position(|cell| !is_blank(cell)))
+        .min()
+        .unwrap_or(0);
+    let right = rows
+        .iter()
+        .filter_map(|row| row.iter().rposition(|cell| !is_blank(cell)))
+        .max()
+        .unwrap_or(0);
+
+    rows.iter()
+        .map(|row| row[left..=right.min(row.len() - 1)].to_vec())
+        .collect()
+}
+
+/// Leaves out the blank cells at the end of every row.
+pub(crate) fn trim_line_ends(cells: &mut [Vec<Cell>]) {
+    for row in cells {
+        let len = row
+            .iter()
+            .rposition(|cell| !is_blank(cell))
+            .map_or(0, |x| x + 1);
+        row.truncate(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(rows: &[&str]) -> Vec<Vec<Cell>> {
+        rows.iter()
+            .map(|row| row.chars().map(Cell::new).collect())
+            .collect()
+    }
+
+    #[test]
+    fn trims_blank_rows_and_columns() {
+        assert_eq!(
+            trim(cells(&["⠀⠀⠀⠀", "⠀⣿⠀⠀", "⠀⠀⣿⠀", "⠀⠀⠀⠀"])),
+            cells(&["⣿⠀", "⠀⣿"])
+        );
+        assert!(trim(cells(&["  ", "  "])).is_empty());
+
+        let mut lines = cells(&["⣿⠀⠀", "⠀⠀⠀", "⠀⣿⠀"]);
+        trim_line_ends(&mut lines);
+        assert_eq!(lines, cells(&["⣿", "", "⠀⣿"]));
+    }
+}
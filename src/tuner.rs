@@ -0,0 +1,152 @@
+use crate::{render, Dither, ImageSize, RenderOptions, RuleExpr};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use image::DynamicImage;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// The dithers cycled through with `d`.
+const DITHERS: [Dither; 5] = [
+    Dither::None,
+    Dither::FloydSteinberg,
+    Dither::Bayer(2),
+    Dither::Bayer(4),
+    Dither::Bayer(8),
+];
+
+const HELP: &str = "+/- threshold  </> reach  d dither  : rule  enter accept  esc cancel";
+
+/// Keeps an image on screen while the rule and dither are adjusted.
+pub struct Tuner<'a> {
+    img: &'a DynamicImage,
+    opts: RenderOptions,
+    /// The rule being typed, after `:`.
+    typing: Option<String>,
+    error: Option<String>,
+}
+
+impl<'a> Tuner<'a> {
+    pub fn new(img: &'a DynamicImage, opts: &RenderOptions) -> Self {
+        Tuner {
+            img,
+            opts: opts.clone(),
+            typing: None,
+            error: None,
+        }
+    }
+
+    /// Takes over the terminal until the user accepts the settings, which
+    /// are returned, or cancels.
+    pub fn run(mut self) -> io::Result<Option<RenderOptions>> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide)?;
+
+        let result = self.event_loop(&mut out);
+
+        execute!(out, Show, LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    fn event_loop<W: Write>(&mut self, out: &mut W) -> io::Result<Option<RenderOptions>> {
+        loop {
+            self.draw(out)?;
+
+            let code = match event::read()? {
+                Event::Key(KeyEvent {
+                    code,
+                    kind: KeyEventKind::Press,
+                    ..
+                }) => code,
+                _ => continue,
+            };
+
+            if self.typing.is_some() {
+                self.type_key(code);
+                continue;
+            }
+
+            self.error = None;
+            match code {
+                KeyCode::Enter | KeyCode::Char('q') => return Ok(Some(self.opts.clone())),
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Char('+') | KeyCode::Char('=') => self.adjust(4),
+                KeyCode::Char('-') => self.adjust(-4),
+                KeyCode::Char('>') | KeyCode::Char('.') => {
+                    self.opts.rule = self.opts.rule.adjusted_reach(1)
+                }
+                KeyCode::Char('<') | KeyCode::Char(',') => {
+                    self.opts.rule = self.opts.rule.adjusted_reach(-1)
+                }
+                KeyCode::Char('d') => {
+                    let next = DITHERS
+                        .iter()
+                        .position(|&d| d == self.opts.dither)
+                        .map_or(0, |i| i + 1);
+                    self.opts.dither = DITHERS[next % DITHERS.len()];
+                }
+                KeyCode::Char(':') => self.typing = Some(String::new()),
+                _ => {}
+            }
+        }
+    }
+
+    fn adjust(&mut self, delta: i32) {
+        self.opts.rule = self.opts.rule.resolve(self.img).adjusted(delta);
+    }
+
+    fn type_key(&mut self, code: KeyCode) {
+        let typed = self.typing.as_mut().unwrap();
+        match code {
+            KeyCode::Char(c) => typed.push(c),
+            KeyCode::Backspace => {
+                typed.pop();
+            }
+            KeyCode::Esc => self.typing = None,
+            KeyCode::Enter => {
+                match RuleExpr::from_str(typed) {
+                    Ok(rule) => self.opts.rule = rule,
+                    Err(e) => self.error = Some(e.to_string()),
+                }
+                self.typing = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn draw<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let (cols, rows) = terminal::size().unwrap_or((80, 24));
+        let (cell_width, cell_height) = self.opts.charset.cell_size();
+
+        // fit the image above the status line
+        let opts = RenderOptions {
+            size: ImageSize::Sized {
+                width: (cols as u32).max(1) * cell_width,
+                height: (rows as u32).saturating_sub(1).max(1) * cell_height,
+            },
+            ..self.opts.clone()
+        };
+        let text = render(self.img, &opts);
+
+        queue!(out, Clear(ClearType::All))?;
+        for (row, line) in text.lines().enumerate() {
+            queue!(out, MoveTo(0, row as u16), Print(line))?;
+        }
+
+        let status = match (&self.typing, &self.error) {
+            (Some(typed), _) => format!(":{}", typed),
+            (None, Some(e)) => e.clone(),
+            (None, None) => format!(
+                "{}  dither: {}  |  {}",
+                self.opts.rule, self.opts.dither, HELP
+            ),
+        };
+        queue!(out, MoveTo(0, rows.saturating_sub(1)), Print(status))?;
+
+        out.flush()
+    }
+}
@@ -0,0 +1,94 @@
+use crate::RenderOptions;
+use image::{DynamicImage, RgbaImage};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{self, Tree};
+use thiserror::Error;
+
+/// Whether `bytes` look like an SVG document, an `<svg` element near their
+/// start.
+pub fn is_svg(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(1024)];
+    String::from_utf8_lossy(head).contains("<svg")
+}
+
+#[derive(Error, Debug)]
+pub enum SvgInputError {
+    #[error("invalid SVG: {0}")]
+    Parse(#[from] usvg::Error),
+    #[error("the SVG is too large to rasterize at {0}x{1}")]
+    TooLarge(u32, u32),
+}
+
+/// Rasterizes the SVG document in `bytes` at the resolution the output of
+/// `opts` asks for, so that it's never resized up: at its own size unless
+/// `opts.size` sets one.
+pub fn rasterize_svg(bytes: &[u8], opts: &RenderOptions) -> Result<DynamicImage, SvgInputError> {
+    let mut usvg_opts = usvg::Options::default();
+    usvg_opts.fontdb_mut().load_system_fonts();
+    let tree = Tree::from_data(bytes, &usvg_opts)?;
+
+    let (svg_width, svg_height) = (tree.size().width(), tree.size().height());
+    let own_size = (
+        (svg_width.ceil() as u32).max(1),
+        (svg_height.ceil() as u32).max(1),
+    );
+    let (width, _) = opts
+        .size
+        .dimensions(own_size, opts.charset.cell_size(), opts.cell_aspect);
+    let scale = width as f32 / svg_width;
+    let (width, height) = (
+        ((svg_width * scale).ceil() as u32).max(1),
+        ((svg_height * scale).ceil() as u32).max(1),
+    );
+
+    let mut pixmap = Pixmap::new(width, height).ok_or(SvgInputError::TooLarge(width, height))?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // tiny-skia keeps the colors premultiplied by their alpha
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|px| {
+            let px = px.demultiply();
+            [px.red(), px.green(), px.blue(), px.alpha()]
+        })
+        .collect();
+
+    Ok(DynamicImage::ImageRgba8(
+        RgbaImage::from_raw(width, height, pixels).unwrap(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageSize;
+    use image::GenericImageView;
+
+    const SQUARE: &[u8] = br#"<?xml version="1.0"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="10" height="20">
+  <rect width="10" height="20" fill="black"/>
+</svg>"#;
+
+    #[test]
+    fn recognizes_svgs() {
+        assert!(is_svg(SQUARE));
+        assert!(!is_svg(b"\x89PNG\r\n\x1a\n"));
+    }
+
+    #[test]
+    fn rasterizes_at_the_requested_width() {
+        let opts = RenderOptions {
+            size: ImageSize::Width(40),
+            ..RenderOptions::default()
+        };
+        let img = rasterize_svg(SQUARE, &opts).unwrap();
+
+        assert_eq!(img.dimensions(), (40, 80));
+        assert_eq!(img.to_rgba8().get_pixel(20, 40).0, [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::animation::Frame;
+use image::{DynamicImage, RgbImage};
+use std::ffi::OsStr;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Extensions of the files decoded as videos.
+const EXTENSIONS: [&str; 6] = ["mp4", "webm", "mkv", "mov", "avi", "m4v"];
+
+/// Whether the file at `path` looks like a video, going by its extension.
+pub fn is_video(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.iter().any(|v| v.eq_ignore_ascii_case(ext)))
+}
+
+#[derive(Error, Debug)]
+pub enum VideoError {
+    #[error("couldn't run ffmpeg: {0}")]
+    Io(#[from] io::Error),
+    #[error("couldn't probe the video stream of `{0}`")]
+    Probe(String),
+}
+
+/// The frames of a video file, decoded by an `ffmpeg` process.
+pub struct Video {
+    width: u32,
+    height: u32,
+    delay: Duration,
+    child: Child,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Video {
+    /// Starts decoding the first video stream of the file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, VideoError> {
+        let path = path.as_ref();
+
+        Video::spawn(
+            &[OsStr::new("-i"), path.as_os_str()],
+            path.display().to_string(),
+        )
+    }
+
+    /// How many frames the video file at `path` has, estimated from the
+    /// duration and the frame rate of its first video stream.
+    pub fn frame_count(path: impl AsRef<Path>) -> Result<usize, VideoError> {
+        let path = path.as_ref();
+        let input = [OsStr::new("-i"), path.as_os_str()];
+        let probe_error = || VideoError::Probe(path.display().to_string());
+        let (_, _, delay) = probe(&input)?.ok_or_else(probe_error)?;
+
+        let output = Command::new("ffprobe")
+            .args(["-v", "error", "-show_entries", "format=duration"])
+            .args(["-of", "csv=p=0"])
+            .args(input)
+            .stdin(Stdio::null())
+            .output()?;
+        let duration = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|duration| *duration >= 0.0 && duration.is_finite())
+            .ok_or_else(probe_error)?;
+
+        Ok((duration / delay.as_secs_f64()).round() as usize)
+    }
+
+    /// Starts capturing frames from a webcam, at `fps` frames per second;
+    /// `device` defaults to the first camera of the platform.
+    pub fn camera(device: Option<&str>, fps: f32) -> Result<Self, VideoError> {
+        let (format, default) = if cfg!(target_os = "macos") {
+            ("avfoundation", "0")
+        } else if cfg!(windows) {
+            ("dshow", "video=0")
+        } else {
+            ("v4l2", "/dev/video0")
+        };
+        let device = device.unwrap_or(default);
+        let rate = fps.to_string();
+
+        let mut video = Video::spawn(
+            &[
+                OsStr::new("-f"),
+                OsStr::new(format),
+                OsStr::new("-framerate"),
+                OsStr::new(&rate),
+                OsStr::new("-i"),
+                OsStr::new(device),
+            ],
+            device.to_string(),
+        )?;
+        video.delay = Duration::from_secs_f64(1.0 / fps as f64);
+
+        Ok(video)
+    }
+
+    /// Runs `ffmpeg` with the `input` arguments, decoding raw RGB frames.
+    fn spawn(input: &[&OsStr], name: String) -> Result<Self, VideoError> {
+        let (width, height, delay) = probe(input)?.ok_or(VideoError::Probe(name))?;
+
+        let mut child = Command::new("ffmpeg")
+            .args(["-loglevel", "error"])
+            .args(input)
+            .args(["-map", "0:v:0", "-f", "rawvideo", "-pix_fmt", "rgb24", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        Ok(Video {
+            width,
+            height,
+            delay,
+            child,
+            stdout,
+        })
+    }
+}
+
+/// The width, height and frame delay of the first video stream of `input`.
+fn probe(input: &[&OsStr]) -> io::Result<Option<(u32, u32, Duration)>> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args(["-show_entries", "stream=width,height,r_frame_rate"])
+        .args(["-of", "csv=p=0"])
+        .args(input)
+        .stdin(Stdio::null())
+        .output()?;
+
+    Ok(parse_probe(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the `ffprobe` output for a stream, e.g. `1920,1080,30000/1001`.
+fn parse_probe(s: &str) -> Option<(u32, u32, Duration)> {
+    let mut fields = s.trim().split(',');
+    let width = fields.next()?.parse().ok()?;
+    let height = fields.next()?.parse().ok()?;
+    let (num, den) = fields.next()?.split_once('/')?;
+    let fps = num.parse::<f64>().ok()? / den.parse::<f64>().ok()?;
+    if !(fps > 0.0 && fps.is_finite()) {
+        return None;
+    }
+
+    Some((width, height, Duration::from_secs_f64(1.0 / fps)))
+}
+
+impl Iterator for Video {
+    type Item = io::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = vec![0; self.width as usize * self.height as usize * 3];
+        match self.stdout.read_exact(&mut buf) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return None,
+            Err(e) => return Some(Err(e)),
+        }
+
+        let image = RgbImage::from_raw(self.width, self.height, buf).unwrap();
+        Some(Ok(Frame {
+            image: DynamicImage::ImageRgb8(image),
+            delay: self.delay,
+        }))
+    }
+}
+
+impl Drop for Video {
+    fn drop(&mut self) {
+        // stop decoding when playback ends early
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,216 @@
+use crate::screen::{Anchor, Screen};
+use crate::{
+    detect_synchronized_output, render_cells, ImageSize, OnOffRule, RenderOptions, RuleExpr,
+};
+use crossterm::cursor::{Hide, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use image::{DynamicImage, GenericImageView};
+use std::io::{self, Write};
+
+/// The rules cycled through with `r`, after the initial one.
+const RULES: [OnOffRule; 6] = [
+    OnOffRule::LumaThreshold(128),
+    OnOffRule::Otsu,
+    OnOffRule::Adaptive {
+        window: 15,
+        c: 5,
+        kernel: crate::AdaptiveKernel::Mean,
+    },
+    OnOffRule::Sobel(128),
+    OnOffRule::Canny(50, 100),
+    OnOffRule::PxThreshold(384),
+];
+
+const ZOOM_STEP: f32 = 1.25;
+const MAX_ZOOM: f32 = 16.0;
+
+const HELP: &str = "+/- threshold  [/] zoom  arrows pan  i invert  r rule  q quit";
+
+/// Full screen, interactive view of an image.
+pub struct Viewer<'a> {
+    img: &'a DynamicImage,
+    opts: RenderOptions,
+    rules: Vec<RuleExpr>,
+    rule: usize,
+    zoom: f32,
+    /// Top left corner of the view, in pixels of the zoomed image.
+    pan: (u32, u32),
+    /// The image resized for the current zoom and terminal size, kept
+    /// around so that panning and changing the rule don't resize it again.
+    zoomed: Option<DynamicImage>,
+    screen: Screen,
+}
+
+impl<'a> Viewer<'a> {
+    pub fn new(img: &'a DynamicImage, opts: &RenderOptions) -> Self {
+        let mut rules = vec![opts.rule.clone()];
+        rules.extend(
+            RULES
+                .iter()
+                .map(|&rule| RuleExpr::from(rule))
+                .filter(|rule| *rule != opts.rule),
+        );
+
+        Viewer {
+            img,
+            opts: RenderOptions {
+                size: ImageSize::Default,
+                ..opts.clone()
+            },
+            rules,
+            rule: 0,
+            zoom: 1.0,
+            pan: (0, 0),
+            zoomed: None,
+            screen: Screen::new(opts.color, Anchor::Home, detect_synchronized_output()),
+        }
+    }
+
+    /// Takes over the terminal until the user quits.
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide)?;
+
+        let result = self.event_loop(&mut out);
+
+        execute!(out, Show, LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    fn event_loop<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        loop {
+            self.draw(out)?;
+
+            // only redraw for the events that change something
+            loop {
+                match event::read()? {
+                    Event::Key(KeyEvent {
+                        code,
+                        kind: KeyEventKind::Press,
+                        ..
+                    }) => match self.handle(code) {
+                        Some(true) => break,
+                        Some(false) => return Ok(()),
+                        None => {}
+                    },
+                    Event::Resize(..) => {
+                        self.zoomed = None;
+                        self.screen.clear();
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Applies a key press; `Some(true)` when the view needs to be drawn
+    /// again, `Some(false)` to quit.
+    fn handle(&mut self, code: KeyCode) -> Option<bool> {
+        let (cell_width, cell_height) = self.opts.charset.cell_size();
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(false),
+            KeyCode::Char('+') | KeyCode::Char('=') => self.adjust(4),
+            KeyCode::Char('-') => self.adjust(-4),
+            KeyCode::Char(']') => self.set_zoom(self.zoom * ZOOM_STEP),
+            KeyCode::Char('[') => self.set_zoom(self.zoom / ZOOM_STEP),
+            KeyCode::Left | KeyCode::Char('h') => self.pan_by(-4 * cell_width as i32, 0),
+            KeyCode::Right | KeyCode::Char('l') => self.pan_by(4 * cell_width as i32, 0),
+            KeyCode::Up | KeyCode::Char('k') => self.pan_by(0, -2 * cell_height as i32),
+            KeyCode::Down | KeyCode::Char('j') => self.pan_by(0, 2 * cell_height as i32),
+            KeyCode::Char('i') => self.opts.invert = !self.opts.invert,
+            KeyCode::Char('r') => {
+                self.rule = (self.rule + 1) % self.rules.len();
+                self.opts.rule = self.rules[self.rule].clone();
+            }
+            _ => return None,
+        }
+
+        Some(true)
+    }
+
+    fn adjust(&mut self, delta: i32) {
+        let rule = self.rules[self.rule].resolve(self.img).adjusted(delta);
+        self.rules[self.rule] = rule.clone();
+        self.opts.rule = rule;
+    }
+
+    fn set_zoom(&mut self, zoom: f32) {
+        let zoom = zoom.clamp(1.0, MAX_ZOOM);
+        if zoom == self.zoom {
+            return;
+        }
+
+        // keep the center of the view in place
+        let (w, h) = self.view_pixels();
+        let ratio = zoom / self.zoom;
+        let center =
+            |pan: u32, size: u32| ((pan + size / 2) as f32 * ratio) as i32 - size as i32 / 2;
+        let (cx, cy) = (center(self.pan.0, w), center(self.pan.1, h));
+
+        self.zoom = zoom;
+        self.zoomed = None;
+        self.pan = (cx.max(0) as u32, cy.max(0) as u32);
+    }
+
+    fn pan_by(&mut self, dx: i32, dy: i32) {
+        self.pan = (
+            (self.pan.0 as i32 + dx).max(0) as u32,
+            (self.pan.1 as i32 + dy).max(0) as u32,
+        );
+    }
+
+    /// Size, in pixels, of the part of the image on screen, the last row
+    /// being the status line.
+    fn view_pixels(&self) -> (u32, u32) {
+        let (cols, rows) = terminal::size().unwrap_or((80, 24));
+        let (cell_width, cell_height) = self.opts.charset.cell_size();
+
+        (
+            (cols as u32).max(1) * cell_width,
+            (rows as u32).saturating_sub(1).max(1) * cell_height,
+        )
+    }
+
+    fn zoomed(&mut self) -> &DynamicImage {
+        let (w, h) = self.view_pixels();
+        let (img, zoom, filter) = (self.img, self.zoom, self.opts.filter);
+
+        self.zoomed.get_or_insert_with(|| {
+            // at zoom 1 the whole image fits on screen
+            let fit = (w as f32 / img.width() as f32).min(h as f32 / img.height() as f32);
+            let scale = fit * zoom;
+            let width = ((img.width() as f32 * scale).round() as u32).max(1);
+            let height = ((img.height() as f32 * scale).round() as u32).max(1);
+
+            img.resize_exact(width, height, filter.into())
+        })
+    }
+
+    fn draw<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let (w, h) = self.view_pixels();
+        let (zw, zh) = self.zoomed().dimensions();
+
+        // panning stops at the edges of the image
+        self.pan = (
+            self.pan.0.min(zw.saturating_sub(w)),
+            self.pan.1.min(zh.saturating_sub(h)),
+        );
+        let (x, y) = self.pan;
+        let view = self.zoomed().crop_imm(x, y, w.min(zw), h.min(zh));
+
+        let cells = render_cells(&view, &self.opts);
+        let status = format!(
+            "{}  {:.1}x{}  |  {}",
+            self.opts.rule,
+            self.zoom,
+            if self.opts.invert { "  inverted" } else { "" },
+            HELP
+        );
+        self.screen.draw_with_footer(out, cells, &status)
+    }
+}
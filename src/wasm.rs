@@ -0,0 +1,79 @@
+use crate::{Preprocess, RenderOptions};
+use js_sys::Reflect;
+use std::fmt::Display;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// Renders the image encoded in `bytes`, in any format the `image` crate
+/// reads, with the options of `opts`: an object with the keys of the config
+/// file, `edges`, `format` and `invert`, all optional, like
+///
+/// ```js
+/// render(bytes, { rule: "Otsu", size: "80x_", dither: "floyd-steinberg", invert: true })
+/// ```
+///
+/// Throws the error message when the image can't be decoded or an option is
+/// invalid; PNG output falls back to plain text, as with [`crate::render`].
+#[wasm_bindgen]
+pub fn render(bytes: &[u8], opts: JsValue) -> Result<String, JsValue> {
+    let img = image::load_from_memory(bytes).map_err(|e| e.to_string())?;
+    let opts = options(&opts)?;
+
+    Ok(crate::render(&img, &opts))
+}
+
+fn options(opts: &JsValue) -> Result<RenderOptions, JsValue> {
+    let defaults = RenderOptions::default();
+
+    Ok(RenderOptions {
+        size: parse(opts, "size")?.unwrap_or(defaults.size),
+        rule: parse(opts, "rule")?.unwrap_or(defaults.rule),
+        preprocess: Preprocess {
+            contrast: get(opts, "contrast")?
+                .map(|v| {
+                    v.as_f64()
+                        .ok_or_else(|| invalid("contrast", "not a number"))
+                })
+                .transpose()?
+                .map_or(defaults.preprocess.contrast, |c| c as f32),
+            ..defaults.preprocess
+        },
+        color: parse(opts, "color")?.unwrap_or(defaults.color),
+        dither: parse(opts, "dither")?.unwrap_or(defaults.dither),
+        charset: parse(opts, "charset")?.unwrap_or(defaults.charset),
+        edges: parse(opts, "edges")?.unwrap_or(defaults.edges),
+        format: parse(opts, "format")?.unwrap_or(defaults.format),
+        invert: get(opts, "invert")?.map_or(defaults.invert, |v| v.is_truthy()),
+        ..defaults
+    })
+}
+
+/// The value of `key`, `None` when `opts` is `undefined` or `null`, or the
+/// key is missing.
+fn get(opts: &JsValue, key: &str) -> Result<Option<JsValue>, JsValue> {
+    if opts.is_undefined() || opts.is_null() {
+        return Ok(None);
+    }
+
+    let value = Reflect::get(opts, &JsValue::from_str(key))?;
+    Ok(Some(value).filter(|v| !v.is_undefined() && !v.is_null()))
+}
+
+/// The string of `key`, parsed the way the command line parses it.
+fn parse<T: FromStr>(opts: &JsValue, key: &'static str) -> Result<Option<T>, JsValue>
+where
+    T::Err: Display,
+{
+    get(opts, key)?
+        .map(|value| {
+            let value = value
+                .as_string()
+                .ok_or_else(|| invalid(key, "not a string"))?;
+            T::from_str(&value).map_err(|e| invalid(key, e))
+        })
+        .transpose()
+}
+
+fn invalid(key: &str, e: impl Display) -> JsValue {
+    JsValue::from_str(&format!("invalid `{}`: {}", key, e))
+}
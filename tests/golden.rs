@@ -0,0 +1,110 @@
+//! Renders the images in `tests/fixtures` with every rule at several sizes,
+//! and compares the braille output with the files in `tests/golden`.
+//! The resized renders follow the filters of `image`, pinned to an exact
+//! version among the dev-dependencies for them to stay the same.
+//!
+//! After a change that is meant to change the output, regenerate the files
+//! with `UPDATE_GOLDEN=1 cargo test --test golden` and review the diff.
+
+use asciidraw::{render, AdaptiveKernel, ImageSize, OnOffRule, RenderOptions, RuleExpr};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const FIXTURES: [&str; 2] = ["gradient", "shapes"];
+
+const SIZES: [&str; 5] = ["_", "32x_", "_x20", "40x40", "150%"];
+
+fn rules() -> Vec<OnOffRule> {
+    vec![
+        OnOffRule::PxThreshold(384),
+        OnOffRule::InvertedPxThreshold(384),
+        OnOffRule::Border(30, 2),
+        OnOffRule::LumaThreshold(128),
+        OnOffRule::Otsu,
+        OnOffRule::Adaptive {
+            window: 7,
+            c: 5,
+            kernel: AdaptiveKernel::Mean,
+        },
+        OnOffRule::Adaptive {
+            window: 7,
+            c: 5,
+            kernel: AdaptiveKernel::Gaussian,
+        },
+        OnOffRule::Sobel(100),
+        OnOffRule::Canny(50, 100),
+    ]
+}
+
+fn size(s: &str) -> ImageSize {
+    match s.strip_suffix('%') {
+        Some(_) => ImageSize::parse_scale(s).unwrap(),
+        None => s.parse().unwrap(),
+    }
+}
+
+/// `Border(30,2)` as `border-30-2`.
+fn file_name(rule: &OnOffRule) -> String {
+    let name = rule
+        .to_string()
+        .to_lowercase()
+        .replace(|c: char| !c.is_ascii_alphanumeric(), "-");
+
+    format!("{}.txt", name.trim_end_matches('-'))
+}
+
+fn dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(name)
+}
+
+/// The renders of `img` with `rule` at every size, each under a header.
+fn renders(img: &image::DynamicImage, rule: &OnOffRule) -> String {
+    SIZES
+        .iter()
+        .map(|s| {
+            let opts = RenderOptions {
+                size: size(s),
+                rule: RuleExpr::from(*rule),
+                ..RenderOptions::default()
+            };
+            format!("--- size {} ---\n{}", s, render(img, &opts))
+        })
+        .collect()
+}
+
+#[test]
+fn braille_matches_golden_files() {
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    let mut mismatches = Vec::new();
+
+    for fixture in FIXTURES.iter() {
+        let img = image::open(dir("fixtures").join(format!("{}.png", fixture))).unwrap();
+        let golden_dir = dir("golden").join(fixture);
+
+        for rule in rules() {
+            let path = golden_dir.join(file_name(&rule));
+            let actual = renders(&img, &rule);
+
+            if update {
+                fs::create_dir_all(&golden_dir).unwrap();
+                fs::write(&path, actual).unwrap();
+                continue;
+            }
+
+            match fs::read_to_string(&path) {
+                Ok(expected) if expected == actual => {}
+                _ => mismatches.push(path.display().to_string()),
+            }
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "renders differ from (or are missing) the golden files, rerun with \
+         UPDATE_GOLDEN=1 if the change is intended:\n{}",
+        mismatches.join("\n")
+    );
+}